@@ -0,0 +1,52 @@
+//! Benchmark compiling a datapack with many functions.
+//!
+//! Run with `cargo bench --bench compile` and with `--features parallel` to compare
+//! the sequential and the concurrent compilation of namespaces.
+
+use std::{hint::black_box, time::Instant};
+
+use shulkerbox::prelude::*;
+
+const NAMESPACES: usize = 10;
+const FUNCTIONS_PER_NAMESPACE: usize = 100;
+const ITERATIONS: u32 = 20;
+
+fn build_datapack() -> Datapack {
+    let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+    for namespace in 0..NAMESPACES {
+        let namespace = dp.namespace_mut(&format!("bench_{namespace}"));
+        for function in 0..FUNCTIONS_PER_NAMESPACE {
+            let function = namespace.function_mut(&format!("function_{function}"));
+            for i in 0..20 {
+                function.add_command(format!("say {i}").as_str());
+            }
+            function.add_command(Command::Execute(Execute::If(
+                Condition::from("entity @s[tag=a]") | Condition::from("entity @s[tag=b]"),
+                Box::new(Execute::Runs(vec!["say then".into(), "say again".into()])),
+                Some(Box::new(Execute::Run(Box::new("say else".into())))),
+            )));
+        }
+    }
+    dp
+}
+
+fn main() {
+    let dp = build_datapack();
+    let options = CompileOptions::default();
+
+    // warm up
+    black_box(dp.compile(&options));
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(dp.compile(&options));
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "compiled {} functions in {:?} per iteration (parallel: {})",
+        NAMESPACES * FUNCTIONS_PER_NAMESPACE,
+        elapsed / ITERATIONS,
+        cfg!(feature = "parallel")
+    );
+}
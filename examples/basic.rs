@@ -21,16 +21,16 @@ fn main() {
     // add the call command to the function "bar"
     bar_function.add_command(call_func);
     // add a complex command to the function "bar"
-    bar_function.add_command(Command::Execute(Execute::As(
-        "@a".to_string(),
-        Box::new(Execute::If(
-            Condition::from("block ~ ~ ~ minecraft:stone")
-                | !(!Condition::from("block ~ ~1 ~ minecraft:stone")
-                    | "block ~ ~-1 ~ minecraft:stone".into()),
-            Box::new(Execute::Run(Box::new("say bar".into()))),
-            None,
-        )),
-    )));
+    bar_function.add_command(Command::Execute(
+        Execute::builder()
+            .as_("@a")
+            .if_cond(
+                Condition::from("block ~ ~ ~ minecraft:stone")
+                    | !(!Condition::from("block ~ ~1 ~ minecraft:stone")
+                        | "block ~ ~-1 ~ minecraft:stone".into()),
+            )
+            .run("say bar"),
+    ));
 
     dp.add_load("test:foo");
 
@@ -0,0 +1,256 @@
+//! Advancements that run rewards when their criteria are met.
+
+use std::collections::BTreeMap;
+
+use getset::{CopyGetters, Getters};
+use serde_json::{json, Map, Value};
+
+use crate::virtual_fs::VFile;
+
+/// Advancement with criteria that have to be met to grant it.
+///
+/// Grant it with [`Command::advancement_grant`](super::Command::advancement_grant) and the id of the advancement.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+pub struct Advancement {
+    /// Id of the parent advancement
+    #[get = "pub"]
+    parent: Option<String>,
+    /// Criteria of the advancement by name
+    #[get = "pub"]
+    criteria: BTreeMap<String, Criterion>,
+    /// Groups of criterion names, of which at least one criterion per group has to be met
+    #[get = "pub"]
+    requirements: Vec<Vec<String>>,
+    /// Rewards granted with the advancement
+    #[get = "pub"]
+    rewards: AdvancementRewards,
+}
+
+impl Advancement {
+    /// Create a new advancement without criteria.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the id of the parent advancement.
+    #[must_use]
+    pub fn with_parent(self, parent: &str) -> Self {
+        Self {
+            parent: Some(parent.to_string()),
+            ..self
+        }
+    }
+
+    /// Add the criterion with the name.
+    #[must_use]
+    pub fn with_criterion(mut self, name: &str, criterion: Criterion) -> Self {
+        self.add_criterion(name, criterion);
+        self
+    }
+
+    /// Set the requirements as groups of criterion names.
+    ///
+    /// Without requirements, all criteria have to be met.
+    #[must_use]
+    pub fn with_requirements(self, requirements: Vec<Vec<String>>) -> Self {
+        Self {
+            requirements,
+            ..self
+        }
+    }
+
+    /// Set the rewards granted with the advancement.
+    #[must_use]
+    pub fn with_rewards(self, rewards: AdvancementRewards) -> Self {
+        Self { rewards, ..self }
+    }
+
+    /// Add the criterion with the name, replacing a criterion with the same name.
+    pub fn add_criterion(&mut self, name: &str, criterion: Criterion) {
+        self.criteria.insert(name.to_string(), criterion);
+    }
+
+    /// Get the JSON representation of the advancement.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        let mut json = Map::new();
+        if let Some(parent) = &self.parent {
+            json.insert("parent".to_string(), json!(parent));
+        }
+        json.insert(
+            "criteria".to_string(),
+            self.criteria
+                .iter()
+                .map(|(name, criterion)| (name.clone(), criterion.to_json()))
+                .collect(),
+        );
+        if !self.requirements.is_empty() {
+            json.insert("requirements".to_string(), json!(self.requirements));
+        }
+        if !self.rewards.is_empty() {
+            json.insert("rewards".to_string(), self.rewards.to_json());
+        }
+        Value::Object(json)
+    }
+
+    /// Compile the advancement into a virtual file.
+    #[must_use]
+    pub fn compile(&self) -> VFile {
+        VFile::Text(self.to_json().to_string())
+    }
+}
+
+/// Criterion of an [`Advancement`], met when the trigger fires and its conditions pass.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct Criterion {
+    /// Trigger of the criterion, e.g. `minecraft:tick`
+    #[get = "pub"]
+    trigger: String,
+    /// Conditions of the trigger given as JSON object
+    #[get = "pub"]
+    conditions: Option<Value>,
+}
+
+impl Criterion {
+    /// Create a new criterion with the trigger and without conditions.
+    #[must_use]
+    pub fn new(trigger: &str) -> Self {
+        Self {
+            trigger: trigger.to_string(),
+            conditions: None,
+        }
+    }
+
+    /// Set the conditions of the trigger.
+    #[must_use]
+    pub fn with_conditions(self, conditions: Value) -> Self {
+        Self {
+            conditions: Some(conditions),
+            ..self
+        }
+    }
+
+    /// Get the JSON representation of the criterion.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        let mut json = json!({ "trigger": self.trigger });
+        if let Some(conditions) = &self.conditions {
+            json["conditions"] = conditions.clone();
+        }
+        json
+    }
+}
+
+/// Rewards of an [`Advancement`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters, CopyGetters)]
+pub struct AdvancementRewards {
+    /// Id of the function run as the player
+    #[get = "pub"]
+    function: Option<String>,
+    /// Ids of the loot tables given to the player
+    #[get = "pub"]
+    loot: Vec<String>,
+    /// Experience points given to the player
+    #[get_copy = "pub"]
+    experience: Option<i32>,
+}
+
+impl AdvancementRewards {
+    /// Create new, empty rewards.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the id of the function run as the player.
+    #[must_use]
+    pub fn with_function(self, function: &str) -> Self {
+        Self {
+            function: Some(function.to_string()),
+            ..self
+        }
+    }
+
+    /// Add the id of a loot table given to the player.
+    #[must_use]
+    pub fn with_loot(mut self, loot_table: &str) -> Self {
+        self.loot.push(loot_table.to_string());
+        self
+    }
+
+    /// Set the experience points given to the player.
+    #[must_use]
+    pub fn with_experience(self, experience: i32) -> Self {
+        Self {
+            experience: Some(experience),
+            ..self
+        }
+    }
+
+    /// Check whether no rewards are set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.function.is_none() && self.loot.is_empty() && self.experience.is_none()
+    }
+
+    /// Get the JSON representation of the rewards.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        let mut json = Map::new();
+        if let Some(function) = &self.function {
+            json.insert("function".to_string(), json!(function));
+        }
+        if !self.loot.is_empty() {
+            json.insert("loot".to_string(), json!(self.loot));
+        }
+        if let Some(experience) = self.experience {
+            json.insert("experience".to_string(), json!(experience));
+        }
+        Value::Object(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        let advancement =
+            Advancement::new().with_criterion("tick", Criterion::new("minecraft:tick"));
+        assert_eq!(
+            advancement.compile().as_text(),
+            Some(r#"{"criteria":{"tick":{"trigger":"minecraft:tick"}}}"#)
+        );
+
+        let advancement = advancement
+            .with_parent("foo:root")
+            .with_criterion(
+                "sneak",
+                Criterion::new("minecraft:location")
+                    .with_conditions(json!({ "player": { "flags": { "is_sneaking": true } } })),
+            )
+            .with_requirements(vec![vec!["tick".to_string(), "sneak".to_string()]])
+            .with_rewards(
+                AdvancementRewards::new()
+                    .with_function("foo:reward")
+                    .with_loot("foo:chest")
+                    .with_experience(10),
+            );
+        let json = advancement.to_json();
+        assert_eq!(json["parent"], "foo:root");
+        assert_eq!(
+            json["criteria"]["sneak"]["conditions"]["player"]["flags"]["is_sneaking"],
+            true
+        );
+        assert_eq!(json["requirements"], json!([["tick", "sneak"]]));
+        assert_eq!(
+            json["rewards"],
+            json!({ "function": "foo:reward", "loot": ["foo:chest"], "experience": 10 })
+        );
+    }
+}
@@ -1,36 +1,83 @@
-use std::ops::{BitAnd, BitOr, Not, RangeInclusive};
+use std::{
+    ops::{BitAnd, BitOr, Bound, Not, RangeBounds, RangeInclusive},
+    sync::PoisonError,
+};
 
-use chksum_md5 as md5;
+use getset::{CopyGetters, Getters};
 
-use super::Command;
+use super::{require_format, unique_hash, Command};
 use crate::util::{
-    compile::{CompileOptions, FunctionCompilerState, MutCompilerState},
+    compile::{report, CompileOptions, DiagnosticKind, FunctionCompilerState, MutCompilerState},
+    tokenize::split_words,
     ExtendableQueue,
 };
 
 /// Execute command with all its variants.
+///
+/// To run multiple commands atomically under the same execute context, run a
+/// [`Command::Group`] with [`Execute::Run`]. The group is compiled into a single
+/// generated function, so the execute command compiles to one `run function` line.
 #[allow(missing_docs)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Execute {
-    Align(String, Box<Execute>),
-    Anchored(String, Box<Execute>),
-    As(String, Box<Execute>),
-    At(String, Box<Execute>),
-    AsAt(String, Box<Execute>),
-    Facing(String, Box<Execute>),
-    In(String, Box<Execute>),
-    On(String, Box<Execute>),
-    Positioned(String, Box<Execute>),
-    Rotated(String, Box<Execute>),
-    Store(String, Box<Execute>),
-    Summon(String, Box<Execute>),
-    If(Condition, Box<Execute>, Option<Box<Execute>>),
+    Align(String, Box<Self>),
+    Anchored(String, Box<Self>),
+    As(String, Box<Self>),
+    At(String, Box<Self>),
+    AsAt(String, Box<Self>),
+    Facing(String, Box<Self>),
+    In(String, Box<Self>),
+    On(String, Box<Self>),
+    Positioned(String, Box<Self>),
+    Rotated(String, Box<Self>),
+    Store(String, Box<Self>),
+    Summon(String, Box<Self>),
+    If(Condition, Box<Self>, Option<Box<Self>>),
+    IfCached(CachedCondition, Box<Self>, Option<Box<Self>>),
     Run(Box<Command>),
     Runs(Vec<Command>),
 }
 
 impl Execute {
+    /// Create a new builder for an execute command.
+    #[must_use]
+    pub fn builder() -> ExecuteBuilder {
+        ExecuteBuilder::default()
+    }
+
+    /// Create an `align` subcommand.
+    ///
+    /// Returns `None` if the axes are not a non-empty combination of `x`, `y` and `z` without repeats.
+    #[must_use]
+    pub fn align(axes: &str, next: Self) -> Option<Self> {
+        is_valid_axes(axes).then(|| Self::Align(axes.to_string(), Box::new(next)))
+    }
+
+    /// Create a `store` subcommand storing the result or success of the following part in the target.
+    #[must_use]
+    pub fn store(target: &StoreTarget, next: Self) -> Self {
+        Self::Store(target.compile(), Box::new(next))
+    }
+
+    /// Create an `anchored` subcommand setting the anchor of the following part.
+    #[must_use]
+    pub fn anchored(anchor: Anchor, next: Self) -> Self {
+        Self::Anchored(anchor.name().to_string(), Box::new(next))
+    }
+
+    /// Create a `facing` subcommand rotating the following part towards the target.
+    #[must_use]
+    pub fn facing(target: &FacingTarget, next: Self) -> Self {
+        Self::Facing(target.compile(), Box::new(next))
+    }
+
+    /// Create an `on` subcommand selecting the entities in the relation to the current entity.
+    #[must_use]
+    pub fn on(relation: OnRelation, next: Self) -> Self {
+        Self::On(relation.name().to_string(), Box::new(next))
+    }
+
     /// Compile the execute command into a list of strings.
     pub fn compile(
         &self,
@@ -91,7 +138,16 @@ impl Execute {
                 function_state,
             ),
             Self::If(cond, then, el) => compile_if_cond(
-                cond,
+                cond.compile(options, global_state, function_state),
+                then.as_ref(),
+                el.as_deref(),
+                &prefix,
+                options,
+                global_state,
+                function_state,
+            ),
+            Self::IfCached(cond, then, el) => compile_if_cond(
+                cond.compile(options, global_state, function_state),
                 then.as_ref(),
                 el.as_deref(),
                 &prefix,
@@ -134,20 +190,142 @@ impl Execute {
     }
 
     /// Get the count of the commands the execute command will compile into.
-    #[tracing::instrument(skip(options))]
+    ///
+    /// The count is computed from the structure of the command without compiling it,
+    /// so it is cheap even for deeply nested commands.
+    #[tracing::instrument(level = "trace", skip(options))]
     pub(super) fn get_count(&self, options: &CompileOptions) -> usize {
+        self.count_internal(false, options)
+    }
+
+    /// Get the number of strings [`Execute::compile_internal`] returns, without compiling the command.
+    fn count_internal(&self, require_grouping: bool, options: &CompileOptions) -> usize {
+        match self {
+            Self::Align(_, next)
+            | Self::Anchored(_, next)
+            | Self::As(_, next)
+            | Self::At(_, next)
+            | Self::AsAt(_, next)
+            | Self::Facing(_, next)
+            | Self::In(_, next)
+            | Self::On(_, next)
+            | Self::Positioned(_, next)
+            | Self::Rotated(_, next)
+            | Self::Store(_, next) => next.count_internal(require_grouping, options),
+            Self::Summon(_, next) => next.count_internal(true, options),
+            Self::If(cond, then, el) => {
+                count_if_cond(cond.compiled_len(), then, el.as_deref(), options)
+            }
+            Self::IfCached(cond, then, el) => {
+                count_if_cond(cond.compiled_len(), then, el.as_deref(), options)
+            }
+            Self::Run(command) => match &**command {
+                Command::Execute(ex) => ex.count_internal(require_grouping, options),
+                command => command.compiled_len(options),
+            },
+            Self::Runs(commands) if !require_grouping => {
+                commands.iter().map(|cmd| cmd.compiled_len(options)).sum()
+            }
+            Self::Runs(commands) => Command::Group(commands.clone()).compiled_len(options),
+        }
+    }
+
+    /// Describe what the execute command will compile into without generating any files.
+    ///
+    /// The helper function paths are predicted as if the execute command was the first command
+    /// of the compilation that requires a generated function, placed in the function at `function_path`.
+    /// Prefix the path with the namespace of the function, e.g. `ns:foo`, to predict the hashed helper names
+    /// of that namespace.
+    #[must_use]
+    pub fn explain(&self, options: &CompileOptions, function_path: &str) -> ExecutePlan {
         let global_state = MutCompilerState::default();
-        let function_state =
-            FunctionCompilerState::new("[INTERNAL]", "[INTERNAL]", ExtendableQueue::default());
+        let queue = ExtendableQueue::default();
+        let (namespace, function_path) = function_path
+            .split_once(':')
+            .unwrap_or(("[INTERNAL]", function_path));
+        let function_state = FunctionCompilerState::new(function_path, namespace, queue.clone());
 
-        self.compile_internal(
-            String::new(),
-            false,
-            options,
-            &global_state,
-            &function_state,
-        )
-        .len()
+        let line_count = self.compile(options, &global_state, &function_state).len();
+        let generated_functions = global_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take_generated_functions();
+        let helper_functions = queue
+            .chain(generated_functions)
+            .map(|(path, _)| path)
+            .collect();
+
+        let mut reasons = Vec::new();
+        self.collect_grouping_reasons(options, &mut reasons);
+
+        ExecutePlan {
+            line_count,
+            helper_functions,
+            reasons,
+        }
+    }
+
+    /// Collect the reasons why parts of the execute command need to be grouped.
+    fn collect_grouping_reasons(
+        &self,
+        options: &CompileOptions,
+        reasons: &mut Vec<GroupingReason>,
+    ) {
+        match self {
+            Self::Align(_, next)
+            | Self::Anchored(_, next)
+            | Self::As(_, next)
+            | Self::At(_, next)
+            | Self::AsAt(_, next)
+            | Self::Facing(_, next)
+            | Self::In(_, next)
+            | Self::On(_, next)
+            | Self::Positioned(_, next)
+            | Self::Rotated(_, next)
+            | Self::Store(_, next)
+            | Self::Summon(_, next) => next.collect_grouping_reasons(options, reasons),
+            Self::If(cond, then, el) => {
+                collect_if_reasons(cond.compiled_len(), then, el.as_deref(), options, reasons);
+            }
+            Self::IfCached(cond, then, el) => {
+                collect_if_reasons(cond.compiled_len(), then, el.as_deref(), options, reasons);
+            }
+            Self::Run(cmd) => {
+                if let Command::Execute(ex) = cmd.as_ref() {
+                    ex.collect_grouping_reasons(options, reasons);
+                }
+            }
+            Self::Runs(cmds) => {
+                for cmd in cmds {
+                    if let Command::Execute(ex) = cmd {
+                        ex.collect_grouping_reasons(options, reasons);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replace the next part of the execute command.
+    /// Run commands do not have a next part and are returned unchanged.
+    fn with_next(self, next: Self) -> Self {
+        let next = Box::new(next);
+        match self {
+            Self::Align(arg, _) => Self::Align(arg, next),
+            Self::Anchored(arg, _) => Self::Anchored(arg, next),
+            Self::As(arg, _) => Self::As(arg, next),
+            Self::At(arg, _) => Self::At(arg, next),
+            Self::AsAt(arg, _) => Self::AsAt(arg, next),
+            Self::Facing(arg, _) => Self::Facing(arg, next),
+            Self::In(arg, _) => Self::In(arg, next),
+            Self::On(arg, _) => Self::On(arg, next),
+            Self::Positioned(arg, _) => Self::Positioned(arg, next),
+            Self::Rotated(arg, _) => Self::Rotated(arg, next),
+            Self::Store(arg, _) => Self::Store(arg, next),
+            Self::Summon(arg, _) => Self::Summon(arg, next),
+            Self::If(cond, _, el) => Self::If(cond, next, el),
+            Self::IfCached(cond, _, el) => Self::IfCached(cond, next, el),
+            Self::Run(_) | Self::Runs(_) => self,
+        }
     }
 
     /// Get the variant name of the execute command.
@@ -166,7 +344,7 @@ impl Execute {
             Self::Rotated(..) => "rotated",
             Self::Store(..) => "store",
             Self::Summon(..) => "summon",
-            Self::If(..) => "if",
+            Self::If(..) | Self::IfCached(..) => "if",
             Self::Run(..) => "run",
             Self::Runs(..) => "runs",
         }
@@ -175,9 +353,25 @@ impl Execute {
     /// Check whether the execute command is valid with the given pack format.
     #[must_use]
     pub fn validate(&self, pack_formats: &RangeInclusive<u8>) -> bool {
+        self.incompatibilities(pack_formats).is_empty()
+    }
+
+    /// Get the reasons why the execute command is not valid with the given pack formats.
+    #[must_use]
+    pub fn incompatibilities(&self, pack_formats: &RangeInclusive<u8>) -> Vec<String> {
+        let subcommand = |min_format| {
+            require_format(
+                &format!("subcommand `{}`", self.variant_name()),
+                min_format,
+                pack_formats,
+            )
+        };
         match self {
-            Self::Run(cmd) => cmd.validate(pack_formats),
-            Self::Runs(cmds) => cmds.iter().all(|cmd| cmd.validate(pack_formats)),
+            Self::Run(cmd) => cmd.incompatibilities(pack_formats),
+            Self::Runs(cmds) => cmds
+                .iter()
+                .flat_map(|cmd| cmd.incompatibilities(pack_formats))
+                .collect(),
             Self::Facing(_, next)
             | Self::Store(_, next)
             | Self::Positioned(_, next)
@@ -186,18 +380,269 @@ impl Execute {
             | Self::As(_, next)
             | Self::At(_, next)
             | Self::AsAt(_, next)
-            | Self::Align(_, next)
-            | Self::Anchored(_, next) => pack_formats.start() >= &4 && next.validate(pack_formats),
-            Self::If(_, next, el) => {
-                pack_formats.start() >= &4
-                    && next.validate(pack_formats)
-                    && el.as_deref().map_or(true, |el| el.validate(pack_formats))
-            }
-            Self::Summon(_, next) | Self::On(_, next) => {
-                pack_formats.start() >= &12 && next.validate(pack_formats)
-            }
+            | Self::Anchored(_, next) => subcommand(4)
+                .into_iter()
+                .chain(next.incompatibilities(pack_formats))
+                .collect(),
+            Self::Align(axes, next) => subcommand(4)
+                .into_iter()
+                .chain((!is_valid_axes(axes)).then(|| format!("invalid axes `{axes}` of `align`")))
+                .chain(next.incompatibilities(pack_formats))
+                .collect(),
+            Self::If(cond, next, el) => subcommand(cond.min_format())
+                .into_iter()
+                .chain(next.incompatibilities(pack_formats))
+                .chain(el.iter().flat_map(|el| el.incompatibilities(pack_formats)))
+                .collect(),
+            Self::IfCached(cond, next, el) => subcommand(cond.condition().min_format())
+                .into_iter()
+                .chain(next.incompatibilities(pack_formats))
+                .chain(el.iter().flat_map(|el| el.incompatibilities(pack_formats)))
+                .collect(),
+            Self::Summon(_, next) | Self::On(_, next) => subcommand(12)
+                .into_iter()
+                .chain(next.incompatibilities(pack_formats))
+                .collect(),
+        }
+    }
+}
+
+/// Description of what an execute command will compile into, created by [`Execute::explain`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Getters, CopyGetters)]
+pub struct ExecutePlan {
+    /// Number of lines the execute command compiles into.
+    #[get_copy = "pub"]
+    line_count: usize,
+    /// Predicted paths of the helper functions that will be generated.
+    #[get = "pub"]
+    helper_functions: Vec<String>,
+    /// Reasons why commands need to be grouped, in the order they occur.
+    #[get = "pub"]
+    reasons: Vec<GroupingReason>,
+}
+
+impl ExecutePlan {
+    /// Check whether a helper function will be generated.
+    #[must_use]
+    pub fn generates_helper(&self) -> bool {
+        !self.helper_functions.is_empty()
+    }
+}
+
+/// Reason why commands of an execute command need to be grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GroupingReason {
+    /// The then branch of an if condition consists of more than one command.
+    MultiCommandThen,
+    /// The condition is a disjunction and has to be checked part by part.
+    Disjunction,
+    /// An else branch is present and requires tracking whether the then branch ran.
+    Else,
+}
+
+/// Builder for chaining execute subcommands without nesting them by hand.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ExecuteBuilder {
+    /// Subcommands in order, their next part is replaced when building.
+    parts: Vec<Execute>,
+    /// Comments to insert in front of the commands to run.
+    comments: Vec<Command>,
+}
+
+impl ExecuteBuilder {
+    /// Append a subcommand, the next part will be set when building.
+    fn push<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(Box<Execute>) -> Execute,
+    {
+        self.parts.push(f(Box::new(Execute::Runs(Vec::new()))));
+        self
+    }
+
+    /// Add an `align` subcommand.
+    ///
+    /// Invalid axes are reported by [`Execute::validate`].
+    #[must_use]
+    pub fn align(self, axes: &str) -> Self {
+        self.push(|next| Execute::Align(axes.to_string(), next))
+    }
+
+    /// Add an `anchored` subcommand.
+    #[must_use]
+    pub fn anchored(self, anchor: &str) -> Self {
+        self.push(|next| Execute::Anchored(anchor.to_string(), next))
+    }
+
+    /// Add an `anchored` subcommand with the anchor.
+    #[must_use]
+    pub fn anchored_to(self, anchor: Anchor) -> Self {
+        self.anchored(anchor.name())
+    }
+
+    /// Add an `as` subcommand.
+    #[must_use]
+    pub fn as_(self, selector: &str) -> Self {
+        self.push(|next| Execute::As(selector.to_string(), next))
+    }
+
+    /// Add an `at` subcommand.
+    #[must_use]
+    pub fn at(self, selector: &str) -> Self {
+        self.push(|next| Execute::At(selector.to_string(), next))
+    }
+
+    /// Add `as` and `at` subcommands for the same selector.
+    #[must_use]
+    pub fn as_at(self, selector: &str) -> Self {
+        self.push(|next| Execute::AsAt(selector.to_string(), next))
+    }
+
+    /// Add a `facing` subcommand with the given raw arguments.
+    #[must_use]
+    pub fn facing(self, arg: &str) -> Self {
+        self.push(|next| Execute::Facing(arg.to_string(), next))
+    }
+
+    /// Add a `facing` subcommand rotating towards the target.
+    #[must_use]
+    pub fn facing_target(self, target: &FacingTarget) -> Self {
+        self.facing(&target.compile())
+    }
+
+    /// Add an `in` subcommand.
+    #[must_use]
+    pub fn in_(self, dimension: &str) -> Self {
+        self.push(|next| Execute::In(dimension.to_string(), next))
+    }
+
+    /// Add an `on` subcommand.
+    #[must_use]
+    pub fn on(self, relation: &str) -> Self {
+        self.push(|next| Execute::On(relation.to_string(), next))
+    }
+
+    /// Add an `on` subcommand with the relation.
+    #[must_use]
+    pub fn on_relation(self, relation: OnRelation) -> Self {
+        self.on(relation.name())
+    }
+
+    /// Add a `positioned` subcommand with the given raw arguments.
+    #[must_use]
+    pub fn positioned(self, arg: &str) -> Self {
+        self.push(|next| Execute::Positioned(arg.to_string(), next))
+    }
+
+    /// Add a `rotated` subcommand with the given raw arguments.
+    #[must_use]
+    pub fn rotated(self, arg: &str) -> Self {
+        self.push(|next| Execute::Rotated(arg.to_string(), next))
+    }
+
+    /// Add a `summon` subcommand.
+    #[must_use]
+    pub fn summon(self, entity: &str) -> Self {
+        self.push(|next| Execute::Summon(entity.to_string(), next))
+    }
+
+    /// Only run the following part if the condition is met.
+    #[must_use]
+    pub fn if_cond(self, cond: impl Into<Condition>) -> Self {
+        self.push(|next| Execute::If(cond.into(), next, None))
+    }
+
+    /// Only run the following part if the condition is met, otherwise run the else part.
+    #[must_use]
+    pub fn if_cond_else(self, cond: impl Into<Condition>, el: Execute) -> Self {
+        self.push(|next| Execute::If(cond.into(), next, Some(Box::new(el))))
+    }
+
+    /// Only run the following part if the cached condition is met, reusing its compiled form.
+    #[must_use]
+    pub fn if_cached(self, cond: CachedCondition) -> Self {
+        self.push(|next| Execute::IfCached(cond, next, None))
+    }
+
+    /// Add a `store` subcommand with the given raw arguments.
+    #[must_use]
+    pub fn store(self, arg: &str) -> Self {
+        self.push(|next| Execute::Store(arg.to_string(), next))
+    }
+
+    /// Add a `store` subcommand storing into the target.
+    #[must_use]
+    pub fn store_target(self, target: &StoreTarget) -> Self {
+        self.store(&target.compile())
+    }
+
+    /// Store the result of the command in the score of the holder.
+    #[must_use]
+    pub fn store_result_score(self, holder: &str, objective: &str) -> Self {
+        self.store(&format!("result score {holder} {objective}"))
+    }
+
+    /// Store whether the command was successful in the score of the holder.
+    #[must_use]
+    pub fn store_success_score(self, holder: &str, objective: &str) -> Self {
+        self.store(&format!("success score {holder} {objective}"))
+    }
+
+    /// Store the result of the command at the path of the storage.
+    /// The value is converted to the given NBT type after being multiplied by the scale.
+    #[must_use]
+    pub fn store_result_storage(self, storage: &str, path: &str, ty: &str, scale: f64) -> Self {
+        self.store(&format!("result storage {storage} {path} {ty} {scale}"))
+    }
+
+    /// Store whether the command was successful at the path of the storage.
+    /// The value is converted to the given NBT type after being multiplied by the scale.
+    #[must_use]
+    pub fn store_success_storage(self, storage: &str, path: &str, ty: &str, scale: f64) -> Self {
+        self.store(&format!("success storage {storage} {path} {ty} {scale}"))
+    }
+
+    /// Add a comment in front of the commands to run.
+    ///
+    /// The comment is part of the generated function if the commands need to be grouped.
+    #[must_use]
+    pub fn with_comment(mut self, comment: &str) -> Self {
+        self.comments.push(Command::Comment(comment.to_string()));
+        self
+    }
+
+    /// Finish the execute command by running the given command.
+    #[must_use]
+    pub fn run(self, command: impl Into<Command>) -> Execute {
+        if self.comments.is_empty() {
+            self.finish(Execute::Run(Box::new(command.into())))
+        } else {
+            self.runs(vec![command.into()])
         }
     }
+
+    /// Finish the execute command by running the given commands.
+    #[must_use]
+    pub fn runs(mut self, commands: Vec<Command>) -> Execute {
+        let mut runs = std::mem::take(&mut self.comments);
+        runs.extend(commands);
+        self.finish(Execute::Runs(runs))
+    }
+
+    fn finish(self, last: Execute) -> Execute {
+        self.parts
+            .into_iter()
+            .rev()
+            .fold(last, |next, part| part.with_next(next))
+    }
+}
+
+/// Check whether the axes are a non-empty combination of `x`, `y` and `z` without repeats.
+fn is_valid_axes(axes: &str) -> bool {
+    (1..=3).contains(&axes.len())
+        && axes
+            .chars()
+            .enumerate()
+            .all(|(i, c)| matches!(c, 'x' | 'y' | 'z') && !axes[..i].contains(c))
 }
 
 /// Combine command parts, respecting if the second part is a comment
@@ -214,7 +659,7 @@ fn map_run_cmd(cmd: String, prefix: &str) -> (bool, String) {
 /// The first tuple element is a boolean indicating if the prefix should be used for that command.
 #[tracing::instrument(skip_all)]
 fn compile_if_cond(
-    cond: &Condition,
+    str_cond: Vec<String>,
     then: &Execute,
     el: Option<&Execute>,
     prefix: &str,
@@ -223,15 +668,11 @@ fn compile_if_cond(
     function_state: &FunctionCompilerState,
 ) -> Vec<(bool, String)> {
     let then_count = then.get_count(options);
+    let storage = format!("{}:cond", options.internal_namespace);
 
-    let str_cond = cond.clone().compile(options, global_state, function_state);
-    let require_grouping_uid = (el.is_some() || then_count > 1).then(|| {
-        // calculate a unique condition id for the else check
-        let uid = function_state.request_uid();
-        let pre_hash = function_state.path().to_owned() + ":" + &uid.to_string();
-
-        md5::hash(pre_hash).to_hex_lowercase()
-    });
+    // calculate a unique condition id for the else check
+    let require_grouping_uid = (el.is_some() || then_count > 1)
+        .then(|| unique_hash(options, global_state, function_state));
     #[allow(clippy::option_if_let_else)]
     let then = if let Some(success_uid) = require_grouping_uid.as_deref() {
         // prepare commands for grouping
@@ -244,15 +685,15 @@ fn compile_if_cond(
         // this condition will be checked after the group ran to determine if the else part should be executed
         if el.is_some() && str_cond.len() <= 1 {
             group_cmd.push(
-                format!("data modify storage shulkerbox:cond {success_uid} set value true")
+                format!("data modify storage {storage} {success_uid} set value true")
                     .as_str()
                     .into(),
             );
         }
         Command::Group(group_cmd)
             .compile(options, global_state, function_state)
-            .iter()
-            .map(|s| (true, "run ".to_string() + s))
+            .into_iter()
+            .map(|s| map_run_cmd(s, ""))
             .collect()
     } else {
         then.compile_internal(
@@ -266,16 +707,20 @@ fn compile_if_cond(
     // if the conditions have multiple parts joined by a disjunction, commands need to be grouped
     let each_or_cmd = (str_cond.len() > 1).then(|| {
         let success_uid = require_grouping_uid.as_deref().unwrap_or_else(|| {
-            tracing::error!("No success_uid found for each_or_cmd, using default");
+            report(
+                global_state,
+                DiagnosticKind::Internal,
+                "No success_uid found for each_or_cmd, using default".to_string(),
+            );
             "if_success"
         });
         (
-            format!("data modify storage shulkerbox:cond {success_uid} set value true"),
+            format!("data modify storage {storage} {success_uid} set value true"),
             combine_conditions_commands(
                 str_cond.clone(),
                 &[(
                     true,
-                    format!("run data modify storage shulkerbox:cond {success_uid} set value true"),
+                    format!("run data modify storage {storage} {success_uid} set value true"),
                 )],
             ),
         )
@@ -283,10 +728,14 @@ fn compile_if_cond(
     // build the condition for each then command
     let successful_cond = if each_or_cmd.is_some() {
         let success_uid = require_grouping_uid.as_deref().unwrap_or_else(|| {
-            tracing::error!("No success_uid found for each_or_cmd, using default");
+            report(
+                global_state,
+                DiagnosticKind::Internal,
+                "No success_uid found for each_or_cmd, using default".to_string(),
+            );
             "if_success"
         });
-        Condition::Atom(format!("data storage shulkerbox:cond {{{success_uid}:1b}}")).compile(
+        Condition::Atom(format!("data storage {storage} {{{success_uid}:1b}}")).compile(
             options,
             global_state,
             function_state,
@@ -300,12 +749,17 @@ fn compile_if_cond(
     let el_commands = el
         .map(|el| {
             let success_uid = require_grouping_uid.as_deref().unwrap_or_else(|| {
-                tracing::error!("No success_uid found for each_or_cmd, using default");
+                report(
+                    global_state,
+                    DiagnosticKind::Internal,
+                    "No success_uid found for each_or_cmd, using default".to_string(),
+                );
                 "if_success"
             });
-            let else_cond =
-                (!Condition::Atom(format!("data storage shulkerbox:cond {{{success_uid}:1b}}")))
-                    .compile(options, global_state, function_state);
+            let else_cond = (!Condition::Atom(format!(
+                "data storage {storage} {{{success_uid}:1b}}"
+            )))
+            .compile(options, global_state, function_state);
             let el = el.compile_internal(
                 String::new(),
                 else_cond.len() > 1,
@@ -320,12 +774,16 @@ fn compile_if_cond(
     // reset the success storage if needed
     let reset_success_storage = if each_or_cmd.is_some() || el.is_some() {
         let success_uid = require_grouping_uid.as_deref().unwrap_or_else(|| {
-            tracing::error!("No success_uid found for each_or_cmd, using default");
+            report(
+                global_state,
+                DiagnosticKind::Internal,
+                "No success_uid found for each_or_cmd, using default".to_string(),
+            );
             "if_success"
         });
         Some((
             false,
-            format!("data remove storage shulkerbox:cond {success_uid}"),
+            format!("data remove storage {storage} {success_uid}"),
         ))
     } else {
         None
@@ -350,6 +808,60 @@ fn compile_if_cond(
         .collect()
 }
 
+/// Get the number of strings [`compile_if_cond`] returns, without compiling the command.
+fn collect_if_reasons(
+    cond_len: usize,
+    then: &Execute,
+    el: Option<&Execute>,
+    options: &CompileOptions,
+    reasons: &mut Vec<GroupingReason>,
+) {
+    if then.get_count(options) > 1 {
+        reasons.push(GroupingReason::MultiCommandThen);
+    }
+    if cond_len > 1 {
+        reasons.push(GroupingReason::Disjunction);
+    }
+    if let Some(el) = el {
+        reasons.push(GroupingReason::Else);
+        el.collect_grouping_reasons(options, reasons);
+    }
+    then.collect_grouping_reasons(options, reasons);
+}
+
+fn count_if_cond(
+    cond_len: usize,
+    then: &Execute,
+    el: Option<&Execute>,
+    options: &CompileOptions,
+) -> usize {
+    // the count of the then part is only needed if there is no else part
+    let then_count = el.is_none().then(|| then.get_count(options));
+
+    let then_len = match then_count {
+        Some(count) if count <= 1 => count,
+        _ => {
+            let mut group_cmd = match then {
+                Execute::Run(cmd) => vec![(**cmd).clone()],
+                Execute::Runs(cmds) => cmds.clone(),
+                ex => vec![Command::Execute(ex.clone())],
+            };
+            if el.is_some() && cond_len <= 1 {
+                // placeholder for storing the success of the condition
+                group_cmd.push(Command::raw(""));
+            }
+            Command::Group(group_cmd).compiled_len(options)
+        }
+    };
+
+    let each_or_len = if cond_len > 1 { cond_len } else { 0 };
+    let successful_cond_len = if cond_len > 1 { 1 } else { cond_len };
+    let el_len = el.map_or(0, |el| el.get_count(options));
+    let reset_len = if cond_len > 1 || el.is_some() { 2 } else { 0 };
+
+    reset_len + each_or_len + successful_cond_len * then_len + el_len
+}
+
 fn combine_conditions_commands(
     conditions: Vec<String>,
     commands: &[(bool, String)],
@@ -376,35 +888,247 @@ fn combine_conditions_commands(
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Condition {
     Atom(String),
-    Not(Box<Condition>),
-    And(Box<Condition>, Box<Condition>),
-    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Self>),
+    And(Box<Self>, Box<Self>),
+    Or(Box<Self>, Box<Self>),
 }
 impl Condition {
-    /// Normalize the condition to eliminate complex negations.
-    /// Uses De Morgan's laws to simplify the condition.
-    #[must_use]
-    pub fn normalize(&self) -> Self {
-        match self {
-            Self::Atom(_) => self.clone(),
-            Self::Not(c) => match *c.clone() {
-                Self::Atom(c) => Self::Not(Box::new(Self::Atom(c))),
-                Self::Not(c) => c.normalize(),
-                Self::And(a, b) => ((!*a).normalize()) | ((!*b).normalize()),
-                Self::Or(a, b) => ((!*a).normalize()) & ((!*b).normalize()),
-            },
-            Self::And(a, b) => a.normalize() & b.normalize(),
-            Self::Or(a, b) => a.normalize() | b.normalize(),
+    /// Parse the conditions of an execute command, e.g. `if block ~ ~ ~ stone unless entity @e[type=zombie]`.
+    ///
+    /// Each `if` clause becomes an atom and each `unless` clause a negated atom, all joined by [`Condition::And`].
+    /// An `if` or `unless` word only starts a new clause once the current clause has all arguments of its condition type,
+    /// e.g. `if score @s if matches 1` checks the objective `if`. For unknown condition types, every such word starts a new clause.
+    ///
+    /// # Errors
+    /// - If the string contains no clauses
+    /// - If a clause does not start with `if` or `unless`
+    /// - If a clause has no arguments
+    /// - If the brackets, braces or quotes of an argument are not closed
+    pub fn parse(conditions: &str) -> Result<Self, ConditionParseError> {
+        let words = split_words(conditions)
+            .ok_or_else(|| ConditionParseError::Unclosed(conditions.trim().to_string()))?;
+        let mut words = words
+            .into_iter()
+            .map(|range| (range.start, range.end))
+            .peekable();
+        let mut condition: Option<Self> = None;
+
+        while let Some((start, end)) = words.next() {
+            let negated = match &conditions[start..end] {
+                "if" => false,
+                "unless" => true,
+                _ => {
+                    return Err(ConditionParseError::MissingKeyword(
+                        conditions[start..].trim().to_string(),
+                    ))
+                }
+            };
+            let mut atom_range: Option<(usize, usize)> = None;
+            let mut arguments = Vec::new();
+            while let Some(&(word_start, word_end)) = words.peek() {
+                let word = &conditions[word_start..word_end];
+                let complete = condition_arity(&arguments).is_none_or(|n| arguments.len() >= n);
+                if complete && matches!(word, "if" | "unless") {
+                    break;
+                }
+                arguments.push(word);
+                atom_range = Some((atom_range.map_or(word_start, |(s, _)| s), word_end));
+                words.next();
+            }
+            let (atom_start, atom_end) = atom_range.ok_or_else(|| {
+                ConditionParseError::MissingArguments(conditions[start..end].to_string())
+            })?;
+
+            let atom = Self::Atom(conditions[atom_start..atom_end].to_string());
+            let clause = if negated {
+                Self::Not(Box::new(atom))
+            } else {
+                atom
+            };
+            condition = Some(match condition {
+                Some(condition) => condition & clause,
+                None => clause,
+            });
         }
+
+        condition.ok_or(ConditionParseError::Empty)
     }
 
-    /// Convert the condition into a truth table.
-    /// This will expand the condition into all possible combinations of its atoms.
-    /// All vector elements are in disjunction with each other and do not contain disjunctions and complex negations in them.
+    /// Create a condition that checks the biome at the given position.
+    ///
+    /// Requires pack format 10 or higher.
+    #[must_use]
+    pub fn biome(pos: &str, biome: &str) -> Self {
+        Self::Atom(format!("biome {pos} {biome}"))
+    }
+
+    /// Create a condition that checks whether the predicate with the id, e.g. `ns:name`, passes.
+    /// Predicates of the datapack are created with [`Namespace::predicate_mut`](crate::datapack::Namespace::predicate_mut).
+    ///
+    /// Requires pack format 5 or higher.
+    #[must_use]
+    pub fn predicate(predicate: &str) -> Self {
+        Self::Atom(format!("predicate {predicate}"))
+    }
+
+    /// Create a condition that checks whether any item in the slots matches the item predicate.
+    /// The source has to be either `entity <selector>` or `block <pos>`.
+    ///
+    /// Requires pack format 41 or higher.
+    #[must_use]
+    pub fn items(source: &str, slots: &str, item_predicate: &str) -> Self {
+        Self::Atom(format!("items {source} {slots} {item_predicate}"))
+    }
+
+    /// Create a condition that checks whether the score of the target is within the range.
+    ///
+    /// Exclusive bounds are converted into inclusive ones, a range with a single value is written as that value.
+    /// An empty range, e.g. `5..5`, results in a condition that is never met.
+    #[must_use]
+    pub fn score_matches<R>(target: &str, objective: &str, range: R) -> Self
+    where
+        R: RangeBounds<i32>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(start) => Some(Some(*start)),
+            Bound::Excluded(start) => start.checked_add(1).map(Some),
+            Bound::Unbounded => Some(None),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => Some(Some(*end)),
+            Bound::Excluded(end) => end.checked_sub(1).map(Some),
+            Bound::Unbounded => Some(None),
+        };
+        let (Some(start), Some(end)) = (start, end) else {
+            return Self::never_score(target, objective);
+        };
+        let range = match (start, end) {
+            (Some(start), Some(end)) if start > end => {
+                return Self::never_score(target, objective);
+            }
+            (Some(start), Some(end)) if start == end => start.to_string(),
+            (Some(start), Some(end)) => format!("{start}..{end}"),
+            (Some(start), None) => format!("{start}.."),
+            (None, Some(end)) => format!("..{end}"),
+            (None, None) => format!("{}..", i32::MIN),
+        };
+
+        Self::Atom(format!("score {target} {objective} matches {range}"))
+    }
+
+    /// Create a condition that is never met, as the score has to be both set and unset.
+    fn never_score(target: &str, objective: &str) -> Self {
+        let any = Self::score_matches(target, objective, ..);
+        any.clone() & !any
+    }
+
+    /// Create a condition that compares the scores of two targets.
+    #[must_use]
+    pub fn score_compare(
+        target: &str,
+        objective: &str,
+        ordering: ScoreOrdering,
+        source: &str,
+        source_objective: &str,
+    ) -> Self {
+        Self::Atom(format!(
+            "score {target} {objective} {} {source} {source_objective}",
+            ordering.symbol()
+        ))
+    }
+
+    /// Create a condition that is met if exactly one of the conditions is met.
+    ///
+    /// Expressed as `(a & !b) | (!a & b)`.
+    #[must_use]
+    pub fn xor(a: Self, b: Self) -> Self {
+        (a.clone() & !b.clone()) | (!a & b)
+    }
+
+    /// Create a condition that is met if all conditions are met.
+    ///
+    /// Returns `None` if there are no conditions.
+    pub fn all<I>(conditions: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        conditions.into_iter().reduce(BitAnd::bitand)
+    }
+
+    /// Create a condition that is met if any of the conditions is met.
+    ///
+    /// Returns `None` if there are no conditions.
+    pub fn any<I>(conditions: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        conditions.into_iter().reduce(BitOr::bitor)
+    }
+
+    /// Get the minimum pack format required by the condition.
+    #[must_use]
+    pub fn min_format(&self) -> u8 {
+        match self {
+            Self::Atom(s) => match s.split_ascii_whitespace().next() {
+                Some("predicate") => 5,
+                Some("biome") => 10,
+                Some("dimension" | "loaded") => 12,
+                Some("function") => 26,
+                Some("items") => 41,
+                _ => 4,
+            },
+            Self::Not(c) => c.min_format(),
+            Self::And(a, b) | Self::Or(a, b) => a.min_format().max(b.min_format()),
+        }
+    }
+
+    /// Get the number of strings the condition compiles into.
+    fn compiled_len(&self) -> usize {
+        match self {
+            Self::Atom(_) => 1,
+            Self::Not(n) if matches!(**n, Self::Atom(_)) => 1,
+            _ => self.simplify().to_truth_table().len(),
+        }
+    }
+
+    /// Negate the condition.
+    ///
+    /// Double negations are removed and negations of `And` and `Or` are pushed into
+    /// their operands using De Morgan's laws, so only atoms end up negated.
+    #[must_use]
+    pub fn negate(self) -> Self {
+        match self {
+            Self::Atom(_) => Self::Not(Box::new(self)),
+            Self::Not(c) => *c,
+            Self::And(a, b) => a.negate() | b.negate(),
+            Self::Or(a, b) => a.negate() & b.negate(),
+        }
+    }
+
+    /// Normalize the condition to eliminate complex negations.
+    /// Uses De Morgan's laws to simplify the condition.
+    #[must_use]
+    pub fn normalize(&self) -> Self {
+        match self {
+            Self::Atom(_) => self.clone(),
+            Self::Not(c) => match *c.clone() {
+                Self::Atom(c) => Self::Not(Box::new(Self::Atom(c))),
+                Self::Not(c) => c.normalize(),
+                Self::And(a, b) => ((!*a).normalize()) | ((!*b).normalize()),
+                Self::Or(a, b) => ((!*a).normalize()) & ((!*b).normalize()),
+            },
+            Self::And(a, b) => a.normalize() & b.normalize(),
+            Self::Or(a, b) => a.normalize() | b.normalize(),
+        }
+    }
+
+    /// Convert the condition into a truth table.
+    /// This will expand the condition into all possible combinations of its atoms.
+    /// All vector elements are in disjunction with each other and do not contain disjunctions and complex negations in them.
     #[must_use]
     pub fn to_truth_table(&self) -> Vec<Self> {
         match self.normalize() {
-            Self::Atom(_) | Self::Not(_) => vec![self.clone()],
+            normalized @ (Self::Atom(_) | Self::Not(_)) => vec![normalized],
             Self::Or(a, b) => a
                 .to_truth_table()
                 .into_iter()
@@ -424,6 +1148,114 @@ impl Condition {
         }
     }
 
+    /// Convert the condition into disjunctive normal form.
+    ///
+    /// The outer vector elements are in disjunction with each other,
+    /// the literals of each inner vector are in conjunction.
+    #[must_use]
+    pub fn to_dnf(&self) -> Vec<Vec<Literal>> {
+        self.to_truth_table()
+            .iter()
+            .map(|conjunction| {
+                let mut literals = Vec::new();
+                conjunction.collect_literals(&mut literals);
+                literals
+            })
+            .collect()
+    }
+
+    /// Create a condition from disjunctive normal form.
+    ///
+    /// Returns `None` if there are no conjunctions or a conjunction has no literals,
+    /// as constant conditions cannot be represented.
+    #[must_use]
+    pub fn from_dnf(dnf: &[Vec<Literal>]) -> Option<Self> {
+        dnf.iter()
+            .map(|conjunction| {
+                conjunction
+                    .iter()
+                    .map(Literal::to_condition)
+                    .reduce(BitAnd::bitand)
+            })
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .reduce(BitOr::bitor)
+    }
+
+    /// Simplify the condition without changing its truth value.
+    ///
+    /// Duplicate literals of a conjunction are removed (`A & A = A`), as well as conjunctions
+    /// that can never be true (`A & !A`), duplicate conjunctions and conjunctions that are absorbed
+    /// by a conjunction of a subset of their literals (`A | (A & B) = A`).
+    /// The result is in disjunctive normal form. If the condition can never be true, it is returned unchanged.
+    #[must_use]
+    pub fn simplify(&self) -> Self {
+        let conjunctions = self
+            .to_dnf()
+            .into_iter()
+            .filter_map(|conjunction| {
+                let mut literals: Vec<Literal> = Vec::with_capacity(conjunction.len());
+                for literal in conjunction {
+                    if literals
+                        .iter()
+                        .any(|l| l.atom == literal.atom && l.negated != literal.negated)
+                    {
+                        return None;
+                    }
+                    if !literals.contains(&literal) {
+                        literals.push(literal);
+                    }
+                }
+                Some(literals)
+            })
+            .collect::<Vec<_>>();
+
+        let is_subset = |a: &[Literal], b: &[Literal]| a.iter().all(|l| b.contains(l));
+        let simplified = conjunctions
+            .iter()
+            .enumerate()
+            .filter(|(i, conjunction)| {
+                !conjunctions.iter().enumerate().any(|(j, other)| {
+                    // keep the first of equal conjunctions
+                    i != &j
+                        && is_subset(other, conjunction)
+                        && (!is_subset(conjunction, other) || j < *i)
+                })
+            })
+            .map(|(_, conjunction)| conjunction.clone())
+            .collect::<Vec<_>>();
+
+        Self::from_dnf(&simplified).unwrap_or_else(|| self.clone())
+    }
+
+    /// Collect the literals of a conjunction without disjunctions and complex negations.
+    fn collect_literals(&self, literals: &mut Vec<Literal>) {
+        match self {
+            Self::Atom(atom) => literals.push(Literal {
+                atom: atom.clone(),
+                negated: false,
+            }),
+            Self::Not(n) => match n.as_ref() {
+                Self::Atom(atom) => literals.push(Literal {
+                    atom: atom.clone(),
+                    negated: true,
+                }),
+                n => {
+                    let mut inner = Vec::new();
+                    n.collect_literals(&mut inner);
+                    literals.extend(inner.into_iter().map(|literal| Literal {
+                        negated: !literal.negated,
+                        ..literal
+                    }));
+                }
+            },
+            Self::And(a, b) | Self::Or(a, b) => {
+                a.collect_literals(literals);
+                b.collect_literals(literals);
+            }
+        }
+    }
+
     /// Convert the condition into a string.
     ///
     /// Will fail if the condition contains an `Or` variant. Use `compile` instead.
@@ -452,7 +1284,18 @@ impl Condition {
         _global_state: &MutCompilerState,
         _function_state: &FunctionCompilerState,
     ) -> Vec<String> {
-        let truth_table = self.to_truth_table();
+        // skip building the truth table for the common case of a single (negated) atom
+        match self {
+            Self::Atom(s) => return vec![format!("if {s}")],
+            Self::Not(n) => {
+                if let Self::Atom(s) = n.as_ref() {
+                    return vec![format!("unless {s}")];
+                }
+            }
+            Self::And(..) | Self::Or(..) => {}
+        }
+
+        let truth_table = self.simplify().to_truth_table();
 
         truth_table
             .into_iter()
@@ -464,6 +1307,406 @@ impl Condition {
     }
 }
 
+/// Comparison operator for [`Condition::score_compare`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScoreOrdering {
+    /// `<`
+    Less,
+    /// `<=`
+    LessOrEqual,
+    /// `=`
+    Equal,
+    /// `>=`
+    GreaterOrEqual,
+    /// `>`
+    Greater,
+}
+
+impl ScoreOrdering {
+    /// Get the operator symbol used in the command.
+    #[must_use]
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Equal => "=",
+            Self::GreaterOrEqual => ">=",
+            Self::Greater => ">",
+        }
+    }
+}
+
+/// Target of a `store` subcommand, created with [`Execute::store`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreTarget {
+    /// Store the result of the command.
+    Result(StoreDestination),
+    /// Store whether the command was successful, `1` or `0`.
+    Success(StoreDestination),
+}
+
+impl StoreTarget {
+    /// Compile the target into the arguments of the `store` subcommand.
+    #[must_use]
+    pub fn compile(&self) -> String {
+        match self {
+            Self::Result(destination) => format!("result {}", destination.compile()),
+            Self::Success(destination) => format!("success {}", destination.compile()),
+        }
+    }
+}
+
+/// Destination of a [`StoreTarget`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreDestination {
+    /// Score of the target in the objective.
+    Score {
+        /// Score holder, e.g. a selector or fake player.
+        target: String,
+        /// Name of the objective.
+        objective: String,
+    },
+    /// NBT path in a storage, the value is multiplied by the scale.
+    Storage {
+        /// Resource location of the storage.
+        resource: String,
+        /// NBT path in the storage.
+        path: String,
+        /// Type of the stored value.
+        ty: NbtType,
+        /// Factor the value is multiplied by.
+        scale: f64,
+    },
+    /// Value or maximum of a bossbar.
+    Bossbar {
+        /// Id of the bossbar.
+        id: String,
+        /// Field of the bossbar.
+        field: BossbarField,
+    },
+    /// NBT path in the block entity at the position, the value is multiplied by the scale.
+    Block {
+        /// Position of the block.
+        pos: String,
+        /// NBT path in the block entity.
+        path: String,
+        /// Type of the stored value.
+        ty: NbtType,
+        /// Factor the value is multiplied by.
+        scale: f64,
+    },
+    /// NBT path in the entity, the value is multiplied by the scale.
+    Entity {
+        /// Selector of a single entity.
+        target: String,
+        /// NBT path in the entity.
+        path: String,
+        /// Type of the stored value.
+        ty: NbtType,
+        /// Factor the value is multiplied by.
+        scale: f64,
+    },
+}
+
+impl StoreDestination {
+    /// Compile the destination into the arguments following `result` or `success`.
+    #[must_use]
+    pub fn compile(&self) -> String {
+        match self {
+            Self::Score { target, objective } => format!("score {target} {objective}"),
+            Self::Storage {
+                resource,
+                path,
+                ty,
+                scale,
+            } => format!("storage {resource} {path} {} {scale}", ty.name()),
+            Self::Bossbar { id, field } => format!("bossbar {id} {}", field.name()),
+            Self::Block {
+                pos,
+                path,
+                ty,
+                scale,
+            } => format!("block {pos} {path} {} {scale}", ty.name()),
+            Self::Entity {
+                target,
+                path,
+                ty,
+                scale,
+            } => format!("entity {target} {path} {} {scale}", ty.name()),
+        }
+    }
+}
+
+/// Numeric NBT type of a value stored with a [`StoreDestination`].
+#[allow(missing_docs)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NbtType {
+    Byte,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+}
+
+impl NbtType {
+    /// Get the name of the type used in the command.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Byte => "byte",
+            Self::Short => "short",
+            Self::Int => "int",
+            Self::Long => "long",
+            Self::Float => "float",
+            Self::Double => "double",
+        }
+    }
+
+    /// Get the type with the name used in the command.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::Byte,
+            Self::Short,
+            Self::Int,
+            Self::Long,
+            Self::Float,
+            Self::Double,
+        ]
+        .into_iter()
+        .find(|ty| ty.name() == name)
+    }
+}
+
+/// Field of a bossbar a value is stored in with [`StoreDestination::Bossbar`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BossbarField {
+    /// Current value of the bossbar.
+    Value,
+    /// Maximum value of the bossbar.
+    Max,
+}
+
+impl BossbarField {
+    /// Get the name of the field used in the command.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Value => "value",
+            Self::Max => "max",
+        }
+    }
+}
+
+/// Anchor of an entity set with [`Execute::anchored`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Anchor {
+    /// Eyes of the entity.
+    Eyes,
+    /// Feet of the entity.
+    Feet,
+}
+
+impl Anchor {
+    /// Get the name of the anchor used in the command.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Eyes => "eyes",
+            Self::Feet => "feet",
+        }
+    }
+}
+
+/// Target to face with [`Execute::facing`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum FacingTarget {
+    /// Absolute position.
+    Position(f64, f64, f64),
+    /// Anchor of the entity.
+    Entity {
+        /// Selector of the entity.
+        selector: String,
+        /// Anchor of the entity to face.
+        anchor: Anchor,
+    },
+}
+
+impl FacingTarget {
+    /// Compile the target into the arguments of the `facing` subcommand.
+    #[must_use]
+    pub fn compile(&self) -> String {
+        match self {
+            Self::Position(x, y, z) => format!("{x} {y} {z}"),
+            Self::Entity { selector, anchor } => format!("entity {selector} {}", anchor.name()),
+        }
+    }
+}
+
+/// Relation of entities selected with [`Execute::on`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OnRelation {
+    /// Entity that last attacked the current entity.
+    Attacker,
+    /// Entity that controls the current entity, e.g. the rider of a horse.
+    Controller,
+    /// Entity holding the lead of the current entity.
+    Leasher,
+    /// Entity that created the current entity, e.g. the shooter of an arrow.
+    Origin,
+    /// Owner of the current tamed entity.
+    Owner,
+    /// Entities directly riding the current entity.
+    Passengers,
+    /// Entity the current entity is targeting.
+    Target,
+    /// Entity the current entity is riding.
+    Vehicle,
+}
+
+impl OnRelation {
+    /// All relations.
+    pub const ALL: [Self; 8] = [
+        Self::Attacker,
+        Self::Controller,
+        Self::Leasher,
+        Self::Origin,
+        Self::Owner,
+        Self::Passengers,
+        Self::Target,
+        Self::Vehicle,
+    ];
+
+    /// Get the name of the relation used in the command.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Attacker => "attacker",
+            Self::Controller => "controller",
+            Self::Leasher => "leasher",
+            Self::Origin => "origin",
+            Self::Owner => "owner",
+            Self::Passengers => "passengers",
+            Self::Target => "target",
+            Self::Vehicle => "vehicle",
+        }
+    }
+
+    /// Get the relation with the name used in the command.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|relation| relation.name() == name)
+    }
+}
+
+/// Condition with its truth table and compiled form computed once on creation.
+///
+/// Useful for complex conditions that are used in many places, as compiling them
+/// only clones the precomputed strings.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CachedCondition {
+    condition: Condition,
+    truth_table: Vec<Condition>,
+    compiled: Vec<String>,
+}
+
+impl CachedCondition {
+    /// Create a new cached condition, computing its truth table and compiled form.
+    #[must_use]
+    pub fn new(condition: Condition) -> Self {
+        let truth_table = condition.to_truth_table();
+        let compiled = condition.compile(
+            &CompileOptions::default(),
+            &MutCompilerState::default(),
+            &FunctionCompilerState::default(),
+        );
+
+        Self {
+            condition,
+            truth_table,
+            compiled,
+        }
+    }
+
+    /// Get the cached condition.
+    #[must_use]
+    pub fn condition(&self) -> &Condition {
+        &self.condition
+    }
+
+    /// Get the truth table of the condition.
+    #[must_use]
+    pub fn to_truth_table(&self) -> Vec<Condition> {
+        self.truth_table.clone()
+    }
+
+    /// Get the number of strings the condition compiles into.
+    #[must_use]
+    pub fn compiled_len(&self) -> usize {
+        self.compiled.len()
+    }
+
+    /// Compile the condition into a list of strings that can be used in Minecraft.
+    ///
+    /// Returns the precomputed result of [`Condition::compile`].
+    #[must_use]
+    pub fn compile(
+        &self,
+        _options: &CompileOptions,
+        _global_state: &MutCompilerState,
+        _function_state: &FunctionCompilerState,
+    ) -> Vec<String> {
+        self.compiled.clone()
+    }
+}
+
+impl From<Condition> for CachedCondition {
+    fn from(condition: Condition) -> Self {
+        Self::new(condition)
+    }
+}
+
+impl From<CachedCondition> for Condition {
+    fn from(cached: CachedCondition) -> Self {
+        cached.condition
+    }
+}
+
+/// Possibly negated atom of a condition in disjunctive normal form.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Literal {
+    /// The condition string of the atom.
+    pub atom: String,
+    /// Whether the atom is negated.
+    pub negated: bool,
+}
+
+impl Literal {
+    /// Convert the literal into a condition.
+    #[must_use]
+    pub fn to_condition(&self) -> Condition {
+        let atom = Condition::Atom(self.atom.clone());
+        if self.negated {
+            !atom
+        } else {
+            atom
+        }
+    }
+}
+
 impl From<&str> for Condition {
     fn from(s: &str) -> Self {
         Self::Atom(s.to_string())
@@ -474,7 +1717,7 @@ impl Not for Condition {
     type Output = Self;
 
     fn not(self) -> Self {
-        Self::Not(Box::new(self))
+        self.negate()
     }
 }
 impl BitAnd for Condition {
@@ -492,10 +1735,59 @@ impl BitOr for Condition {
     }
 }
 
+/// Error of parsing conditions with [`Condition::parse`].
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum ConditionParseError {
+    /// The string contains no clauses.
+    #[error("conditions are empty")]
+    Empty,
+    /// A clause does not start with `if` or `unless`.
+    #[error("expected `if` or `unless` before `{0}`")]
+    MissingKeyword(String),
+    /// A clause has no arguments after its keyword.
+    #[error("`{0}` clause has no arguments")]
+    MissingArguments(String),
+    /// The brackets, braces or quotes of an argument are not closed.
+    #[error("arguments of conditions `{0}` are not closed")]
+    Unclosed(String),
+}
+
+/// Get the number of words of the condition starting with the given words, including its type.
+///
+/// Returns `None` if the condition type is unknown.
+fn condition_arity(words: &[&str]) -> Option<usize> {
+    match *words.first()? {
+        "entity" | "predicate" | "dimension" | "function" => Some(2),
+        "loaded" => Some(4),
+        "block" | "biome" => Some(5),
+        "blocks" => Some(11),
+        // `score <target> <objective> matches <range>` or `score <target> <objective> <operation> <source> <objective>`
+        "score" => Some(if words.get(3) == Some(&"matches") {
+            5
+        } else {
+            6
+        }),
+        "data" => match *words.get(1)? {
+            "block" => Some(6),
+            "entity" | "storage" => Some(4),
+            _ => None,
+        },
+        "items" => match *words.get(1)? {
+            "block" => Some(7),
+            "entity" => Some(5),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::datapack::command::ReturnValue;
+
     #[allow(clippy::redundant_clone)]
     #[test]
     fn test_condition() {
@@ -541,16 +1833,49 @@ mod tests {
         );
     }
 
-    #[allow(clippy::redundant_clone)]
     #[test]
-    fn test_truth_table() {
-        let c1 = Condition::Atom("foo".to_string());
-        let c2 = Condition::Atom("bar".to_string());
-        let c3 = Condition::Atom("baz".to_string());
-        let c4 = Condition::Atom("foobar".to_string());
-
-        assert_eq!(
-            (c1.clone() & c2.clone()).to_truth_table(),
+    fn test_normalize_idempotent() {
+        /// Generate a random condition using a xorshift generator.
+        fn random_condition(state: &mut u64, depth: u8) -> Condition {
+            *state ^= *state << 13;
+            *state ^= *state >> 7;
+            *state ^= *state << 17;
+            let choice = if depth == 0 { 0 } else { *state % 4 };
+            match choice {
+                0 => Condition::Atom(format!("atom{}", *state % 5)),
+                1 => Condition::Not(Box::new(random_condition(state, depth - 1))),
+                2 => Condition::And(
+                    Box::new(random_condition(state, depth - 1)),
+                    Box::new(random_condition(state, depth - 1)),
+                ),
+                _ => Condition::Or(
+                    Box::new(random_condition(state, depth - 1)),
+                    Box::new(random_condition(state, depth - 1)),
+                ),
+            }
+        }
+
+        let mut state = 0x5EED_u64;
+        for _ in 0..1000 {
+            let condition = random_condition(&mut state, 6);
+            let normalized = condition.normalize();
+            assert_eq!(normalized.normalize(), normalized, "{condition:?}");
+        }
+
+        let double_negated = Condition::Not(Box::new(Condition::Not(Box::new("a".into()))));
+        assert_eq!(double_negated.to_truth_table(), vec![Condition::from("a")]);
+    }
+
+    #[allow(clippy::redundant_clone)]
+    #[test]
+    fn test_truth_table() {
+        let c1 = Condition::Atom("foo".to_string());
+        let c2 = Condition::Atom("bar".to_string());
+        let c3 = Condition::Atom("baz".to_string());
+        let c4 = Condition::Atom("foobar".to_string());
+
+        assert_eq!(
+            (c1.clone() & c2.clone()).to_truth_table(),
             vec![c1.clone() & c2.clone()]
         );
 
@@ -583,6 +1908,836 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_align() {
+        let run = || Execute::Run(Box::new("say hi".into()));
+
+        for axes in ["x", "yz", "zxy"] {
+            let ex = Execute::align(axes, run()).expect("valid axes rejected");
+            assert!(ex.validate(&(4..=48)));
+        }
+        for axes in ["", "xx", "w", "xyzx", "x y"] {
+            assert!(Execute::align(axes, run()).is_none(), "{axes}");
+            assert!(!Execute::Align(axes.to_string(), Box::new(run())).validate(&(4..=48)));
+        }
+    }
+
+    #[test]
+    fn test_run_group() {
+        let ex = Execute::As(
+            "@a".to_string(),
+            Box::new(Execute::Run(Box::new(Command::Group(vec![
+                "say 1".into(),
+                "say 2".into(),
+            ])))),
+        );
+
+        let options = CompileOptions::default();
+        assert_eq!(ex.get_count(&options), 1);
+
+        let queue = ExtendableQueue::default();
+        let compiled = ex.compile(
+            &options,
+            &MutCompilerState::default(),
+            &FunctionCompilerState::new("foo", "bar", queue.clone()),
+        );
+
+        let (path, function) = queue.pop_front().expect("no function generated");
+        assert!(queue.is_empty());
+        assert_eq!(
+            compiled,
+            vec![format!("execute as @a run function bar:{path}")]
+        );
+        assert_eq!(
+            function.get_commands(),
+            &vec![Command::from("say 1"), Command::from("say 2")]
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let options = CompileOptions::default();
+
+        let simple = Execute::As(
+            "@a".to_string(),
+            Box::new(Execute::Run(Box::new("say hi".into()))),
+        );
+        let plan = simple.explain(&options, "foo");
+        assert_eq!(plan.line_count(), 1);
+        assert!(!plan.generates_helper());
+        assert!(plan.reasons().is_empty());
+
+        let grouped = Execute::If(
+            Condition::from("a") | Condition::from("b"),
+            Box::new(Execute::Runs(vec!["say 1".into(), "say 2".into()])),
+            Some(Box::new(Execute::Run(Box::new("say 3".into())))),
+        );
+        let plan = grouped.explain(&options, "bar:foo");
+        assert_eq!(
+            plan.reasons(),
+            &vec![
+                GroupingReason::MultiCommandThen,
+                GroupingReason::Disjunction,
+                GroupingReason::Else
+            ]
+        );
+        assert_eq!(plan.helper_functions().len(), 1);
+        assert!(plan.helper_functions()[0].starts_with("sb/foo/"));
+
+        let mut queue = ExtendableQueue::default();
+        let compiled = grouped.compile(
+            &options,
+            &MutCompilerState::default(),
+            &FunctionCompilerState::new("foo", "bar", queue.clone()),
+        );
+        assert_eq!(plan.line_count(), compiled.len());
+        assert_eq!(
+            queue.next().map(|(path, _)| path).as_ref(),
+            plan.helper_functions().first()
+        );
+    }
+
+    #[test]
+    fn test_score_conditions() {
+        let matches = |range: String| Condition::Atom(format!("score @s foo matches {range}"));
+
+        assert_eq!(
+            Condition::score_matches("@s", "foo", 1..=20),
+            matches("1..20".to_string())
+        );
+        assert_eq!(
+            Condition::score_matches("@s", "foo", 1..20),
+            matches("1..19".to_string())
+        );
+        assert_eq!(
+            Condition::score_matches("@s", "foo", 5..=5),
+            matches("5".to_string())
+        );
+        assert_eq!(
+            Condition::score_matches("@s", "foo", -3..),
+            matches("-3..".to_string())
+        );
+        assert_eq!(
+            Condition::score_matches("@s", "foo", ..=7),
+            matches("..7".to_string())
+        );
+        assert_eq!(
+            Condition::score_matches("@s", "foo", ..),
+            matches(format!("{}..", i32::MIN))
+        );
+
+        let never = matches(format!("{}..", i32::MIN)) & !matches(format!("{}..", i32::MIN));
+        assert_eq!(Condition::score_matches("@s", "foo", 5..5), never);
+        assert_eq!(
+            Condition::score_matches("@s", "foo", (Bound::Included(3), Bound::Included(1))),
+            never
+        );
+        assert_eq!(Condition::score_matches("@s", "foo", ..i32::MIN), never);
+        assert_eq!(
+            Condition::score_matches("@s", "foo", (Bound::Excluded(i32::MAX), Bound::Unbounded)),
+            never
+        );
+        assert_eq!(
+            Condition::score_matches(
+                "@s",
+                "foo",
+                (Bound::Excluded(i32::MAX - 1), Bound::Unbounded)
+            ),
+            matches(format!("{}..", i32::MAX))
+        );
+        assert_eq!(
+            Condition::score_matches("@s", "foo", ..=i32::MAX),
+            matches(format!("..{}", i32::MAX))
+        );
+
+        assert_eq!(
+            Condition::score_compare("@s", "foo", ScoreOrdering::LessOrEqual, "#max", "bar"),
+            Condition::Atom("score @s foo <= #max bar".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cached_condition() {
+        let condition = Condition::from("a") & !(Condition::from("b") | Condition::from("c"));
+        let cached = CachedCondition::from(condition.clone());
+
+        let options = CompileOptions::default();
+        let global_state = MutCompilerState::default();
+        let function_state = FunctionCompilerState::default();
+
+        assert_eq!(
+            cached.compile(&options, &global_state, &function_state),
+            condition.compile(&options, &global_state, &function_state)
+        );
+        assert_eq!(cached.to_truth_table(), condition.to_truth_table());
+        assert_eq!(Condition::from(cached), condition);
+    }
+
+    #[test]
+    fn test_if_cached() {
+        let condition = Condition::from("a") | Condition::from("b");
+        let options = CompileOptions::default();
+        let global_state = MutCompilerState::default();
+        let function_state = FunctionCompilerState::default();
+
+        let then = Box::new(Execute::Run(Box::new("say hi".into())));
+        let uncached = Execute::If(condition.clone(), then.clone(), None).compile(
+            &options,
+            &global_state,
+            &function_state,
+        );
+        let cached = Execute::IfCached(condition.clone().into(), then, None).compile(
+            &options,
+            &global_state,
+            &function_state,
+        );
+        assert_eq!(cached, uncached);
+
+        // the stored compiled form is used as is, the condition is not compiled again
+        let mut cached = CachedCondition::new(condition);
+        cached.compiled = vec!["if entity @s".to_string()];
+        let ex = Execute::builder().if_cached(cached).run("say hi");
+        assert_eq!(ex.get_count(&options), 1);
+        assert_eq!(
+            ex.compile(&options, &global_state, &function_state),
+            vec!["execute if entity @s run say hi".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_dnf() {
+        let literal = |atom: &str, negated| Literal {
+            atom: atom.to_string(),
+            negated,
+        };
+        let a = Condition::from("a");
+        let b = Condition::from("b");
+        let c = Condition::from("c");
+
+        let condition = a.clone() & !(b.clone() & c.clone());
+        let dnf = condition.to_dnf();
+        assert_eq!(
+            dnf,
+            vec![
+                vec![literal("a", false), literal("b", true)],
+                vec![literal("a", false), literal("c", true)]
+            ]
+        );
+        assert_eq!(Condition::from_dnf(&dnf), Some((a.clone() & !b) | (a & !c)));
+
+        assert_eq!(Condition::from_dnf(&[]), None);
+        assert_eq!(Condition::from_dnf(&[vec![]]), None);
+    }
+
+    #[test]
+    fn test_xor() {
+        let (a, b) = (Condition::from("a"), Condition::from("b"));
+
+        let xor = Condition::xor(a.clone(), b.clone());
+        assert_eq!(
+            xor.to_truth_table(),
+            vec![a.clone() & !b.clone(), !a.clone() & b.clone()]
+        );
+        assert_eq!(xor.simplify(), xor);
+        assert_eq!(
+            Condition::xor(a.clone(), !b.clone()).to_truth_table(),
+            vec![a.clone() & b.clone(), !a & !b]
+        );
+    }
+
+    #[test]
+    fn test_all_any() {
+        let (a, b, c) = (
+            Condition::from("a"),
+            Condition::from("b"),
+            Condition::from("c"),
+        );
+
+        assert_eq!(
+            Condition::all([a.clone(), b.clone(), c.clone()]),
+            Some(a.clone() & b.clone() & c.clone())
+        );
+        assert_eq!(
+            Condition::any(vec![a.clone(), b.clone(), c.clone()]),
+            Some(a.clone() | b.clone() | c)
+        );
+        assert_eq!(Condition::all([a.clone()]), Some(a));
+        assert_eq!(Condition::all(Vec::new()), None);
+        assert_eq!(Condition::any(std::iter::empty()), None);
+        assert_eq!(
+            Condition::any([b.clone()]).map(|cond| cond.to_truth_table()),
+            Some(vec![b])
+        );
+    }
+
+    #[test]
+    fn test_simplify() {
+        let (a, b, c) = (
+            Condition::from("a"),
+            Condition::from("b"),
+            Condition::from("c"),
+        );
+
+        // idempotence
+        assert_eq!((a.clone() & a.clone()).simplify(), a);
+        assert_eq!((a.clone() | a.clone()).simplify(), a);
+        // absorption
+        assert_eq!((a.clone() | (a.clone() & b.clone())).simplify(), a);
+        assert_eq!(((a.clone() & b.clone()) | a.clone()).simplify(), a);
+        assert_eq!((a.clone() & (a.clone() | b.clone())).simplify(), a);
+        // contradictions are removed
+        assert_eq!(((a.clone() & !a.clone()) | b.clone()).simplify(), b);
+        assert_eq!((a.clone() & !a.clone()).simplify(), a.clone() & !a.clone());
+        // nothing to simplify
+        assert_eq!(
+            ((a.clone() & b.clone()) | c.clone()).simplify(),
+            (a.clone() & b.clone()) | c.clone()
+        );
+
+        let compile = |cond: &Condition| {
+            cond.compile(
+                &CompileOptions::default(),
+                &MutCompilerState::default(),
+                &FunctionCompilerState::default(),
+            )
+        };
+        assert_eq!(
+            compile(&(a.clone() | (a.clone() & b.clone()) | (b.clone() & a.clone()))),
+            vec!["if a".to_string()]
+        );
+        assert_eq!(
+            compile(&((a.clone() & c.clone()) | (b & c.clone()) | (c & a))),
+            vec!["if a if c".to_string(), "if b if c".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_simplify_truth_value() {
+        fn eval(cond: &Condition, values: &[bool], atoms: &[&str]) -> bool {
+            match cond {
+                Condition::Atom(atom) => values[atoms.iter().position(|a| a == atom).unwrap()],
+                Condition::Not(c) => !eval(c, values, atoms),
+                Condition::And(a, b) => eval(a, values, atoms) && eval(b, values, atoms),
+                Condition::Or(a, b) => eval(a, values, atoms) || eval(b, values, atoms),
+            }
+        }
+
+        let atoms = ["a", "b", "c"];
+        let conditions = [
+            Condition::from("a") | (Condition::from("a") & Condition::from("b")),
+            (Condition::from("a") & Condition::from("b")) | !Condition::from("c"),
+            !(Condition::from("a") | Condition::from("b")) & Condition::from("a"),
+            (Condition::from("a") | Condition::from("b"))
+                & (Condition::from("b") | !Condition::from("c"))
+                & Condition::from("a"),
+        ];
+
+        for cond in conditions {
+            let simplified = cond.simplify();
+            for bits in 0..8u8 {
+                let values = [bits & 1 != 0, bits & 2 != 0, bits & 4 != 0];
+                assert_eq!(
+                    eval(&cond, &values, &atoms),
+                    eval(&simplified, &values, &atoms),
+                    "{cond:?} simplified to {simplified:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_negate() {
+        let a = Condition::from("a");
+        let b = Condition::from("b");
+        let c = Condition::from("c");
+
+        assert_eq!(!a.clone(), Condition::Not(Box::new(a.clone())));
+        assert_eq!(!!a.clone(), a);
+        assert_eq!(
+            !(a.clone() & (b.clone() | c.clone())),
+            Condition::Or(
+                Box::new(Condition::Not(Box::new(a.clone()))),
+                Box::new(Condition::And(
+                    Box::new(Condition::Not(Box::new(b.clone()))),
+                    Box::new(Condition::Not(Box::new(c)))
+                ))
+            )
+        );
+        assert_eq!(!!(a.clone() | b.clone()), a | b);
+    }
+
+    #[test]
+    fn test_min_format() {
+        let score = Condition::from("score @s foo matches 1");
+        let biome = Condition::biome("~ ~ ~", "minecraft:plains");
+        let items = Condition::items("entity @s", "weapon.mainhand", "minecraft:stick");
+
+        assert_eq!(score.min_format(), 4);
+        assert_eq!(biome.min_format(), 10);
+        assert_eq!(Condition::predicate("foo:bar").min_format(), 5);
+        assert_eq!((score.clone() & !items.clone()).min_format(), 41);
+        assert_eq!((score | biome.clone()).min_format(), 10);
+
+        let ex = Execute::If(
+            items,
+            Box::new(Execute::Run(Box::new("say hi".into()))),
+            None,
+        );
+        assert!(ex.validate(&(41..=48)));
+        assert!(!ex.validate(&(26..=48)));
+
+        let ex = Execute::If(
+            biome,
+            Box::new(Execute::Run(Box::new("say hi".into()))),
+            None,
+        );
+        assert!(ex.validate(&(10..=12)));
+        assert!(!ex.validate(&(9..=12)));
+
+        let ex = Execute::If(
+            Condition::from("entity @s"),
+            Box::new(Execute::Summon(
+                "minecraft:pig".to_string(),
+                Box::new(Execute::Run(Box::new("attribute @s foo get".into()))),
+            )),
+            None,
+        );
+        assert_eq!(
+            ex.incompatibilities(&(5..=48)),
+            vec![
+                "subcommand `summon` requires pack format >= 12".to_string(),
+                "command `attribute` requires pack format >= 6".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_builder_store() {
+        let ex = Execute::builder()
+            .store_result_score("@s", "foo")
+            .store_success_storage("foo:bar", "baz", "byte", 1.0)
+            .run("time query gametime");
+
+        let expected = Execute::Store(
+            "result score @s foo".to_string(),
+            Box::new(Execute::Store(
+                "success storage foo:bar baz byte 1".to_string(),
+                Box::new(Execute::Run(Box::new("time query gametime".into()))),
+            )),
+        );
+        assert_eq!(ex, expected);
+
+        let compiled = ex.compile(
+            &CompileOptions::default(),
+            &MutCompilerState::default(),
+            &FunctionCompilerState::default(),
+        );
+        assert_eq!(
+            compiled,
+            vec!["execute store result score @s foo store success storage foo:bar baz byte 1 run time query gametime".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_anchored_facing() {
+        let options = &CompileOptions::default();
+        let global_state = &MutCompilerState::default();
+        let function_state = &FunctionCompilerState::default();
+        let run = || Execute::Run(Box::new("say hi".into()));
+
+        let anchored = Execute::anchored(Anchor::Eyes, run());
+        assert_eq!(
+            anchored.compile(options, global_state, function_state),
+            vec!["execute anchored eyes run say hi".to_string()]
+        );
+        assert_eq!(
+            Execute::builder().anchored_to(Anchor::Eyes).run("say hi"),
+            anchored
+        );
+
+        let target = FacingTarget::Entity {
+            selector: "@p".to_string(),
+            anchor: Anchor::Eyes,
+        };
+        let facing = Execute::facing(&target, run());
+        assert_eq!(
+            facing.compile(options, global_state, function_state),
+            vec!["execute facing entity @p eyes run say hi".to_string()]
+        );
+        assert_eq!(
+            Execute::builder().facing_target(&target).run("say hi"),
+            facing
+        );
+
+        let position = Execute::facing(&FacingTarget::Position(1.0, -2.5, 3.0), run());
+        assert_eq!(
+            position.compile(options, global_state, function_state),
+            vec!["execute facing 1 -2.5 3 run say hi".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_predicate_condition() {
+        let ex = Execute::If(
+            Condition::predicate("foo:chance"),
+            Box::new(Execute::Run(Box::new("say lucky".into()))),
+            None,
+        );
+        assert_eq!(
+            ex.compile(
+                &CompileOptions::default(),
+                &MutCompilerState::default(),
+                &FunctionCompilerState::default()
+            ),
+            vec!["execute if predicate foo:chance run say lucky".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        let condition = Condition::parse("if block ~ ~ ~ stone unless entity @e[type=zombie]")
+            .expect("failed to parse conditions");
+        assert_eq!(
+            condition,
+            Condition::from("block ~ ~ ~ stone") & !Condition::from("entity @e[type=zombie]")
+        );
+        assert_eq!(
+            condition,
+            Condition::And(
+                Box::new(Condition::Atom("block ~ ~ ~ stone".to_string())),
+                Box::new(Condition::Not(Box::new(Condition::Atom(
+                    "entity @e[type=zombie]".to_string()
+                )))),
+            )
+        );
+
+        let ex = Execute::If(
+            condition,
+            Box::new(Execute::Run(Box::new("say hi".into()))),
+            None,
+        );
+        assert_eq!(
+            ex.compile(
+                &CompileOptions::default(),
+                &MutCompilerState::default(),
+                &FunctionCompilerState::default()
+            ),
+            vec![
+                "execute if block ~ ~ ~ stone unless entity @e[type=zombie] run say hi".to_string()
+            ]
+        );
+
+        assert_eq!(
+            Condition::parse("unless data entity @s {Tags:[\"if x\"]}  if score @s a matches 1"),
+            Ok(!Condition::from("data entity @s {Tags:[\"if x\"]}")
+                & Condition::from("score @s a matches 1"))
+        );
+        assert_eq!(
+            Condition::parse("unless predicate foo:bar"),
+            Ok(Condition::Not(Box::new("predicate foo:bar".into())))
+        );
+
+        // `if` and `unless` are arguments until the clause is complete
+        assert_eq!(
+            Condition::parse("if score @s if matches 1 unless entity @s"),
+            Ok(Condition::from("score @s if matches 1") & !Condition::from("entity @s"))
+        );
+        assert_eq!(
+            Condition::parse("if score @s unless < @p if unless block ~ ~ ~ stone"),
+            Ok(Condition::from("score @s unless < @p if") & !Condition::from("block ~ ~ ~ stone"))
+        );
+        assert_eq!(
+            Condition::parse("if data storage if unless if items entity @s unless *"),
+            Ok(Condition::from("data storage if unless")
+                & Condition::from("items entity @s unless *"))
+        );
+        // unknown condition types end at the next keyword
+        assert_eq!(
+            Condition::parse("if custom a if b"),
+            Ok(Condition::from("custom a") & Condition::from("b"))
+        );
+
+        assert_eq!(Condition::parse("  "), Err(ConditionParseError::Empty));
+        assert_eq!(
+            Condition::parse("block ~ ~ ~ stone"),
+            Err(ConditionParseError::MissingKeyword(
+                "block ~ ~ ~ stone".to_string()
+            ))
+        );
+        assert_eq!(
+            Condition::parse("if block ~ ~ ~ stone unless"),
+            Err(ConditionParseError::MissingArguments("unless".to_string()))
+        );
+        assert_eq!(
+            Condition::parse("if entity @e[type=zombie"),
+            Err(ConditionParseError::Unclosed(
+                "if entity @e[type=zombie".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_on_relation() {
+        let options = &CompileOptions::default();
+        let global_state = &MutCompilerState::default();
+        let function_state = &FunctionCompilerState::default();
+
+        for (relation, name) in OnRelation::ALL.into_iter().zip([
+            "attacker",
+            "controller",
+            "leasher",
+            "origin",
+            "owner",
+            "passengers",
+            "target",
+            "vehicle",
+        ]) {
+            let ex = Execute::on(relation, Execute::Run(Box::new("say hi".into())));
+            assert_eq!(
+                ex.compile(options, global_state, function_state),
+                vec![format!("execute on {name} run say hi")]
+            );
+            assert_eq!(OnRelation::from_name(name), Some(relation));
+            assert_eq!(Execute::builder().on_relation(relation).run("say hi"), ex);
+        }
+        assert_eq!(OnRelation::from_name("rider"), None);
+    }
+
+    #[test]
+    fn test_store_target() {
+        let score = StoreTarget::Result(StoreDestination::Score {
+            target: "@s".to_string(),
+            objective: "foo".to_string(),
+        });
+        assert_eq!(score.compile(), "result score @s foo");
+
+        let storage = StoreTarget::Success(StoreDestination::Storage {
+            resource: "foo:bar".to_string(),
+            path: "baz".to_string(),
+            ty: NbtType::Byte,
+            scale: 1.0,
+        });
+        assert_eq!(storage.compile(), "success storage foo:bar baz byte 1");
+
+        let entity = StoreTarget::Result(StoreDestination::Entity {
+            target: "@s".to_string(),
+            path: "Health".to_string(),
+            ty: NbtType::Float,
+            scale: 0.5,
+        });
+        assert_eq!(entity.compile(), "result entity @s Health float 0.5");
+
+        let bossbar = StoreTarget::Result(StoreDestination::Bossbar {
+            id: "foo:bar".to_string(),
+            field: BossbarField::Max,
+        });
+        assert_eq!(bossbar.compile(), "result bossbar foo:bar max");
+
+        let ex = Execute::store(&score, Execute::Run(Box::new("time query gametime".into())));
+        assert_eq!(
+            ex,
+            Execute::builder()
+                .store_result_score("@s", "foo")
+                .run("time query gametime")
+        );
+        assert_eq!(
+            Execute::builder()
+                .store_target(&storage)
+                .run("time query gametime"),
+            Execute::builder()
+                .store_success_storage("foo:bar", "baz", "byte", 1.0)
+                .run("time query gametime")
+        );
+    }
+
+    #[test]
+    fn test_builder_chain() {
+        let cond = Condition::from("block ~ ~-1 ~ minecraft:stone");
+        let built = Execute::builder()
+            .as_("@a")
+            .at("@s")
+            .if_cond_else(cond.clone(), Execute::Run(Box::new("say no".into())))
+            .positioned("~ ~1 ~")
+            .runs(vec!["say yes".into(), "say again".into()]);
+
+        let expected = Execute::As(
+            "@a".to_string(),
+            Box::new(Execute::At(
+                "@s".to_string(),
+                Box::new(Execute::If(
+                    cond,
+                    Box::new(Execute::Positioned(
+                        "~ ~1 ~".to_string(),
+                        Box::new(Execute::Runs(vec!["say yes".into(), "say again".into()])),
+                    )),
+                    Some(Box::new(Execute::Run(Box::new("say no".into())))),
+                )),
+            )),
+        );
+        assert_eq!(built, expected);
+
+        let compile = |ex: &Execute| {
+            ex.compile(
+                &CompileOptions::default(),
+                &MutCompilerState::default(),
+                &FunctionCompilerState::default(),
+            )
+        };
+        assert_eq!(compile(&built), compile(&expected));
+    }
+
+    #[test]
+    fn test_compile_single_atom() {
+        let options = &CompileOptions::default();
+        let global_state = &MutCompilerState::default();
+        let function_state = &FunctionCompilerState::default();
+
+        let general = |c: &Condition| {
+            c.to_truth_table()
+                .into_iter()
+                .map(|c| c.str_cond().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let atom = Condition::from("entity @s[tag=foo]");
+        assert_eq!(
+            atom.compile(options, global_state, function_state),
+            vec!["if entity @s[tag=foo]".to_string()]
+        );
+        assert_eq!(
+            atom.compile(options, global_state, function_state),
+            general(&atom)
+        );
+
+        let not_atom = !atom;
+        assert_eq!(
+            not_atom.compile(options, global_state, function_state),
+            vec!["unless entity @s[tag=foo]".to_string()]
+        );
+        assert_eq!(
+            not_atom.compile(options, global_state, function_state),
+            general(&not_atom)
+        );
+    }
+
+    #[test]
+    fn test_disjunction_runs_single_helper() {
+        let queue = ExtendableQueue::default();
+        let function_state = FunctionCompilerState::new("foo", "bar", queue.clone());
+
+        let compiled = Execute::If(
+            Condition::from("a") | "b".into() | "c".into(),
+            Box::new(Execute::Runs(vec![
+                "say 1".into(),
+                "say 2".into(),
+                "say 3".into(),
+            ])),
+            None,
+        )
+        .compile(
+            &CompileOptions::default(),
+            &MutCompilerState::default(),
+            &function_state,
+        );
+
+        // one check per disjunct and a single call of the helper function
+        assert_eq!(
+            compiled
+                .iter()
+                .filter(|cmd| cmd.contains("set value true"))
+                .count(),
+            3
+        );
+        assert_eq!(
+            compiled
+                .iter()
+                .filter(|cmd| cmd.contains("run function bar:sb/foo/"))
+                .count(),
+            1
+        );
+        assert!(!compiled.iter().any(|cmd| cmd.contains("say")));
+
+        // the then commands only appear once in the helper function
+        assert_eq!(queue.len(), 1);
+        let (_, helper) = queue.pop_front().expect("no helper function generated");
+        assert_eq!(
+            helper.get_commands(),
+            &vec![
+                Command::from("say 1"),
+                Command::from("say 2"),
+                Command::from("say 3")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_builder_comment() {
+        let queue = ExtendableQueue::default();
+        let function_state = FunctionCompilerState::new("foo", "bar", queue.clone());
+
+        let ex = Execute::builder()
+            .store_result_score("@s", "foo")
+            .with_comment("greet the player")
+            .runs(vec!["say hi".into()]);
+        assert_eq!(
+            ex,
+            Execute::Store(
+                "result score @s foo".to_string(),
+                Box::new(Execute::Runs(vec![
+                    Command::Comment("greet the player".to_string()),
+                    "say hi".into()
+                ]))
+            )
+        );
+
+        let then = Execute::builder()
+            .with_comment("greet the player")
+            .run("say hi");
+        let compiled = Execute::If(
+            "entity @s".into(),
+            Box::new(then),
+            Some(Box::new(Execute::Run(Box::new("say else".into())))),
+        )
+        .compile(
+            &CompileOptions::default(),
+            &MutCompilerState::default(),
+            &function_state,
+        );
+        assert!(compiled.iter().all(|cmd| !cmd.contains("run #")));
+
+        let (_, helper) = queue.pop_front().expect("no helper function generated");
+        assert_eq!(
+            helper.get_commands().first(),
+            Some(&Command::Comment("greet the player".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_if_nested_execute_flattened() {
+        let queue = ExtendableQueue::default();
+        let function_state = FunctionCompilerState::new("foo", "bar", queue.clone());
+
+        let compiled = Execute::If(
+            "score @s foo matches 1".into(),
+            Box::new(Execute::Run(Box::new(Command::Execute(Execute::As(
+                "@a".to_string(),
+                Box::new(Execute::Run(Box::new("say hi".into()))),
+            ))))),
+            None,
+        )
+        .compile(
+            &CompileOptions::default(),
+            &MutCompilerState::default(),
+            &function_state,
+        );
+
+        assert_eq!(
+            compiled,
+            vec!["execute if score @s foo matches 1 as @a run say hi".to_string()]
+        );
+        assert!(queue.is_empty());
+    }
+
     #[test]
     fn test_combine_conditions_commands() {
         let conditions = vec!["a", "b", "c"]
@@ -634,4 +2789,122 @@ mod tests {
 
         assert_eq!(direct, vec!["say direct".to_string()]);
     }
+
+    #[test]
+    fn test_internal_namespace() {
+        let ex = Execute::If(
+            Condition::from("entity @s[tag=a]") | Condition::from("entity @s[tag=b]"),
+            Box::new(Execute::Run(Box::new("say then".into()))),
+            Some(Box::new(Execute::Run(Box::new("say else".into())))),
+        );
+
+        for pack_format in [15, 48] {
+            let compile = |options: &CompileOptions| {
+                ex.compile(
+                    &CompileOptions {
+                        pack_format,
+                        ..options.clone()
+                    },
+                    &MutCompilerState::default(),
+                    &FunctionCompilerState::default(),
+                )
+            };
+
+            let default = compile(&CompileOptions::default());
+            assert!(default
+                .iter()
+                .any(|cmd| cmd.contains("storage shulkerbox:cond")));
+
+            let custom = compile(&CompileOptions::default().with_internal_namespace("mypack"));
+            assert!(custom.iter().any(|cmd| cmd.contains("storage mypack:cond")));
+            assert!(custom.iter().all(|cmd| !cmd.contains("shulkerbox")));
+            assert_eq!(
+                custom,
+                default
+                    .iter()
+                    .map(|cmd| cmd.replace("shulkerbox:cond", "mypack:cond"))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[allow(clippy::redundant_clone)]
+    #[test]
+    fn test_get_count_matches_compiled() {
+        let run = |cmd: &str| Box::new(Execute::Run(Box::new(cmd.into())));
+        let a = Condition::from("entity @s[tag=a]");
+        let b = Condition::from("entity @s[tag=b]");
+        let c = Condition::from("entity @s[tag=c]");
+
+        let nested = (0..4).fold(*run("say end"), |el, i| {
+            Execute::If(
+                Condition::from(format!("score @s foo matches {i}").as_str()),
+                run(&format!("say {i}")),
+                Some(Box::new(el)),
+            )
+        });
+
+        let cases = vec![
+            *run("say hi"),
+            Execute::As("@a".to_string(), run("say hi")),
+            Execute::If(a.clone(), run("say then"), None),
+            Execute::If(!a.clone(), run("say then"), Some(run("say else"))),
+            Execute::If(a.clone() | b.clone(), run("say then"), None),
+            Execute::If(
+                (a.clone() & b.clone()) | !c.clone(),
+                run("say then"),
+                Some(run("say else")),
+            ),
+            Execute::If(
+                a.clone(),
+                Box::new(Execute::Runs(vec!["say 1".into(), "say 2".into()])),
+                None,
+            ),
+            Execute::If(
+                a.clone() | c.clone(),
+                Box::new(Execute::Runs(vec!["say 1".into(), "say 2".into()])),
+                Some(Box::new(Execute::If(b.clone(), run("say 3"), None))),
+            ),
+            Execute::If(
+                a.clone(),
+                Box::new(Execute::If(b.clone() | c.clone(), run("say nested"), None)),
+                None,
+            ),
+            Execute::Runs(vec![
+                "say 1".into(),
+                Command::Group(vec!["say 2".into(), "say 3".into()]),
+                Command::Comment("comment".to_string()),
+                "say 4\nsay 5".into(),
+            ]),
+            Execute::Summon(
+                "pig".to_string(),
+                Box::new(Execute::Runs(vec!["say 1".into(), "say 2".into()])),
+            ),
+            Execute::As(
+                "@a".to_string(),
+                Box::new(Execute::Run(Box::new(Command::Group(vec![
+                    "say 1".into(),
+                    Command::Return(ReturnValue::Run(Box::new("say 2".into()))),
+                ])))),
+            ),
+            nested,
+        ];
+
+        for options in [
+            CompileOptions::default(),
+            CompileOptions::default().with_minify(true),
+            CompileOptions::default().with_pack_format(15),
+        ] {
+            for ex in &cases {
+                let compiled = ex.compile_internal(
+                    String::new(),
+                    false,
+                    &options,
+                    &MutCompilerState::default(),
+                    &FunctionCompilerState::default(),
+                );
+                assert_eq!(ex.get_count(&options), compiled.len(), "{ex:?}");
+            }
+        }
+    }
 }
@@ -1,16 +1,29 @@
 //! Represents a command that can be included in a function.
 
 mod execute;
-use std::{collections::HashMap, ops::RangeInclusive, sync::OnceLock};
+mod visit;
+use std::{
+    collections::HashMap,
+    ops::RangeInclusive,
+    sync::{OnceLock, PoisonError},
+};
 
-pub use execute::{Condition, Execute};
+pub use execute::{
+    Anchor, BossbarField, CachedCondition, Condition, ConditionParseError, Execute, ExecuteBuilder,
+    ExecutePlan, FacingTarget, GroupingReason, Literal, NbtType, OnRelation, ScoreOrdering,
+    StoreDestination, StoreTarget,
+};
+pub use visit::CommandVisitor;
 
 use chksum_md5 as md5;
 
-use super::Function;
+use super::{
+    text::{HoverEvent, TextComponent},
+    Function,
+};
 use crate::{
     prelude::Datapack,
-    util::compile::{CompileOptions, FunctionCompilerState, MutCompilerState},
+    util::compile::{CompileOptions, DebugFormat, FunctionCompilerState, MutCompilerState},
 };
 
 /// Represents a command that can be included in a function.
@@ -24,9 +37,15 @@ pub enum Command {
     /// Execute command
     Execute(Execute),
     /// Group of commands to be called instantly after each other
-    Group(Vec<Command>),
+    Group(Vec<Self>),
     /// Comment to be added to the function
     Comment(String),
+    /// Return from the function
+    Return(ReturnValue),
+    /// Macro line with `$(name)` placeholders that are replaced with the arguments of the function call
+    MacroLine(String),
+    /// Schedule a function or clear its schedules
+    Schedule(Schedule),
 }
 
 impl Command {
@@ -36,6 +55,60 @@ impl Command {
         Self::Raw(command.to_string())
     }
 
+    /// Create a `tellraw` command showing the text component to the players matching the selector.
+    #[must_use]
+    pub fn tellraw(selector: &str, component: &TextComponent) -> Self {
+        Self::Raw(format!("tellraw {selector} {}", component.to_json()))
+    }
+
+    /// Create a `title` command showing the text component as title to the players matching the selector.
+    #[must_use]
+    pub fn title(selector: &str, component: &TextComponent) -> Self {
+        Self::Raw(format!("title {selector} title {}", component.to_json()))
+    }
+
+    /// Create a command calling the function with the arguments for its macro lines.
+    #[must_use]
+    pub fn function_with(function: &str, args: &MacroArgs) -> Self {
+        Self::Raw(format!("function {function} {}", args.compile()))
+    }
+
+    /// Create a command calling the function with the arguments for its macro lines.
+    ///
+    /// Like [`Command::function_with`], but taking the id from the function.
+    #[must_use]
+    pub fn call_with(function: &Function, args: &MacroArgs) -> Self {
+        Self::Raw(format!("{} {}", function_call(function), args.compile()))
+    }
+
+    /// Create a `schedule` command running the function after the time.
+    #[must_use]
+    pub fn schedule(function: &str, time: ScheduleTime, mode: ScheduleMode) -> Self {
+        Self::Schedule(Schedule::Function {
+            function: function.to_string(),
+            time,
+            mode,
+        })
+    }
+
+    /// Create a `schedule` command clearing the schedules of the function.
+    #[must_use]
+    pub fn schedule_clear(function: &str) -> Self {
+        Self::Schedule(Schedule::Clear(function.to_string()))
+    }
+
+    /// Create an `advancement` command granting the advancement to the players matching the selector.
+    #[must_use]
+    pub fn advancement_grant(selector: &str, advancement: &str) -> Self {
+        Self::Raw(format!("advancement grant {selector} only {advancement}"))
+    }
+
+    /// Create an `advancement` command revoking the advancement from the players matching the selector.
+    #[must_use]
+    pub fn advancement_revoke(selector: &str, advancement: &str) -> Self {
+        Self::Raw(format!("advancement revoke {selector} only {advancement}"))
+    }
+
     /// Compile the command into a string.
     pub fn compile(
         &self,
@@ -48,7 +121,11 @@ impl Command {
             Self::Debug(message) => compile_debug(message, options),
             Self::Execute(ex) => ex.compile(options, global_state, function_state),
             Self::Group(commands) => compile_group(commands, options, global_state, function_state),
+            Self::Comment(_) if options.minify => Vec::new(),
             Self::Comment(comment) => vec!["#".to_string() + comment],
+            Self::Return(value) => compile_return(value, options, global_state, function_state),
+            Self::MacroLine(line) => vec![compile_macro_line(line)],
+            Self::Schedule(schedule) => vec![schedule.compile()],
         }
     }
 
@@ -56,22 +133,297 @@ impl Command {
     #[must_use]
     fn get_count(&self, options: &CompileOptions) -> usize {
         match self {
-            // TODO: change comment to compile to `1`, make sure nothing breaks
-            Self::Comment(_) => 0,
-            Self::Debug(_) => usize::from(options.debug),
+            // multiple commands are grouped into a function, nothing is replaced by `return 0`
+            Self::Group(_) | Self::Return(_) | Self::MacroLine(_) | Self::Schedule(_) => 1,
+            Self::Comment(_) => usize::from(!options.minify),
+            Self::Debug(_) => usize::from(options.debug && !options.minify),
             Self::Raw(cmd) => cmd.split('\n').count(),
             Self::Execute(ex) => ex.get_count(options),
-            Self::Group(_) => 1,
         }
     }
 
+    /// Get the count of the commands this command will compile into and the number of compiled strings,
+    /// without compiling it.
+    ///
+    /// Unlike the count, the number of compiled strings counts a raw command containing line breaks only once.
+    fn counts(&self, options: &CompileOptions) -> (usize, usize) {
+        match self {
+            Self::Execute(ex) => {
+                let count = ex.get_count(options);
+                (count, count)
+            }
+            Self::Group(commands) => (1, group_len(commands, options)),
+            Self::Return(ReturnValue::Run(cmd)) => {
+                let (_, len) = group_counts(std::slice::from_ref(cmd.as_ref()), options);
+                (1, len.max(1))
+            }
+            Self::Raw(cmd) => (cmd.split('\n').count(), 1),
+            Self::Return(_)
+            | Self::MacroLine(_)
+            | Self::Comment(_)
+            | Self::Debug(_)
+            | Self::Schedule(_) => {
+                let count = self.get_count(options);
+                (count, count)
+            }
+        }
+    }
+
+    /// Get the number of strings the command compiles into, without compiling it.
+    fn compiled_len(&self, options: &CompileOptions) -> usize {
+        self.counts(options).1
+    }
+
     /// Check whether the command is valid with the given pack format.
     #[must_use]
     pub fn validate(&self, pack_formats: &RangeInclusive<u8>) -> bool {
+        self.incompatibilities(pack_formats).is_empty()
+    }
+
+    /// Get the reasons why the command is not valid with the given pack formats.
+    ///
+    /// Commands in groups are checked as well.
+    #[must_use]
+    pub fn incompatibilities(&self, pack_formats: &RangeInclusive<u8>) -> Vec<String> {
         match self {
-            Self::Comment(_) | Self::Debug(_) | Self::Group(_) => true,
-            Self::Raw(cmd) => validate_raw_cmd(cmd, pack_formats),
-            Self::Execute(ex) => ex.validate(pack_formats),
+            Self::Comment(_) | Self::Debug(_) => Vec::new(),
+            Self::Group(commands) => commands
+                .iter()
+                .flat_map(|cmd| cmd.incompatibilities(pack_formats))
+                .collect(),
+            Self::Raw(cmd) => raw_cmd_incompatibility(cmd, pack_formats)
+                .into_iter()
+                .chain(
+                    is_macro_function_call(cmd)
+                        .then(|| {
+                            require_format(
+                                "calling functions with macro arguments",
+                                18,
+                                pack_formats,
+                            )
+                        })
+                        .flatten(),
+                )
+                .collect(),
+            Self::MacroLine(line) => require_format("macro lines", 18, pack_formats)
+                .into_iter()
+                .chain(
+                    (!validate_macro_line(line))
+                        .then(|| format!("macro line `{line}` has invalid argument names")),
+                )
+                .chain(raw_cmd_incompatibility(line, pack_formats))
+                .collect(),
+            Self::Execute(ex) => ex.incompatibilities(pack_formats),
+            Self::Schedule(schedule) => {
+                // the `append` and `replace` modes and `schedule clear` were added in 1.15
+                let syntax = match schedule {
+                    Schedule::Function { mode, .. } => {
+                        format!("command `schedule` with mode `{mode}`")
+                    }
+                    Schedule::Clear(_) => "command `schedule clear`".to_string(),
+                };
+                require_format(&syntax, 5, pack_formats)
+                    .into_iter()
+                    .collect()
+            }
+            Self::Return(value) => {
+                let (syntax, format) = match value {
+                    ReturnValue::Value(_) => ("command `return`", 15),
+                    ReturnValue::Run(_) => ("command `return run`", 18),
+                    ReturnValue::Fail => ("command `return fail`", 26),
+                };
+                let mut reasons = require_format(syntax, format, pack_formats)
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                if let ReturnValue::Run(cmd) = value {
+                    reasons.extend(cmd.incompatibilities(pack_formats));
+                }
+                reasons
+            }
+        }
+    }
+}
+
+/// Source of the arguments for the macro lines of a function, see [`Command::function_with`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MacroArgs {
+    /// Compound tag in a storage.
+    Storage {
+        /// Id of the storage, e.g. `ns:storage`.
+        resource: String,
+        /// Path of the compound tag in the storage, the root if not set.
+        path: Option<String>,
+    },
+    /// Compound tag of an entity.
+    Entity {
+        /// Selector of the entity, e.g. `@s`.
+        selector: String,
+        /// Path of the compound tag in the entity data, the root if not set.
+        path: Option<String>,
+    },
+    /// Compound tag of a block entity.
+    Block {
+        /// Position of the block entity, e.g. `~ ~-1 ~`.
+        pos: String,
+        /// Path of the compound tag in the block entity data, the root if not set.
+        path: Option<String>,
+    },
+    /// Inline compound tag, e.g. `{name:"foo"}`, see [`MacroArgs::json`].
+    Compound(String),
+}
+
+impl MacroArgs {
+    /// Compile the arguments into the part of the function command following the function name.
+    #[must_use]
+    pub fn compile(&self) -> String {
+        let (kind, source, path) = match self {
+            Self::Storage { resource, path } => ("storage", resource, path),
+            Self::Entity { selector, path } => ("entity", selector, path),
+            Self::Block { pos, path } => ("block", pos, path),
+            Self::Compound(compound) => return compound.clone(),
+        };
+        path.as_ref().map_or_else(
+            || format!("with {kind} {source}"),
+            |path| format!("with {kind} {source} {path}"),
+        )
+    }
+
+    /// Create inline arguments from the JSON object, converting it into a compound tag.
+    ///
+    /// Returns `None` if the value is not an object.
+    #[must_use]
+    pub fn json(value: &serde_json::Value) -> Option<Self> {
+        value
+            .is_object()
+            .then(|| Self::Compound(json_to_snbt(value)))
+    }
+}
+
+/// Convert the JSON value into SNBT.
+///
+/// Keys are only quoted if necessary and `null` values are omitted, as they have no representation in NBT.
+fn json_to_snbt(value: &serde_json::Value) -> String {
+    use serde_json::Value;
+
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => Value::String(s.clone()).to_string(),
+        Value::Array(values) => format!(
+            "[{}]",
+            values
+                .iter()
+                .filter(|value| !value.is_null())
+                .map(json_to_snbt)
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        Value::Object(entries) => format!(
+            "{{{}}}",
+            entries
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| {
+                    let unquoted = !key.is_empty()
+                        && key.chars().all(|c| {
+                            c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+                        });
+                    if unquoted {
+                        format!("{key}:{}", json_to_snbt(value))
+                    } else {
+                        format!("{}:{}", Value::String(key.clone()), json_to_snbt(value))
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+    }
+}
+
+/// Value returned by [`Command::Return`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ReturnValue {
+    /// Return the value, requires pack format 15.
+    Value(i32),
+    /// Return with a failure, requires pack format 26.
+    Fail,
+    /// Return the result of the command, requires pack format 18.
+    Run(Box<Command>),
+}
+
+/// Schedule of a function, see [`Command::Schedule`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Schedule {
+    /// Run the function after the time.
+    Function {
+        /// Id of the function, e.g. `ns:name`.
+        function: String,
+        /// Time after which the function is run.
+        time: ScheduleTime,
+        /// How existing schedules of the function are handled.
+        mode: ScheduleMode,
+    },
+    /// Clear all schedules of the function with the id.
+    Clear(String),
+}
+
+impl Schedule {
+    /// Compile the schedule into a `schedule` command.
+    #[must_use]
+    pub fn compile(&self) -> String {
+        match self {
+            Self::Function {
+                function,
+                time,
+                mode,
+            } => format!("schedule function {function} {time} {mode}"),
+            Self::Clear(function) => format!("schedule clear {function}"),
+        }
+    }
+}
+
+/// Time after which a scheduled function is run.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScheduleTime {
+    /// Time in game ticks.
+    Ticks(u32),
+    /// Time in seconds, 20 ticks each.
+    Seconds(u32),
+    /// Time in in-game days, 24000 ticks each.
+    Days(u32),
+}
+
+impl std::fmt::Display for ScheduleTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ticks(ticks) => write!(f, "{ticks}t"),
+            Self::Seconds(seconds) => write!(f, "{seconds}s"),
+            Self::Days(days) => write!(f, "{days}d"),
+        }
+    }
+}
+
+/// Handling of existing schedules when scheduling a function.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ScheduleMode {
+    /// Keep existing schedules of the function.
+    Append,
+    /// Replace existing schedules of the function.
+    #[default]
+    Replace,
+}
+
+impl std::fmt::Display for ScheduleMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Append => write!(f, "append"),
+            Self::Replace => write!(f, "replace"),
         }
     }
 }
@@ -83,26 +435,208 @@ impl From<&str> for Command {
 }
 impl From<&Function> for Command {
     fn from(value: &Function) -> Self {
-        Self::Raw(format!("function {}:{}", value.namespace(), value.name()))
+        Self::Raw(function_call(value))
     }
 }
 impl From<&mut Function> for Command {
     fn from(value: &mut Function) -> Self {
-        Self::Raw(format!("function {}:{}", value.namespace(), value.name()))
+        Self::Raw(function_call(value))
     }
 }
 
+/// Create the `function` command calling the function without arguments.
+fn function_call(function: &Function) -> String {
+    format!("function {}:{}", function.namespace(), function.name())
+}
+
 fn compile_debug(message: &str, option: &CompileOptions) -> Vec<String> {
-    if option.debug {
+    if option.debug && !option.minify {
+        let DebugFormat {
+            label,
+            label_color,
+            bracket_color,
+            message_color,
+            target,
+        } = &option.debug_format;
+        let hover = HoverEvent::ShowText(vec![
+            TextComponent::new("Debug message generated by Shulkerbox"),
+            TextComponent::new("\nSet debug message to 'false' to disable"),
+        ]);
+        let components = [
+            TextComponent::new("[").with_color(bracket_color),
+            TextComponent::new(label)
+                .with_color(label_color)
+                .with_hover_event(hover),
+            TextComponent::new("]").with_color(bracket_color),
+            TextComponent::new(&format!(" {message}")).with_color(message_color),
+        ];
         vec![format!(
-            r#"tellraw @a [{{"text":"[","color":"dark_blue"}},{{"text":"DEBUG","color":"dark_green","hoverEvent":{{"action":"show_text","value":[{{"text":"Debug message generated by Shulkerbox"}},{{"text":"\nSet debug message to 'false' to disable"}}]}}}},{{"text":"]","color":"dark_blue"}},{{"text":" {}","color":"black"}}]"#,
-            message
+            "tellraw {selector} {}",
+            TextComponent::list_to_json(&components),
+            selector = target.selector(),
         )]
     } else {
         Vec::new()
     }
 }
 
+/// Prefix the line with `$` if it contains macro placeholders.
+fn compile_macro_line(line: &str) -> String {
+    if line.contains("$(") {
+        format!("${line}")
+    } else {
+        line.to_string()
+    }
+}
+
+/// Check whether the placeholders of the macro line are closed and have valid names.
+fn validate_macro_line(line: &str) -> bool {
+    line.split("$(").skip(1).all(|part| {
+        part.split_once(')').is_some_and(|(name, _)| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+    })
+}
+
+/// Check whether the command calls a function with macro arguments.
+fn is_macro_function_call(cmd: &str) -> bool {
+    let mut parts = cmd.split_ascii_whitespace();
+    parts.next() == Some("function") && parts.nth(1).is_some()
+}
+
+fn compile_return(
+    value: &ReturnValue,
+    options: &CompileOptions,
+    global_state: &MutCompilerState,
+    function_state: &FunctionCompilerState,
+) -> Vec<String> {
+    match value {
+        ReturnValue::Value(value) => vec![format!("return {value}")],
+        ReturnValue::Fail => vec!["return fail".to_string()],
+        ReturnValue::Run(cmd) => {
+            // only a single command can be run, so multiple commands are grouped
+            let compiled = compile_group(
+                std::slice::from_ref(cmd.as_ref()),
+                options,
+                global_state,
+                function_state,
+            );
+            if compiled.iter().all(|cmd| cmd.starts_with('#')) {
+                // the command compiles to nothing, but the function still has to return
+                return vec!["return 0".to_string()];
+            }
+            compiled
+                .into_iter()
+                .map(|cmd| {
+                    if cmd.starts_with('#') {
+                        cmd
+                    } else {
+                        format!("return run {cmd}")
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+/// Check whether the compiled command returns from its function, e.g. `return 1` or `execute if entity @s run return fail`.
+///
+/// Macro lines are checked like the command they expand to.
+pub(super) fn is_return_line(line: &str) -> bool {
+    let line = line.strip_prefix('$').unwrap_or(line).trim_start();
+    match line.split_ascii_whitespace().next() {
+        Some("return") => true,
+        Some("execute") => line
+            .split_once(" run ")
+            .is_some_and(|(_, command)| is_return_line(command)),
+        _ => false,
+    }
+}
+
+/// Calculate a hash that is unique among all hashes requested while compiling the datapack.
+///
+/// The hash is calculated from the namespace and the full path of the current function
+/// and an id from a counter shared by the whole compilation. Only truncating the hash can lead to collisions.
+/// With the `parallel` feature, each namespace continues counting from the same id, so the names do not depend
+/// on the order the namespaces are compiled in.
+pub(super) fn unique_hash(
+    options: &CompileOptions,
+    global_state: &MutCompilerState,
+    function_state: &FunctionCompilerState,
+) -> String {
+    let namespace = function_state.namespace();
+    let uid = global_state
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .request_uid();
+
+    let pre_hash = format!("{namespace}:{path}:{uid}", path = function_state.path());
+    let mut hash = md5::hash(pre_hash).to_hex_lowercase();
+    hash.truncate(options.generated_hash_length);
+    hash
+}
+
+/// Strip all leading generated subfolders from the path,
+/// so functions generated from generated functions are nested under a single generated subfolder.
+fn strip_generated_subfolder<'a>(mut path: &'a str, subfolder: &str) -> &'a str {
+    while let Some(stripped) = path
+        .strip_prefix(subfolder)
+        .and_then(|path| path.strip_prefix('/'))
+    {
+        path = stripped;
+    }
+    path
+}
+
+/// Create a function with the commands in the generated subfolder and return its id.
+///
+/// The path of the function is built from the path of the function it is generated from and a [`unique_hash`].
+pub(super) fn generate_function(
+    commands: Vec<Command>,
+    options: &CompileOptions,
+    global_state: &MutCompilerState,
+    function_state: &FunctionCompilerState,
+) -> String {
+    let hash = unique_hash(options, global_state, function_state);
+
+    let generated_namespace = options
+        .generated_namespace
+        .as_deref()
+        .filter(|namespace| *namespace != function_state.namespace());
+
+    // calculate a hashed path for the function in the generated subfolder
+    let function_path = {
+        let subfolder = options.generated_subfolder.as_str();
+        let function_path = strip_generated_subfolder(function_state.path(), subfolder);
+
+        // include the original namespace to avoid collisions in the generated namespace
+        if generated_namespace.is_some() {
+            format!(
+                "{subfolder}/{namespace}/{function_path}/{hash}",
+                namespace = function_state.namespace()
+            )
+        } else {
+            format!("{subfolder}/{function_path}/{hash}")
+        }
+    };
+
+    let namespace = generated_namespace.unwrap_or(function_state.namespace());
+
+    // create a new function with the commands
+    let mut function = Function::new(namespace, &function_path);
+    function.get_commands_mut().extend(commands);
+    if generated_namespace.is_some() {
+        global_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .add_generated_function(&function_path, function);
+    } else {
+        function_state.add_function(&function_path, function);
+    }
+
+    format!("{namespace}:{function_path}")
+}
+
 #[tracing::instrument(skip_all, fields(commands = ?commands))]
 fn compile_group(
     commands: &[Command],
@@ -110,45 +644,115 @@ fn compile_group(
     global_state: &MutCompilerState,
     function_state: &FunctionCompilerState,
 ) -> Vec<String> {
+    let commands = flatten_groups(commands);
     let command_count = commands
         .iter()
         .map(|cmd| cmd.get_count(options))
         .sum::<usize>();
     // only create a function if there are more than one command
     if command_count > 1 {
-        let uid = function_state.request_uid();
+        let id = generate_function(commands, options, global_state, function_state);
+        vec![format!("function {id}")]
+    } else {
+        commands
+            .iter()
+            .flat_map(|cmd| cmd.compile(options, global_state, function_state))
+            .collect::<Vec<_>>()
+    }
+}
 
-        // calculate a hashed path for the function in the `sb` subfolder
-        let function_path = {
-            let function_path = function_state.path();
-            let function_path = function_path.strip_prefix("sb/").unwrap_or(function_path);
+/// Get the number of strings the group compiles into, without compiling it.
+fn group_len(commands: &[Command], options: &CompileOptions) -> usize {
+    group_counts(commands, options).1
+}
 
-            let pre_hash_path = function_path.to_owned() + ":" + &uid.to_string();
-            let hash = md5::hash(pre_hash_path).to_hex_lowercase();
+/// Get the count of the commands in the group after flattening it and the number of strings the group compiles into.
+fn group_counts(commands: &[Command], options: &CompileOptions) -> (usize, usize) {
+    let (count, len) = flatten_groups(commands)
+        .iter()
+        .map(|cmd| cmd.counts(options))
+        .fold((0, 0), |(count, len), (cmd_count, cmd_len)| {
+            (count + cmd_count, len + cmd_len)
+        });
+    // groups of multiple commands compile into a single function call
+    (count, if count > 1 { 1 } else { len })
+}
 
-            "sb/".to_string() + function_path + "/" + &hash[..16]
-        };
+/// Inline the commands of nested groups into the group, so they do not need their own function.
+///
+/// Nested groups containing return commands are kept, as a return inside of them only
+/// returns from the function of the nested group. Raw commands and macro lines are checked with [`is_return_line`].
+fn flatten_groups(commands: &[Command]) -> Vec<Command> {
+    #[derive(Default)]
+    struct ReturnFinder(bool);
 
-        let namespace = function_state.namespace();
+    impl CommandVisitor for ReturnFinder {
+        fn visit_raw(&mut self, command: &str) {
+            self.0 |= command.lines().any(is_return_line);
+        }
 
-        // create a new function with the commands
-        let mut function = Function::new(namespace, &function_path);
-        function.get_commands_mut().extend(commands.iter().cloned());
-        function_state.add_function(&function_path, function);
+        fn visit_macro_line(&mut self, line: &str) {
+            self.0 |= is_return_line(line);
+        }
 
-        vec![format!("function {namespace}:{function_path}")]
-    } else {
-        commands
-            .iter()
-            .flat_map(|cmd| cmd.compile(options, global_state, function_state))
-            .collect::<Vec<_>>()
+        fn visit_return(&mut self, _value: &ReturnValue) {
+            self.0 = true;
+        }
     }
+
+    commands
+        .iter()
+        .flat_map(|cmd| match cmd {
+            Command::Group(nested) => {
+                let mut finder = ReturnFinder::default();
+                cmd.visit(&mut finder);
+                if finder.0 {
+                    vec![cmd.clone()]
+                } else {
+                    flatten_groups(nested)
+                }
+            }
+            cmd => vec![cmd.clone()],
+        })
+        .collect()
+}
+
+/// Get the reason why a feature is not available if the pack formats start below the minimum format.
+fn require_format(
+    feature: &str,
+    min_format: u8,
+    pack_formats: &RangeInclusive<u8>,
+) -> Option<String> {
+    (*pack_formats.start() < min_format)
+        .then(|| format!("{feature} requires pack format >= {min_format}"))
+}
+
+/// Get the reason why the raw command is not valid with the given pack formats.
+fn raw_cmd_incompatibility(cmd: &str, pack_formats: &RangeInclusive<u8>) -> Option<String> {
+    let name = cmd.split_ascii_whitespace().next()?;
+    let range = cmd_formats().get(name)?;
+    let valid = range.start() <= pack_formats.start() && range.end() >= pack_formats.end();
+
+    (!valid).then(|| {
+        if *range.end() == Datapack::LATEST_FORMAT {
+            format!("command `{name}` requires pack format >= {}", range.start())
+        } else if *range.start() == 0 {
+            format!("command `{name}` requires pack format <= {}", range.end())
+        } else {
+            format!(
+                "command `{name}` requires pack format {} to {}",
+                range.start(),
+                range.end()
+            )
+        }
+    })
 }
 
+/// Get the pack formats the commands are available in.
 #[allow(clippy::too_many_lines)]
-fn validate_raw_cmd(cmd: &str, pack_formats: &RangeInclusive<u8>) -> bool {
+fn cmd_formats() -> &'static HashMap<&'static str, RangeInclusive<u8>> {
     static CMD_FORMATS: OnceLock<HashMap<&str, RangeInclusive<u8>>> = OnceLock::new();
-    let cmd_formats = CMD_FORMATS.get_or_init(|| {
+    CMD_FORMATS.get_or_init(|| {
         const LATEST: u8 = Datapack::LATEST_FORMAT;
         const ANY: RangeInclusive<u8> = 0..=LATEST;
         const fn to(to: u8) -> RangeInclusive<u8> {
@@ -253,18 +857,6 @@ fn validate_raw_cmd(cmd: &str, pack_formats: &RangeInclusive<u8>) -> bool {
         map.insert("transfer", from(41));
 
         map
-    });
-
-    cmd.split_ascii_whitespace().next().map_or(true, |cmd| {
-        cmd_formats.get(cmd).map_or(true, |range| {
-            let start_cmd = range.start();
-            let end_cmd = range.end();
-
-            let start_pack = pack_formats.start();
-            let end_pack = pack_formats.end();
-
-            start_cmd <= start_pack && end_cmd >= end_pack
-        })
     })
 }
 
@@ -309,7 +901,442 @@ mod tests {
             comment.compile(options, global_state, function_state),
             vec!["#this is a comment".to_string()]
         );
-        assert_eq!(comment.get_count(options), 0);
+        assert_eq!(comment.get_count(options), 1);
+    }
+
+    #[test]
+    fn test_comment_group() {
+        let group = Command::Group(vec![
+            Command::Comment("comment".to_string()),
+            "say hi".into(),
+        ]);
+        let run_if = Command::Execute(Execute::If(
+            Condition::from("entity @s[tag=foo]"),
+            Box::new(Execute::Runs(vec![
+                Command::Comment("comment".to_string()),
+                "say hi".into(),
+            ])),
+            None,
+        ));
+
+        let global_state = &Mutex::new(CompilerState::default());
+        let queue = crate::util::ExtendableQueue::default();
+        let function_state = &FunctionCompilerState::new("foo", "bar", queue.clone());
+
+        // the emitted comment counts as a line, so the group needs a function
+        let options = &CompileOptions::default();
+        assert_eq!(group.get_count(options), 1);
+        let compiled = group.compile(options, global_state, function_state);
+        assert_eq!(compiled.len(), 1);
+        assert!(compiled[0].starts_with("function bar:sb/foo/"));
+        let compiled = run_if.compile(options, global_state, function_state);
+        assert_eq!(compiled.len(), 1);
+        assert!(compiled[0].starts_with("execute if entity @s[tag=foo] run function bar:sb/foo/"));
+        assert_eq!(queue.len(), 2);
+
+        // the comment is omitted when minifying, so the command is inlined
+        let options = &CompileOptions::default().with_minify(true);
+        assert_eq!(
+            group.compile(options, global_state, function_state),
+            vec!["say hi".to_string()]
+        );
+        assert_eq!(
+            run_if.compile(options, global_state, function_state),
+            vec!["execute if entity @s[tag=foo] run say hi".to_string()]
+        );
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn test_debug_format() {
+        let debug = Command::Debug("hello".to_string());
+
+        let global_state = &Mutex::new(CompilerState::default());
+        let function_state = &FunctionCompilerState::default();
+
+        let default = debug.compile(&CompileOptions::default(), global_state, function_state);
+        assert_eq!(
+            default,
+            vec![
+                r#"tellraw @a [{"text":"[","color":"dark_blue"},{"text":"DEBUG","color":"dark_green","hoverEvent":{"action":"show_text","value":[{"text":"Debug message generated by Shulkerbox"},{"text":"\nSet debug message to 'false' to disable"}]}},{"text":"]","color":"dark_blue"},{"text":" hello","color":"black"}]"#
+            ]
+        );
+        assert!(default[0].starts_with("tellraw @a "));
+        assert!(default[0].contains(r#"{"text":"DEBUG","color":"dark_green""#));
+
+        let options = CompileOptions::default().with_debug_format(
+            DebugFormat::default()
+                .with_label("MYPACK")
+                .with_label_color("gold")
+                .with_target(crate::util::compile::DebugTarget::Executor),
+        );
+        let custom = debug.compile(&options, global_state, function_state);
+        assert_eq!(custom.len(), 1);
+        assert!(custom[0].starts_with("tellraw @s "));
+        assert!(custom[0].contains(r#"{"text":"MYPACK","color":"gold""#));
+        assert!(custom[0].contains(r#"{"text":" hello","color":"black"}"#));
+
+        let disabled = debug.compile(&options.with_debug(false), global_state, function_state);
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn test_generated_subfolder() {
+        let group = Command::Group(vec!["say 1".into(), "say 2".into()]);
+
+        let options = &CompileOptions::default().with_generated_subfolder("zzz_generated");
+        let global_state = &Mutex::new(CompilerState::default());
+        let queue = crate::util::ExtendableQueue::default();
+        let function_state = &FunctionCompilerState::new("foo", "bar", queue.clone());
+
+        let compiled = group.compile(options, global_state, function_state);
+        assert_eq!(compiled.len(), 1);
+        assert!(compiled[0].starts_with("function bar:zzz_generated/foo/"));
+
+        let (path, _) = queue.pop_front().expect("no helper function generated");
+        let nested_state = &FunctionCompilerState::new(&path, "bar", queue);
+        let compiled = group.compile(options, global_state, nested_state);
+        assert!(compiled[0].starts_with(&format!("function bar:{path}/")));
+
+        let options = &options.clone().with_generated_hash_length(8);
+        let compiled = group.compile(options, global_state, function_state);
+        let (_, hash) = compiled[0].rsplit_once('/').unwrap();
+        assert_eq!(hash.len(), 8);
+    }
+
+    #[test]
+    fn test_flatten_groups() {
+        let options = &CompileOptions::default();
+        let global_state = &Mutex::new(CompilerState::default());
+        let compile = |group: &Command| {
+            let queue = crate::util::ExtendableQueue::default();
+            let function_state = &FunctionCompilerState::new("foo", "bar", queue.clone());
+            let compiled = group.compile(options, global_state, function_state);
+            (compiled, queue)
+        };
+
+        let flat = Command::Group(vec!["say 1".into(), "say 2".into(), "say 3".into()]);
+        let nested = Command::Group(vec![
+            Command::Group(vec!["say 1".into(), Command::Group(vec!["say 2".into()])]),
+            "say 3".into(),
+        ]);
+        for group in [&flat, &nested] {
+            let (compiled, queue) = compile(group);
+            assert_eq!(compiled.len(), 1);
+            assert_eq!(queue.len(), 1);
+            let (_, function) = queue.pop_front().unwrap();
+            assert_eq!(
+                function.get_commands(),
+                &vec![
+                    Command::raw("say 1"),
+                    Command::raw("say 2"),
+                    Command::raw("say 3")
+                ]
+            );
+        }
+
+        let single = Command::Group(vec![Command::Group(vec!["say 1".into()])]);
+        let (compiled, queue) = compile(&single);
+        assert_eq!(compiled, vec!["say 1".to_string()]);
+        assert!(queue.is_empty());
+
+        let returning = Command::Group(vec![
+            Command::Group(vec!["say 1".into(), Command::Return(ReturnValue::Value(1))]),
+            "say 2".into(),
+        ]);
+        let (_, queue) = compile(&returning);
+        let (_, function) = queue.pop_front().unwrap();
+        assert_eq!(function.get_commands().len(), 2);
+        assert!(matches!(function.get_commands()[0], Command::Group(_)));
+
+        for returning_command in [
+            Command::raw("return 1"),
+            Command::raw("say 0\nreturn fail"),
+            Command::MacroLine("return $(value)".to_string()),
+            Command::raw("execute if entity @s run return 1"),
+            Command::Execute(Execute::If(
+                Condition::from("entity @s"),
+                Box::new(Execute::Run(Box::new(Command::raw("return run say 1")))),
+                None,
+            )),
+        ] {
+            let returning = Command::Group(vec![
+                Command::Group(vec!["say 1".into(), returning_command.clone()]),
+                "say 2".into(),
+            ]);
+            let (_, queue) = compile(&returning);
+            let (_, function) = queue.pop_front().unwrap();
+            assert_eq!(
+                function.get_commands().len(),
+                2,
+                "{returning_command:?} was inlined"
+            );
+            assert!(matches!(function.get_commands()[0], Command::Group(_)));
+        }
+
+        assert!(is_return_line("$execute as @a run return run say $(x)"));
+        assert!(!is_return_line("say return"));
+        assert!(!is_return_line(
+            "execute if function foo:returning run say 1"
+        ));
+    }
+
+    #[test]
+    fn test_text_commands() {
+        let component = TextComponent::new("hi").with_color("red");
+
+        assert_eq!(
+            Command::tellraw("@a", &component),
+            Command::raw(r#"tellraw @a {"text":"hi","color":"red"}"#)
+        );
+        assert_eq!(
+            Command::title("@s", &component),
+            Command::raw(r#"title @s title {"text":"hi","color":"red"}"#)
+        );
+    }
+
+    #[test]
+    fn test_return() {
+        let options = &CompileOptions::default();
+        let global_state = &Mutex::new(CompilerState::default());
+        let queue = crate::util::ExtendableQueue::default();
+        let function_state = &FunctionCompilerState::new("foo", "bar", queue.clone());
+
+        let value = Command::Return(ReturnValue::Value(3));
+        assert_eq!(
+            value.compile(options, global_state, function_state),
+            vec!["return 3".to_string()]
+        );
+        assert_eq!(
+            Command::Return(ReturnValue::Fail).compile(options, global_state, function_state),
+            vec!["return fail".to_string()]
+        );
+
+        let run = Command::Return(ReturnValue::Run(Box::new("say hi".into())));
+        assert_eq!(
+            run.compile(options, global_state, function_state),
+            vec!["return run say hi".to_string()]
+        );
+        assert_eq!(run.get_count(options), 1);
+        assert!(queue.is_empty());
+
+        let group = Command::Return(ReturnValue::Run(Box::new(Command::Group(vec![
+            "say 1".into(),
+            "say 2".into(),
+        ]))));
+        let compiled = group.compile(options, global_state, function_state);
+        assert_eq!(compiled.len(), 1);
+        assert!(compiled[0].starts_with("return run function bar:sb/foo/"));
+        assert_eq!(group.get_count(options), 1);
+        assert_eq!(queue.len(), 1);
+
+        assert!(value.validate(&(15..=48)));
+        assert!(!value.validate(&(14..=48)));
+        let fail = Command::Return(ReturnValue::Fail);
+        let say = Command::Return(ReturnValue::Run(Box::new("say hi".into())));
+        assert!(value.validate(&(15..=17)));
+        assert!(!say.validate(&(15..=17)));
+        assert!(!fail.validate(&(15..=17)));
+        assert!(say.validate(&(18..=25)));
+        assert!(!fail.validate(&(18..=25)));
+        assert!(fail.validate(&(26..=48)));
+        let transfer = Command::Return(ReturnValue::Run(Box::new("transfer foo".into())));
+        assert!(transfer.validate(&(41..=48)));
+        assert!(!transfer.validate(&(15..=48)));
+    }
+
+    #[test]
+    fn test_return_run_nothing() {
+        let global_state = &Mutex::new(CompilerState::default());
+        let function_state = &FunctionCompilerState::default();
+
+        let cases = [
+            (
+                Command::Debug("hi".to_string()),
+                CompileOptions::default().with_debug(false),
+            ),
+            (
+                Command::Comment("hi".to_string()),
+                CompileOptions::default().with_minify(true),
+            ),
+            (Command::Group(Vec::new()), CompileOptions::default()),
+        ];
+        for (cmd, options) in cases {
+            // the function still returns, even though the command compiles to nothing
+            let ret = Command::Return(ReturnValue::Run(Box::new(cmd)));
+            assert_eq!(
+                ret.compile(&options, global_state, function_state),
+                vec!["return 0".to_string()]
+            );
+            assert_eq!(ret.get_count(&options), 1);
+        }
+    }
+
+    #[test]
+    fn test_schedule() {
+        let options = &CompileOptions::default();
+        let global_state = &Mutex::new(CompilerState::default());
+        let function_state = &FunctionCompilerState::default();
+
+        let append = Command::schedule("foo:bar", ScheduleTime::Seconds(5), ScheduleMode::Append);
+        assert_eq!(
+            append.compile(options, global_state, function_state),
+            vec!["schedule function foo:bar 5s append".to_string()]
+        );
+
+        let replace = Command::schedule("foo:bar", ScheduleTime::Ticks(20), ScheduleMode::Replace);
+        assert_eq!(
+            replace.compile(options, global_state, function_state),
+            vec!["schedule function foo:bar 20t replace".to_string()]
+        );
+        assert_eq!(
+            Command::schedule("foo:bar", ScheduleTime::Days(1), ScheduleMode::default()).compile(
+                options,
+                global_state,
+                function_state
+            ),
+            vec!["schedule function foo:bar 1d replace".to_string()]
+        );
+
+        let clear = Command::schedule_clear("foo:bar");
+        assert_eq!(
+            clear.compile(options, global_state, function_state),
+            vec!["schedule clear foo:bar".to_string()]
+        );
+        assert_eq!(clear.get_count(options), 1);
+
+        assert!(replace.validate(&(5..=48)));
+        assert!(append.validate(&(5..=5)));
+        assert!(clear.validate(&(5..=48)));
+        assert_eq!(
+            append.incompatibilities(&(4..=48)),
+            vec!["command `schedule` with mode `append` requires pack format >= 5".to_string()]
+        );
+        assert!(!replace.validate(&(4..=4)));
+        assert_eq!(
+            clear.incompatibilities(&(4..=48)),
+            vec!["command `schedule clear` requires pack format >= 5".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_macro_line() {
+        let options = &CompileOptions::default();
+        let global_state = &Mutex::new(CompilerState::default());
+        let function_state = &FunctionCompilerState::default();
+
+        let line = Command::MacroLine("say $(name) joined".to_string());
+        assert_eq!(
+            line.compile(options, global_state, function_state),
+            vec!["$say $(name) joined".to_string()]
+        );
+        assert!(line.validate(&(18..=48)));
+        assert!(!line.validate(&(17..=48)));
+
+        let plain = Command::MacroLine("say hi".to_string());
+        assert_eq!(
+            plain.compile(options, global_state, function_state),
+            vec!["say hi".to_string()]
+        );
+
+        assert!(!Command::MacroLine("say $(na-me)".to_string()).validate(&(18..=48)));
+        assert!(!Command::MacroLine("say $(name".to_string()).validate(&(18..=48)));
+        assert!(!Command::MacroLine("say $()".to_string()).validate(&(18..=48)));
+    }
+
+    #[test]
+    fn test_unique_hash() {
+        let options = &CompileOptions::default();
+        let global_state = &Mutex::new(CompilerState::default());
+        let first = FunctionCompilerState::default();
+        let second = FunctionCompilerState::default();
+
+        // function states with the same path share the counter of the compilation
+        let first_hash = unique_hash(options, global_state, &first);
+        let second_hash = unique_hash(options, global_state, &second);
+        assert_ne!(first_hash, second_hash);
+        assert_eq!(global_state.lock().unwrap().request_uid(), 2);
+    }
+
+    #[test]
+    fn test_function_with() {
+        let call = Command::function_with(
+            "foo:bar",
+            &MacroArgs::Storage {
+                resource: "foo:args".to_string(),
+                path: None,
+            },
+        );
+        assert_eq!(call, Command::raw("function foo:bar with storage foo:args"));
+        assert!(call.validate(&(18..=48)));
+        assert!(!call.validate(&(17..=48)));
+        assert!(Command::raw("function foo:bar").validate(&(4..=48)));
+
+        let inline =
+            Command::function_with("foo:bar", &MacroArgs::Compound("{id: 1b}".to_string()));
+        assert_eq!(inline, Command::raw("function foo:bar {id: 1b}"));
+    }
+
+    #[test]
+    fn test_call_with() {
+        let function = Function::new("foo", "bar");
+
+        let storage = Command::call_with(
+            &function,
+            &MacroArgs::Storage {
+                resource: "foo:args".to_string(),
+                path: Some("call.args".to_string()),
+            },
+        );
+        assert_eq!(
+            storage,
+            Command::raw("function foo:bar with storage foo:args call.args")
+        );
+        assert!(storage.validate(&(18..=48)));
+        assert!(!storage.validate(&(17..=48)));
+
+        let entity = Command::call_with(
+            &function,
+            &MacroArgs::Entity {
+                selector: "@s".to_string(),
+                path: Some("data.args".to_string()),
+            },
+        );
+        assert_eq!(
+            entity,
+            Command::raw("function foo:bar with entity @s data.args")
+        );
+
+        let block = Command::call_with(
+            &function,
+            &MacroArgs::Block {
+                pos: "~ ~-1 ~".to_string(),
+                path: None,
+            },
+        );
+        assert_eq!(block, Command::raw("function foo:bar with block ~ ~-1 ~"));
+
+        let inline = Command::call_with(
+            &function,
+            &MacroArgs::json(&serde_json::json!({
+                "id": 1,
+                "name": "say \"hi\"",
+                "weird key": [true, null, 2.5],
+                "nested": { "none": null },
+            }))
+            .expect("object should be valid arguments"),
+        );
+        assert_eq!(
+            inline,
+            Command::raw(
+                r#"function foo:bar {id:1,name:"say \"hi\"",nested:{},"weird key":[true,2.5]}"#
+            )
+        );
+        assert!(!inline.validate(&(17..=48)));
+
+        assert_eq!(MacroArgs::json(&serde_json::Value::Null), None);
+        assert_eq!(MacroArgs::json(&serde_json::json!([1, 2])), None);
+        assert_eq!(MacroArgs::json(&serde_json::json!("foo")), None);
     }
 
     #[test]
@@ -0,0 +1,174 @@
+//! Traversal of command trees.
+
+use super::{Command, Condition, Execute, ReturnValue, Schedule};
+
+/// Visitor for traversing a command tree with [`Command::visit`] or [`Execute::visit`].
+///
+/// All hooks do nothing by default, so only the relevant ones have to be implemented.
+/// Nested commands are visited after the hook of their parent.
+pub trait CommandVisitor {
+    /// Called for each raw command.
+    fn visit_raw(&mut self, _command: &str) {}
+
+    /// Called for each debug message.
+    fn visit_debug(&mut self, _message: &str) {}
+
+    /// Called for each comment.
+    fn visit_comment(&mut self, _comment: &str) {}
+
+    /// Called for each group before its commands are visited.
+    fn visit_group(&mut self, _commands: &[Command]) {}
+
+    /// Called for each execute subcommand with its name and argument, e.g. `("as", "@a")`.
+    ///
+    /// Conditions and the commands to run are passed to [`CommandVisitor::visit_condition`]
+    /// and the other hooks instead.
+    fn visit_subcommand(&mut self, _name: &str, _argument: &str) {}
+
+    /// Called for the condition of each `if` subcommand before its branches are visited.
+    fn visit_condition(&mut self, _condition: &Condition) {}
+
+    /// Called for each macro line.
+    fn visit_macro_line(&mut self, _line: &str) {}
+
+    /// Called for each schedule command.
+    fn visit_schedule(&mut self, _schedule: &Schedule) {}
+
+    /// Called for each return command before the command to run is visited.
+    fn visit_return(&mut self, _value: &ReturnValue) {}
+}
+
+impl Command {
+    /// Traverse the command and all nested commands with the visitor.
+    pub fn visit<V: CommandVisitor>(&self, visitor: &mut V) {
+        match self {
+            Self::Raw(command) => visitor.visit_raw(command),
+            Self::Debug(message) => visitor.visit_debug(message),
+            Self::Comment(comment) => visitor.visit_comment(comment),
+            Self::Group(commands) => {
+                visitor.visit_group(commands);
+                for command in commands {
+                    command.visit(visitor);
+                }
+            }
+            Self::Execute(ex) => ex.visit(visitor),
+            Self::MacroLine(line) => visitor.visit_macro_line(line),
+            Self::Schedule(schedule) => visitor.visit_schedule(schedule),
+            Self::Return(value) => {
+                visitor.visit_return(value);
+                if let ReturnValue::Run(command) = value {
+                    command.visit(visitor);
+                }
+            }
+        }
+    }
+}
+
+impl Execute {
+    /// Traverse the execute command and all nested commands with the visitor.
+    pub fn visit<V: CommandVisitor>(&self, visitor: &mut V) {
+        match self {
+            Self::Align(arg, next)
+            | Self::Anchored(arg, next)
+            | Self::As(arg, next)
+            | Self::At(arg, next)
+            | Self::AsAt(arg, next)
+            | Self::Facing(arg, next)
+            | Self::In(arg, next)
+            | Self::On(arg, next)
+            | Self::Positioned(arg, next)
+            | Self::Rotated(arg, next)
+            | Self::Store(arg, next)
+            | Self::Summon(arg, next) => {
+                visitor.visit_subcommand(self.variant_name(), arg);
+                next.visit(visitor);
+            }
+            Self::If(cond, then, el) => {
+                visitor.visit_condition(cond);
+                then.visit(visitor);
+                if let Some(el) = el {
+                    el.visit(visitor);
+                }
+            }
+            Self::IfCached(cond, then, el) => {
+                visitor.visit_condition(cond.condition());
+                then.visit(visitor);
+                if let Some(el) = el {
+                    el.visit(visitor);
+                }
+            }
+            Self::Run(command) => command.visit(visitor),
+            Self::Runs(commands) => {
+                for command in commands {
+                    command.visit(visitor);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Collector {
+        functions: Vec<String>,
+        selectors: Vec<String>,
+        conditions: usize,
+    }
+
+    impl CommandVisitor for Collector {
+        fn visit_raw(&mut self, command: &str) {
+            if let Some(function) = command.strip_prefix("function ") {
+                self.functions.push(function.to_string());
+            }
+        }
+
+        fn visit_subcommand(&mut self, name: &str, argument: &str) {
+            if matches!(name, "as" | "as_at") {
+                self.selectors.push(argument.to_string());
+            }
+        }
+
+        fn visit_condition(&mut self, _condition: &Condition) {
+            self.conditions += 1;
+        }
+    }
+
+    #[test]
+    fn test_visit() {
+        let command = Command::Group(vec![
+            "function foo:a".into(),
+            Command::Execute(Execute::As(
+                "@a".to_string(),
+                Box::new(Execute::If(
+                    "entity @s[tag=foo]".into(),
+                    Box::new(Execute::Runs(vec![
+                        "function foo:b".into(),
+                        "say hi".into(),
+                    ])),
+                    None,
+                )),
+            )),
+            Command::Execute(Execute::If(
+                "block ~ ~ ~ stone".into(),
+                Box::new(Execute::AsAt(
+                    "@e".to_string(),
+                    Box::new(Execute::Run(Box::new("function foo:c".into()))),
+                )),
+                Some(Box::new(Execute::Run(Box::new("function foo:d".into())))),
+            )),
+        ]);
+
+        let mut collector = Collector::default();
+        command.visit(&mut collector);
+
+        assert_eq!(
+            collector.functions,
+            vec!["foo:a", "foo:b", "foo:c", "foo:d"]
+        );
+        assert_eq!(collector.selectors, vec!["@a", "@e"]);
+        assert_eq!(collector.conditions, 2);
+    }
+}
@@ -5,11 +5,13 @@ use std::ops::RangeInclusive;
 use getset::Getters;
 
 use crate::{
-    util::compile::{CompileOptions, FunctionCompilerState, MutCompilerState},
+    util::compile::{
+        report, CompileOptions, DiagnosticKind, FunctionCompilerState, MutCompilerState,
+    },
     virtual_fs::VFile,
 };
 
-use super::command::Command;
+use super::command::{generate_function, is_return_line, Command};
 
 /// Function that can be called by a command
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -22,21 +24,40 @@ pub struct Function {
     /// Namespace of the function
     #[get = "pub"]
     namespace: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    doc: Option<String>,
 }
 
 impl Function {
-    pub(in crate::datapack) fn new(namespace: &str, name: &str) -> Self {
+    /// Create a new, empty function.
+    #[must_use]
+    pub fn new(namespace: &str, name: &str) -> Self {
         Self {
             commands: Vec::new(),
             name: name.to_string(),
             namespace: namespace.to_string(),
+            doc: None,
         }
     }
+
+    /// Set the namespace of the function.
+    pub(in crate::datapack) fn set_namespace(&mut self, namespace: &str) {
+        self.namespace = namespace.to_string();
+    }
+
     /// Add a command to the function.
     pub fn add_command(&mut self, command: impl Into<Command>) {
         self.commands.push(command.into());
     }
 
+    /// Parse the content of a `.mcfunction` file and add the commands to the function.
+    ///
+    /// Lines starting with `#` are added as comments, lines starting with `$` as macro lines and blank lines are skipped.
+    /// All other lines are added as raw commands, with surrounding whitespace trimmed.
+    pub fn add_commands_from_str(&mut self, text: &str) {
+        self.commands.extend(parse_commands(text));
+    }
+
     /// Get the commands of the function.
     #[must_use]
     pub fn get_commands(&self) -> &Vec<Command> {
@@ -48,6 +69,28 @@ impl Function {
         &mut self.commands
     }
 
+    /// Get the documentation of the function.
+    #[must_use]
+    pub fn get_doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    /// Set the documentation of the function, which is emitted as comment lines at the top of the compiled function.
+    ///
+    /// Each line of the text becomes its own comment line. The documentation is omitted when minifying.
+    pub fn set_doc(&mut self, text: &str) {
+        self.doc = Some(text.to_string());
+    }
+
+    /// Remove consecutive duplicate commands.
+    ///
+    /// Only directly adjacent commands are compared. Execute commands are never removed,
+    /// as their conditions may depend on the previous command.
+    pub fn dedup_consecutive(&mut self) {
+        self.commands
+            .dedup_by(|cmd, prev| cmd == prev && !matches!(cmd, Command::Execute(_)));
+    }
+
     /// Compile the function into a virtual file.
     #[must_use]
     #[tracing::instrument(level = "trace", skip_all)]
@@ -59,15 +102,135 @@ impl Function {
     ) -> VFile {
         tracing::trace!("Compiling function '{}'", self.name);
 
-        let content = self
+        // keep the lines of each command together to only split functions on command boundaries
+        let mut commands = self
             .commands
             .iter()
-            .flat_map(|c| c.compile(options, global_state, function_state))
-            .collect::<Vec<String>>()
-            .join("\n");
+            .map(|c| {
+                let lines = c.compile(options, global_state, function_state);
+                if options.minify {
+                    lines
+                        .iter()
+                        .flat_map(|line| line.lines())
+                        .filter(|line| !line.trim().is_empty())
+                        .map(ToString::to_string)
+                        .collect()
+                } else {
+                    lines
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(doc) = self.doc.as_ref().filter(|_| !options.minify) {
+            let header = doc
+                .lines()
+                .map(|line| format!("# {line}").trim_end().to_string())
+                .collect();
+            commands.insert(0, header);
+        }
+
+        if let Some(max_lines) = options.max_function_lines {
+            self.split_continuation(
+                &mut commands,
+                max_lines,
+                options,
+                global_state,
+                function_state,
+            );
+        }
+
+        let content = commands.concat().join("\n");
+        if content.is_empty() {
+            report(
+                global_state,
+                DiagnosticKind::EmptyFunction,
+                format!("Function '{}:{}' is empty", self.namespace, self.name),
+            );
+        }
         VFile::Text(content)
     }
 
+    /// Move the compiled commands exceeding the maximum number of lines into a continuation function,
+    /// which is called in the last line.
+    ///
+    /// From pack format 18 on, the continuation is called with `return run`, so returns in it also return from this function.
+    /// Below, functions are not split if the moved commands contain returns.
+    fn split_continuation(
+        &self,
+        commands: &mut Vec<Vec<String>>,
+        max_lines: usize,
+        options: &CompileOptions,
+        global_state: &MutCompilerState,
+        function_state: &FunctionCompilerState,
+    ) {
+        // compiled commands may contain line breaks
+        let count_lines = |lines: &Vec<String>| {
+            lines
+                .iter()
+                .map(|line| line.split('\n').count())
+                .sum::<usize>()
+        };
+        if commands.iter().map(count_lines).sum::<usize>() <= max_lines {
+            return;
+        }
+        if commands.iter().flatten().any(|line| line.starts_with('$')) {
+            report(
+                global_state,
+                DiagnosticKind::OversizedFunction,
+                format!(
+                    "Function '{}:{}' exceeds the maximum number of lines but contains macro lines, so it is not split",
+                    self.namespace, self.name
+                ),
+            );
+            return;
+        }
+
+        // keep one line for calling the continuation, but at least one command
+        let mut kept_lines = 0;
+        let split_index = commands
+            .iter()
+            .position(|lines| {
+                kept_lines += count_lines(lines);
+                kept_lines > max_lines - 1
+            })
+            .unwrap_or(commands.len())
+            .max(1);
+
+        let returns = commands[split_index..]
+            .iter()
+            .flatten()
+            .flat_map(|line| line.split('\n'))
+            .any(is_return_line);
+        let return_run = options.pack_format >= 18;
+        if returns && !return_run {
+            report(
+                global_state,
+                DiagnosticKind::OversizedFunction,
+                format!(
+                    "Function '{}:{}' exceeds the maximum number of lines but returns after the split point, which requires pack format >= 18, so it is not split",
+                    self.namespace, self.name
+                ),
+            );
+            return;
+        }
+
+        let continuation = commands
+            .split_off(split_index)
+            .into_iter()
+            .filter(|lines| !lines.is_empty())
+            .map(|lines| Command::Raw(lines.join("\n")))
+            .collect::<Vec<_>>();
+        if !continuation.is_empty() {
+            let id = generate_function(continuation, options, global_state, function_state);
+            let call = if return_run {
+                format!("return run function {id}")
+            } else {
+                format!("function {id}")
+            };
+            commands.push(vec![call]);
+        }
+    }
+
     // Check whether the function is valid with the given pack format.
     #[must_use]
     pub fn validate(&self, pack_formats: &RangeInclusive<u8>) -> bool {
@@ -75,6 +238,21 @@ impl Function {
     }
 }
 
+/// Parse the content of a `.mcfunction` file into commands.
+/// Lines starting with `#` are turned into comments, lines starting with `$` into macro lines and empty lines are skipped.
+pub(super) fn parse_commands(content: &str) -> Vec<Command> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.chars().next() {
+            Some('#') => Command::Comment(line[1..].to_string()),
+            Some('$') => Command::MacroLine(line[1..].to_string()),
+            _ => Command::Raw(line.to_string()),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,4 +281,104 @@ mod tests {
             VFile::Text(content) if content == "say Hello, world!"
         ));
     }
+
+    #[test]
+    fn test_minify() {
+        let mut function = Function::new("namespace", "name");
+        function.add_command(Command::Comment("first".to_string()));
+        function.add_command("say 1");
+        function.add_command(Command::Comment("second".to_string()));
+        function.add_command(Command::Debug("debug".to_string()));
+        function.add_command("say 2\n");
+        function.add_command(Command::Comment("third".to_string()));
+
+        let options = &CompileOptions::default().with_minify(true);
+        let global_state = &Mutex::new(CompilerState::default());
+        let function_state = &FunctionCompilerState::default();
+
+        let compiled = function.compile(options, global_state, function_state);
+        assert_eq!(compiled.as_text(), Some("say 1\nsay 2"));
+
+        let compiled = function.compile(&CompileOptions::default(), global_state, function_state);
+        assert_eq!(compiled.as_text().map(|text| text.lines().count()), Some(7));
+    }
+
+    #[test]
+    fn test_add_commands_from_str() {
+        let script = "# setup\r\nsay 1   \r\n\r\n  say 2\n$say $(value)\n";
+        let mut function = Function::new("namespace", "name");
+        function.add_command("say 0");
+        function.add_commands_from_str(script);
+
+        assert_eq!(
+            function.get_commands(),
+            &vec![
+                Command::raw("say 0"),
+                Command::Comment(" setup".to_string()),
+                Command::raw("say 1"),
+                Command::raw("say 2"),
+                Command::MacroLine("say $(value)".to_string()),
+            ]
+        );
+
+        let compiled = function.compile(
+            &CompileOptions::default(),
+            &Mutex::new(CompilerState::default()),
+            &FunctionCompilerState::default(),
+        );
+        assert_eq!(
+            compiled.as_text(),
+            Some("say 0\n# setup\nsay 1\nsay 2\n$say $(value)")
+        );
+    }
+
+    #[test]
+    fn test_doc() {
+        let mut function = Function::new("namespace", "name");
+        function.set_doc("Does things.\n\nCalled every tick.");
+        function.add_command("say 1");
+        assert_eq!(
+            function.get_doc(),
+            Some("Does things.\n\nCalled every tick.")
+        );
+
+        let global_state = &Mutex::new(CompilerState::default());
+        let function_state = &FunctionCompilerState::default();
+
+        let compiled = function.compile(&CompileOptions::default(), global_state, function_state);
+        assert_eq!(
+            compiled.as_text(),
+            Some("# Does things.\n#\n# Called every tick.\nsay 1")
+        );
+
+        let options = &CompileOptions::default().with_minify(true);
+        let compiled = function.compile(options, global_state, function_state);
+        assert_eq!(compiled.as_text(), Some("say 1"));
+    }
+
+    #[test]
+    fn test_dedup_consecutive() {
+        let mut function = Function::new("namespace", "name");
+        let execute = Command::Execute(crate::datapack::Execute::Run(Box::new("say 2".into())));
+
+        function.add_command("say 1");
+        function.add_command("say 1");
+        function.add_command(execute.clone());
+        function.add_command(execute.clone());
+        function.add_command("say 1");
+        function.add_command("say 1");
+        function.add_command("say 1");
+
+        function.dedup_consecutive();
+
+        assert_eq!(
+            function.get_commands(),
+            &vec![
+                Command::raw("say 1"),
+                execute.clone(),
+                execute,
+                Command::raw("say 1")
+            ]
+        );
+    }
 }
@@ -0,0 +1,47 @@
+//! Loot tables and item modifiers.
+
+use serde_json::Value;
+
+use crate::virtual_fs::VFile;
+
+/// Loot table given as its JSON object.
+///
+/// The default loot table has no pools and drops nothing.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LootTable(pub Value);
+
+impl LootTable {
+    /// Compile the loot table into a virtual file.
+    #[must_use]
+    pub fn compile(&self) -> VFile {
+        VFile::Text(self.0.to_string())
+    }
+}
+
+impl Default for LootTable {
+    fn default() -> Self {
+        Self(Value::Object(serde_json::Map::new()))
+    }
+}
+
+/// Item modifier given as its JSON object or array of item functions.
+///
+/// The default item modifier has no functions and leaves items unchanged.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemModifier(pub Value);
+
+impl ItemModifier {
+    /// Compile the item modifier into a virtual file.
+    #[must_use]
+    pub fn compile(&self) -> VFile {
+        VFile::Text(self.0.to_string())
+    }
+}
+
+impl Default for ItemModifier {
+    fn default() -> Self {
+        Self(Value::Array(Vec::new()))
+    }
+}
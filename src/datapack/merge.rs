@@ -0,0 +1,341 @@
+//! Merging of datapacks.
+
+use std::collections::HashMap;
+
+use getset::Getters;
+
+use super::{
+    namespace::{Namespace, NamespaceConflicts},
+    tag::TagType,
+    Datapack,
+};
+
+/// Report of the conflicts found while merging datapacks with [`Datapack::merge`].
+///
+/// Ids of resources in overlays are prefixed with the directory of the overlay, e.g. `overlay/foo:bar`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+pub struct MergeReport {
+    /// Ids of the functions that existed in both datapacks and were replaced.
+    #[get = "pub"]
+    replaced_functions: Vec<String>,
+    /// Ids and types of the tags that existed in both datapacks and were merged.
+    #[get = "pub"]
+    merged_tags: Vec<(String, TagType)>,
+    /// Ids of the predicates that existed in both datapacks and were replaced.
+    #[get = "pub"]
+    replaced_predicates: Vec<String>,
+    /// Ids of the loot tables that existed in both datapacks and were replaced.
+    #[get = "pub"]
+    replaced_loot_tables: Vec<String>,
+    /// Ids of the item modifiers that existed in both datapacks and were replaced.
+    #[get = "pub"]
+    replaced_item_modifiers: Vec<String>,
+    /// Ids of the advancements that existed in both datapacks and were replaced.
+    #[get = "pub"]
+    replaced_advancements: Vec<String>,
+    /// Paths of the custom files that existed in both datapacks and were replaced.
+    #[get = "pub"]
+    replaced_files: Vec<String>,
+}
+
+impl MergeReport {
+    /// Check whether the datapacks were merged without any conflicts.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.replaced_functions.is_empty()
+            && self.merged_tags.is_empty()
+            && self.replaced_predicates.is_empty()
+            && self.replaced_loot_tables.is_empty()
+            && self.replaced_item_modifiers.is_empty()
+            && self.replaced_advancements.is_empty()
+            && self.replaced_files.is_empty()
+    }
+
+    /// Merge the namespaces into the target namespaces in the order of their names, adding the conflicts
+    /// with ids prefixed by the prefix.
+    fn merge_namespaces(
+        &mut self,
+        prefix: &str,
+        target: &mut HashMap<String, Namespace>,
+        namespaces: HashMap<String, Namespace>,
+    ) {
+        let mut namespaces = namespaces.into_iter().collect::<Vec<_>>();
+        namespaces.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, namespace) in namespaces {
+            let conflicts = target
+                .entry(name.clone())
+                .or_insert_with(|| Namespace::new(&name))
+                .merge_with_conflicts(namespace);
+            self.add_conflicts(&format!("{prefix}{name}"), conflicts);
+        }
+    }
+
+    /// Add the conflicts of merging the namespace.
+    fn add_conflicts(&mut self, namespace: &str, conflicts: NamespaceConflicts) {
+        let ids = |names: Vec<String>| {
+            names
+                .into_iter()
+                .map(|name| format!("{namespace}:{name}"))
+                .collect::<Vec<_>>()
+        };
+        self.replaced_functions.extend(ids(conflicts.functions));
+        self.merged_tags.extend(
+            conflicts
+                .tags
+                .into_iter()
+                .map(|(tag, tag_type)| (format!("{namespace}:{tag}"), tag_type)),
+        );
+        self.replaced_predicates.extend(ids(conflicts.predicates));
+        self.replaced_loot_tables.extend(ids(conflicts.loot_tables));
+        self.replaced_item_modifiers
+            .extend(ids(conflicts.item_modifiers));
+        self.replaced_advancements
+            .extend(ids(conflicts.advancements));
+    }
+}
+
+impl Datapack {
+    /// Merge another datapack into this datapack.
+    ///
+    /// Functions and other resources of the other datapack replace the ones with the same name, tags are merged with
+    /// [`Tag::merge`](super::tag::Tag::merge) and custom files are merged with [`VFolder::merge`](crate::virtual_fs::VFolder::merge).
+    /// Overlays with the same directory are merged like the datapacks, keeping the formats of this datapack,
+    /// other overlays are added.
+    /// Features, filters, objectives and constants are combined, so the load function sets up the objectives
+    /// and constants of both datapacks. Objectives with the same name keep the criteria of this datapack.
+    /// All other properties, like the description and pack format, are kept from this datapack.
+    pub fn merge(&mut self, other: Self) -> MergeReport {
+        let mut report = MergeReport::default();
+
+        report.merge_namespaces("", &mut self.namespaces, other.namespaces);
+
+        for overlay in other.overlays {
+            if let Some(existing) = self
+                .overlays
+                .iter_mut()
+                .find(|existing| existing.directory() == overlay.directory())
+            {
+                let prefix = format!("{}/", overlay.directory());
+                report.merge_namespaces(
+                    &prefix,
+                    existing.namespaces_mut(),
+                    overlay.into_namespaces(),
+                );
+            } else {
+                self.overlays.push(overlay);
+            }
+        }
+
+        for feature in other.features {
+            if !self.features.contains(&feature) {
+                self.features.push(feature);
+            }
+        }
+        for filter in other.filter_blocks {
+            if !self.filter_blocks.contains(&filter) {
+                self.filter_blocks.push(filter);
+            }
+        }
+        for objective in other.objectives {
+            self.register_objective(objective);
+        }
+        self.constants.extend(other.constants);
+
+        report.replaced_files = self.custom_files.merge(other.custom_files);
+        report.replaced_files.sort();
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{datapack::tag::TagValue, virtual_fs::VFile};
+
+    use super::*;
+
+    #[test]
+    fn test_clean_merge() {
+        let mut first = Datapack::new(Datapack::LATEST_FORMAT).with_description("first");
+        first
+            .namespace_mut("foo")
+            .function_mut("a")
+            .add_command("say a");
+        first.add_load("foo:a");
+
+        let mut second = Datapack::new(40).with_description("second");
+        second
+            .namespace_mut("foo")
+            .function_mut("b")
+            .add_command("say b");
+        second
+            .namespace_mut("bar")
+            .function_mut("c")
+            .add_command("say c");
+        second.add_custom_file("pack.png", VFile::Binary(vec![1]));
+
+        let report = first.merge(second);
+        assert!(report.is_clean());
+        assert_eq!(first.description, "first");
+        assert_eq!(first.pack_format, Datapack::LATEST_FORMAT);
+        let foo = first.namespace("foo").unwrap();
+        assert!(foo.function("a").is_some() && foo.function("b").is_some());
+        assert!(first
+            .namespace("bar")
+            .and_then(|bar| bar.function("c"))
+            .is_some());
+        assert!(first.custom_files.get_file("pack.png").is_some());
+    }
+
+    #[test]
+    fn test_conflicting_merge() {
+        let mut first = Datapack::new(Datapack::LATEST_FORMAT);
+        first
+            .namespace_mut("foo")
+            .function_mut("main")
+            .add_command("say first");
+        first.add_load("foo:main");
+        first.add_tick("foo:tick");
+        first.add_custom_file("README.md", VFile::from("first"));
+
+        let mut second = Datapack::new(Datapack::LATEST_FORMAT);
+        second
+            .namespace_mut("foo")
+            .function_mut("main")
+            .add_command("say second");
+        second.add_load("foo:main");
+        second.add_load("foo:init");
+        let tick = second
+            .namespace_mut("minecraft")
+            .tag_mut("tick", TagType::Function);
+        tick.set_replace(true);
+        tick.add_value(TagValue::from("foo:other_tick"));
+        second.add_custom_file("README.md", VFile::from("second"));
+
+        let report = first.merge(second);
+        assert!(!report.is_clean());
+        assert_eq!(report.replaced_functions(), &vec!["foo:main".to_string()]);
+        assert_eq!(
+            report.merged_tags(),
+            &vec![
+                ("minecraft:load".to_string(), TagType::Function),
+                ("minecraft:tick".to_string(), TagType::Function)
+            ]
+        );
+        assert_eq!(report.replaced_files(), &vec!["README.md".to_string()]);
+
+        assert_eq!(
+            first
+                .namespace("foo")
+                .and_then(|foo| foo.function("main"))
+                .map(|main| main.get_commands().len()),
+            Some(1)
+        );
+        let minecraft = first.namespace("minecraft").unwrap();
+        assert_eq!(
+            minecraft
+                .tag("load", TagType::Function)
+                .unwrap()
+                .get_values(),
+            &vec![TagValue::from("foo:main"), TagValue::from("foo:init")]
+        );
+        let tick = minecraft.tag("tick", TagType::Function).unwrap();
+        assert!(tick.get_replace());
+        assert_eq!(tick.get_values(), &vec![TagValue::from("foo:other_tick")]);
+    }
+
+    #[test]
+    fn test_merge_everything() {
+        use crate::{
+            datapack::{loot::LootTable, scoreboard::Objective},
+            util::compile::CompileOptions,
+        };
+
+        let mut first =
+            Datapack::new(Datapack::LATEST_FORMAT).with_features(&["minecraft:trade_rebalance"]);
+        first.register_objective(Objective::dummy("kills"));
+        first.use_constant(1);
+        first.add_filter_block(Some("minecraft"), None);
+        first
+            .add_overlay("old", 10..=20)
+            .namespace_mut("foo")
+            .function_mut("main")
+            .add_command("say first");
+        first
+            .namespace_mut("foo")
+            .predicate_mut("chance")
+            .add_random_chance(0.1);
+
+        let mut second = Datapack::new(Datapack::LATEST_FORMAT).with_features(&[
+            "minecraft:trade_rebalance",
+            "minecraft:redstone_experiments",
+        ]);
+        second.register_objective(Objective::new("kills", "playerKillCount"));
+        second.register_objective(Objective::dummy("deaths"));
+        second.use_constant(2);
+        second.add_filter_block(Some("minecraft"), None);
+        second.add_filter_block(None, Some("loot_table/.*"));
+        let overlay = second.add_overlay("old", 1..=5);
+        overlay
+            .namespace_mut("foo")
+            .function_mut("main")
+            .add_command("say second");
+        overlay
+            .namespace_mut("foo")
+            .function_mut("other")
+            .add_command("say other");
+        second
+            .add_overlay("new", 40..=48)
+            .namespace_mut("bar")
+            .function_mut("main")
+            .add_command("say new");
+        let foo = second.namespace_mut("foo");
+        foo.predicate_mut("chance").add_random_chance(0.9);
+        *foo.loot_table_mut("chest") = LootTable::default();
+        let _ = foo.advancement_mut("root");
+
+        let report = first.merge(second);
+        assert_eq!(
+            report.replaced_functions(),
+            &vec!["old/foo:main".to_string()]
+        );
+        assert_eq!(
+            report.replaced_predicates(),
+            &vec!["foo:chance".to_string()]
+        );
+        assert!(report.replaced_loot_tables().is_empty());
+        assert!(report.replaced_advancements().is_empty());
+        assert!(!report.is_clean());
+
+        let old = first.overlay("old").unwrap();
+        assert_eq!(old.formats(), &(10..=20));
+        let old_foo = old.namespace("foo").unwrap();
+        assert!(old_foo.function("main").is_some() && old_foo.function("other").is_some());
+        assert!(first.overlay("new").is_some());
+        assert_eq!(
+            first.features,
+            vec![
+                "minecraft:trade_rebalance".to_string(),
+                "minecraft:redstone_experiments".to_string()
+            ]
+        );
+        assert_eq!(first.filter_blocks.len(), 2);
+        let foo = first.namespace("foo").unwrap();
+        assert!(foo.loot_table("chest").is_some() && foo.advancement("root").is_some());
+
+        // the load function sets up the objectives and constants of both datapacks
+        let compiled = first.compile(&CompileOptions::default());
+        assert_eq!(
+            compiled
+                .get_file("data/shulkerbox/function/load.mcfunction")
+                .and_then(crate::virtual_fs::VFile::as_text),
+            Some(
+                "scoreboard objectives add kills dummy\n\
+                scoreboard objectives add sb_const dummy\n\
+                scoreboard objectives add deaths dummy\n\
+                scoreboard players set #const_1 sb_const 1\n\
+                scoreboard players set #const_2 sb_const 2"
+            )
+        );
+    }
+}
@@ -1,30 +1,72 @@
 //! Datapack module for creating and managing Minecraft datapacks.
 
+pub mod advancement;
 mod command;
 mod function;
+pub mod loot;
+mod merge;
 mod namespace;
+mod overlay;
+mod parse;
+pub mod predicate;
+pub mod scoreboard;
 pub mod tag;
-pub use command::{Command, Condition, Execute};
+pub mod text;
+mod validation;
+pub use command::{
+    Anchor, BossbarField, CachedCondition, Command, CommandVisitor, Condition, ConditionParseError,
+    Execute, ExecuteBuilder, ExecutePlan, FacingTarget, GroupingReason, Literal, MacroArgs,
+    NbtType, OnRelation, ReturnValue, Schedule, ScheduleMode, ScheduleTime, ScoreOrdering,
+    StoreDestination, StoreTarget,
+};
 pub use function::Function;
+pub use merge::MergeReport;
 pub use namespace::Namespace;
+pub use overlay::Overlay;
+pub use parse::ParseError;
+pub use validation::{ValidateOptions, ValidationError};
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+    ops::RangeInclusive,
+    sync::Mutex,
+};
 
-use std::{collections::HashMap, ops::RangeInclusive, sync::Mutex};
+use text::TextComponent;
 
 use crate::{
-    util::compile::{CompileOptions, CompilerState, MutCompilerState},
+    util::{
+        compile::{
+            try_report, CompileError, CompileOptions, CompilerState, Diagnostic, DiagnosticKind,
+            MutCompilerState,
+        },
+        pack_format,
+    },
     virtual_fs::{VFile, VFolder},
 };
 
+/// Signature at the start of every PNG image.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
 /// A Minecraft datapack.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Datapack {
-    // TODO: Support filter and overlays
     description: String,
+    description_component: Option<TextComponent>,
     pack_format: u8,
     supported_formats: Option<RangeInclusive<u8>>,
     namespaces: HashMap<String, Namespace>,
+    overlays: Vec<Overlay>,
+    features: Vec<String>,
+    filter_blocks: Vec<(Option<String>, Option<String>)>,
+    objectives: Vec<scoreboard::Objective>,
+    constants: BTreeSet<i32>,
     custom_files: VFolder,
+    readme_template: Option<String>,
+    icon: Option<Vec<u8>>,
+    mcmeta_extension: Option<serde_json::Value>,
 }
 
 impl Datapack {
@@ -35,10 +77,19 @@ impl Datapack {
     pub fn new(pack_format: u8) -> Self {
         Self {
             description: String::from("A Minecraft datapack created with shulkerbox"),
+            description_component: None,
             pack_format,
             supported_formats: None,
             namespaces: HashMap::new(),
+            overlays: Vec::new(),
+            features: Vec::new(),
+            filter_blocks: Vec::new(),
+            objectives: Vec::new(),
+            constants: BTreeSet::new(),
             custom_files: VFolder::new(),
+            readme_template: None,
+            icon: None,
+            mcmeta_extension: None,
         }
     }
 
@@ -47,6 +98,19 @@ impl Datapack {
     pub fn with_description(self, description: &str) -> Self {
         Self {
             description: description.to_string(),
+            description_component: None,
+            ..self
+        }
+    }
+
+    /// Set the description of the datapack to a text component, e.g. to color it.
+    ///
+    /// The text of the component is used where only plain text is possible, like in the generated readme.
+    #[must_use]
+    pub fn with_description_component(self, component: TextComponent) -> Self {
+        Self {
+            description: component.get_text().to_string(),
+            description_component: Some(component),
             ..self
         }
     }
@@ -60,8 +124,58 @@ impl Datapack {
         }
     }
 
+    /// Set the experimental features enabled by the datapack, e.g. `minecraft:update_1_21`.
+    #[must_use]
+    pub fn with_features(self, flags: &[&str]) -> Self {
+        Self {
+            features: flags.iter().map(ToString::to_string).collect(),
+            ..self
+        }
+    }
+
+    /// Merge the keys of the JSON object into the generated `pack.mcmeta`.
+    ///
+    /// Keys of a `pack` object are merged into the generated `pack` section,
+    /// so only the given keys of it are overridden. Other keys replace the generated ones.
+    /// Values that are not objects are ignored.
+    #[must_use]
+    pub fn with_mcmeta_extension(self, extension: serde_json::Value) -> Self {
+        Self {
+            mcmeta_extension: Some(extension),
+            ..self
+        }
+    }
+
+    /// Generate a `README.md` file in the root of the datapack from the template.
+    ///
+    /// The template can contain the placeholders `{pack_format}`, `{description}` and `{date}`,
+    /// which are replaced with the pack format, description and the current date (UTC, `YYYY-MM-DD`) when compiling.
+    #[must_use]
+    pub fn with_generated_readme(self, content_template: &str) -> Self {
+        Self {
+            readme_template: Some(content_template.to_string()),
+            ..self
+        }
+    }
+
+    /// Set the icon of the datapack, which is placed as `pack.png` in the root of the compiled datapack.
+    ///
+    /// The icon replaces a `pack.png` in the custom files. A warning is logged if the bytes are not a PNG image.
+    #[must_use]
+    pub fn with_icon(self, png_bytes: Vec<u8>) -> Self {
+        if !png_bytes.starts_with(PNG_SIGNATURE) {
+            tracing::warn!("Icon of the datapack does not look like a PNG image");
+        }
+        Self {
+            icon: Some(png_bytes),
+            ..self
+        }
+    }
+
     /// Set the custom files of the datapack.
     ///
+    /// Hidden files and other junk files are skipped, see [`VFolder::default_path_filter`].
+    ///
     /// # Errors
     /// - If loading the directory fails
     #[cfg(feature = "fs_access")]
@@ -73,6 +187,20 @@ impl Datapack {
         Ok(self.with_template_vfolder(template))
     }
 
+    /// Set the custom files of the datapack, only including files and folders for which the filter returns `true`.
+    ///
+    /// # Errors
+    /// - If loading the directory fails
+    #[cfg(feature = "fs_access")]
+    pub fn with_template_folder_filtered<P, F>(self, path: P, filter: F) -> std::io::Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+        F: Fn(&std::path::Path) -> bool,
+    {
+        let template = VFolder::from_path_filtered(path, filter)?;
+        Ok(self.with_template_vfolder(template))
+    }
+
     /// Set the custom files of the datapack.
     #[must_use]
     pub fn with_template_vfolder(self, mut template: VFolder) -> Self {
@@ -89,6 +217,18 @@ impl Datapack {
         self.namespaces.get(name)
     }
 
+    /// Iterate over the namespaces with their names, sorted by name.
+    pub fn namespaces(&self) -> impl Iterator<Item = (&str, &Namespace)> {
+        self.sorted_namespaces()
+            .into_iter()
+            .map(|(name, namespace)| (name.as_str(), namespace))
+    }
+
+    /// Iterate over the names of the namespaces, sorted by name.
+    pub fn namespace_names(&self) -> impl Iterator<Item = &str> {
+        self.namespaces().map(|(name, _)| name)
+    }
+
     /// Mutably get a namespace by name or create a new one if it doesn't exist.
     pub fn namespace_mut(&mut self, name: &str) -> &mut Namespace {
         self.namespaces
@@ -96,6 +236,39 @@ impl Datapack {
             .or_insert_with(|| Namespace::new(name))
     }
 
+    /// Get an overlay by its directory.
+    #[must_use]
+    pub fn overlay(&self, directory: &str) -> Option<&Overlay> {
+        self.overlays
+            .iter()
+            .find(|overlay| overlay.directory() == directory)
+    }
+
+    /// Get the overlays of the datapack in the order they are applied.
+    #[must_use]
+    pub fn overlays(&self) -> &[Overlay] {
+        &self.overlays
+    }
+
+    /// Add an overlay that is compiled into the folder `directory` and applied for the given pack formats.
+    ///
+    /// If an overlay with the same directory already exists, its formats are updated and it is returned.
+    /// Overlays are applied in the order they are added.
+    pub fn add_overlay(&mut self, directory: &str, formats: RangeInclusive<u8>) -> &mut Overlay {
+        let index = if let Some(index) = self
+            .overlays
+            .iter()
+            .position(|overlay| overlay.directory() == directory)
+        {
+            self.overlays[index].set_formats(formats);
+            index
+        } else {
+            self.overlays.push(Overlay::new(directory, formats));
+            self.overlays.len() - 1
+        };
+        &mut self.overlays[index]
+    }
+
     /// Add a function to the tick function list.
     pub fn add_tick(&mut self, function: &str) {
         self.namespace_mut("minecraft")
@@ -103,6 +276,15 @@ impl Datapack {
             .add_value(tag::TagValue::Simple(function.to_string()));
     }
 
+    /// Insert a function at the index of the tick function list, or at the end if the index is out of bounds.
+    ///
+    /// The functions are run in the order of the list.
+    pub fn add_tick_at(&mut self, function: &str, index: usize) {
+        self.namespace_mut("minecraft")
+            .tag_mut("tick", tag::TagType::Function)
+            .insert_value(index, tag::TagValue::Simple(function.to_string()));
+    }
+
     /// Add a function to the load function list.
     pub fn add_load(&mut self, function: &str) {
         self.namespace_mut("minecraft")
@@ -110,39 +292,313 @@ impl Datapack {
             .add_value(tag::TagValue::Simple(function.to_string()));
     }
 
+    /// Insert a function at the index of the load function list, or at the end if the index is out of bounds.
+    ///
+    /// The functions are run in the order of the list, so inserting at index 0 runs the function first.
+    pub fn add_load_at(&mut self, function: &str, index: usize) {
+        self.namespace_mut("minecraft")
+            .tag_mut("load", tag::TagType::Function)
+            .insert_value(index, tag::TagValue::Simple(function.to_string()));
+    }
+
+    /// Add a reference to the function to each of the given function tags.
+    ///
+    /// The tags are given as pairs of `(tag_namespace, tag_name)`.
+    pub fn register_function(&mut self, function: &Function, tags: &[(&str, &str)]) {
+        let reference = format!("{}:{}", function.namespace(), function.name());
+        for (namespace, name) in tags {
+            self.namespace_mut(namespace)
+                .tag_mut(name, tag::TagType::Function)
+                .add_value(tag::TagValue::Simple(reference.clone()));
+        }
+    }
+
+    /// Hide files of packs and overlays below this datapack.
+    ///
+    /// The namespace and path are regular expressions matching the files to hide,
+    /// which are passed to Minecraft verbatim. If one of them is `None`, it matches everything.
+    pub fn add_filter_block(&mut self, namespace: Option<&str>, path: Option<&str>) {
+        self.filter_blocks.push((
+            namespace.map(ToString::to_string),
+            path.map(ToString::to_string),
+        ));
+    }
+
     /// Add a custom file to the datapack.
     pub fn add_custom_file(&mut self, path: &str, file: VFile) {
         self.custom_files.add_file(path, file);
     }
 
     /// Compile the pack into a virtual folder.
+    ///
+    /// Panics if a file cannot be serialized, use [`Datapack::try_compile`] to handle the error instead.
     #[must_use]
-    #[tracing::instrument(level = "debug", skip(self))]
     pub fn compile(&self, options: &CompileOptions) -> VFolder {
-        tracing::debug!("Compiling datapack: {:?}", self);
-        let options = CompileOptions {
-            pack_format: self.pack_format,
-            ..options.clone()
-        };
+        self.try_compile(options)
+            .expect("Failed to compile datapack")
+    }
 
+    /// Compile the pack into a virtual folder.
+    ///
+    /// With the `parallel` feature, namespaces are compiled concurrently.
+    ///
+    /// # Errors
+    /// - If a JSON file cannot be serialized
+    /// - If the compiler state was poisoned
+    pub fn try_compile(&self, options: &CompileOptions) -> Result<VFolder, CompileError> {
+        self.try_compile_with_diagnostics(options)
+            .map(|(folder, _)| folder)
+    }
+
+    /// Compile the pack into a virtual folder and collect the non-fatal issues found while compiling,
+    /// like commands that are not supported by the pack format, invalid selectors or empty functions.
+    ///
+    /// The issues are logged with `tracing` as well.
+    ///
+    /// # Errors
+    /// - If a JSON file cannot be serialized
+    /// - If the compiler state was poisoned
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn try_compile_with_diagnostics(
+        &self,
+        options: &CompileOptions,
+    ) -> Result<(VFolder, Vec<Diagnostic>), CompileError> {
+        tracing::debug!("Compiling datapack: {:?}", self);
         let compiler_state = Mutex::new(CompilerState::default());
+        let options = self.prepare_compile_options(options, &compiler_state)?;
 
         let mut root_folder = self.custom_files.clone();
-        let mcmeta = generate_mcmeta(self, &options, &compiler_state);
+        let mcmeta = generate_mcmeta(self, &options, &compiler_state)?;
         root_folder.add_file("pack.mcmeta", mcmeta);
-        let mut data_folder = VFolder::new();
+        if let Some(template) = &self.readme_template {
+            let readme = generate_readme(self, template, &current_date());
+            root_folder.add_file("README.md", readme);
+        }
+        if let Some(icon) = &self.icon {
+            root_folder.add_file("pack.png", VFile::Binary(icon.clone()));
+        }
+        let mut compiled_folder = VFolder::new();
 
         // Compile namespaces
-        for (name, namespace) in &self.namespaces {
-            let namespace_folder = namespace.compile(&options, &compiler_state);
-            data_folder.add_existing_folder(name, namespace_folder);
+        let namespaces = self
+            .namespaces_with_load_function(&options, &compiler_state)?
+            .into_iter()
+            .filter(|(_, namespace)| options.emit_empty_namespaces || !namespace.is_empty())
+            .collect();
+        for (name, namespace_folder) in compile_namespaces(namespaces, &options, &compiler_state)? {
+            compiled_folder.add_existing_folder(&format!("data/{name}"), namespace_folder);
         }
+        compile_generated_functions(&options, &compiler_state, |path, file| {
+            compiled_folder.add_file(path, file);
+        })?;
 
-        root_folder.add_existing_folder("data", data_folder);
-        root_folder
+        // Compile overlays
+        for overlay in &self.overlays {
+            let directory = overlay.directory();
+            overlay.try_compile_streaming(&options, &compiler_state, |path, file| {
+                compiled_folder.add_file(&format!("{directory}/{path}"), file);
+            })?;
+        }
+
+        // merge to keep custom files in the data folder
+        root_folder.merge(compiled_folder);
+        let diagnostics = compiler_state.lock()?.take_diagnostics();
+        Ok((root_folder, diagnostics))
+    }
+
+    /// Compile the pack, passing each file with its path to the sink as soon as it is compiled.
+    ///
+    /// In contrast to [`Datapack::compile`], the compiled files are not collected in memory,
+    /// which allows writing very large packs directly to the disk or an archive.
+    /// Custom files are passed first, if a compiled file has the same path, it is passed again afterwards.
+    /// Empty folders are not emitted.
+    ///
+    /// Panics if a file cannot be serialized, use [`Datapack::try_compile_streaming`] to handle the error instead.
+    pub fn compile_streaming<F>(&self, options: &CompileOptions, sink: F)
+    where
+        F: FnMut(&str, VFile),
+    {
+        self.try_compile_streaming(options, sink)
+            .expect("Failed to compile datapack");
+    }
+
+    /// Compile the pack, passing each file with its path to the sink as soon as it is compiled.
+    ///
+    /// See [`Datapack::compile_streaming`] for details.
+    ///
+    /// # Errors
+    /// - If a JSON file cannot be serialized
+    /// - If the compiler state was poisoned
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn try_compile_streaming<F>(
+        &self,
+        options: &CompileOptions,
+        mut sink: F,
+    ) -> Result<(), CompileError>
+    where
+        F: FnMut(&str, VFile),
+    {
+        tracing::debug!("Compiling datapack (streaming)");
+        let compiler_state = Mutex::new(CompilerState::default());
+        let options = self.prepare_compile_options(options, &compiler_state)?;
+
+        for (path, file) in self.custom_files.flatten() {
+            sink(&path, file.clone());
+        }
+        sink(
+            "pack.mcmeta",
+            generate_mcmeta(self, &options, &compiler_state)?,
+        );
+        if let Some(template) = &self.readme_template {
+            sink(
+                "README.md",
+                generate_readme(self, template, &current_date()),
+            );
+        }
+        if let Some(icon) = &self.icon {
+            sink("pack.png", VFile::Binary(icon.clone()));
+        }
+
+        // Compile namespaces, with the `parallel` feature each with its own state as in `compile_namespaces`
+        #[cfg(feature = "parallel")]
+        let base_state = compiler_state.lock()?.fork();
+        for (name, namespace) in self.namespaces_with_load_function(&options, &compiler_state)? {
+            let namespace_sink = |path: &str, file| sink(&format!("data/{name}/{path}"), file);
+            #[cfg(feature = "parallel")]
+            {
+                let namespace_state = Mutex::new(base_state.fork());
+                let result =
+                    namespace.try_compile_streaming(&options, &namespace_state, namespace_sink);
+                compiler_state.lock()?.join(namespace_state.into_inner()?);
+                result?;
+            }
+            #[cfg(not(feature = "parallel"))]
+            namespace.try_compile_streaming(&options, &compiler_state, namespace_sink)?;
+        }
+        compile_generated_functions(&options, &compiler_state, &mut sink)?;
+
+        // Compile overlays
+        for overlay in &self.overlays {
+            let directory = overlay.directory();
+            overlay.try_compile_streaming(&options, &compiler_state, |path, file| {
+                sink(&format!("{directory}/{path}"), file);
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// List the ids of all generated helper functions in a compiled datapack.
+    ///
+    /// Generated functions are identified by being in the subfolder set by
+    /// [`CompileOptions::with_generated_subfolder`], so the same options as for compiling have to be used.
+    #[must_use]
+    pub fn list_generated_functions(compiled: &VFolder, options: &CompileOptions) -> Vec<String> {
+        let directory_name = pack_format::function_directory_name(options.pack_format);
+        let prefix = format!("{}/", options.generated_subfolder);
+        let prefix = prefix.as_str();
+
+        let mut functions = compiled
+            .get_folder("data")
+            .map(|data| {
+                data.get_folders()
+                    .iter()
+                    .filter_map(|(namespace, folder)| {
+                        Some((namespace, folder.get_folder(directory_name)?))
+                    })
+                    .flat_map(|(namespace, folder)| {
+                        folder.flatten().into_iter().filter_map(move |(path, _)| {
+                            let name = path.strip_suffix(".mcfunction")?;
+                            name.starts_with(prefix)
+                                .then(|| format!("{namespace}:{name}"))
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        functions.sort();
+
+        functions
+    }
+
+    /// Get the namespaces sorted by name to make the compilation output deterministic.
+    pub(super) fn sorted_namespaces(&self) -> Vec<(&String, &Namespace)> {
+        let mut namespaces = self.namespaces.iter().collect::<Vec<_>>();
+        namespaces.sort_by_key(|(name, _)| *name);
+        namespaces
+    }
+
+    /// Get the options used for compiling this pack and warn about possible incompatibilities.
+    ///
+    /// # Errors
+    /// - If the compiler state was poisoned
+    fn prepare_compile_options(
+        &self,
+        options: &CompileOptions,
+        state: &MutCompilerState,
+    ) -> Result<CompileOptions, CompileError> {
+        for format in self.crossed_directory_renames() {
+            try_report(
+                state,
+                DiagnosticKind::PackFormat,
+                format!(
+                    "Supported formats cross the directory rename at pack format {format}, directory names are only correct for pack format {}",
+                    self.pack_format
+                ),
+            )?;
+        }
+
+        if options.pack_format_set {
+            if options.pack_format != self.pack_format {
+                try_report(
+                    state,
+                    DiagnosticKind::PackFormat,
+                    format!(
+                        "Compiling for pack format {} although the datapack has pack format {}",
+                        options.pack_format, self.pack_format
+                    ),
+                )?;
+            }
+            Ok(options.clone())
+        } else {
+            Ok(CompileOptions {
+                pack_format: self.pack_format,
+                ..options.clone()
+            })
+        }
+    }
+
+    /// Get the pack formats of directory renames that lie within the supported formats.
+    /// The compiled directory names are only correct for one side of each of these formats.
+    ///
+    /// Renames are skipped if an overlay covers the supported formats on the other side than the base pack format.
+    fn crossed_directory_renames(&self) -> Vec<u8> {
+        self.supported_formats
+            .as_ref()
+            .map(|supported_formats| {
+                pack_format::DIRECTORY_RENAME_FORMATS
+                    .into_iter()
+                    .filter(|&format| {
+                        supported_formats.start() < &format && supported_formats.end() >= &format
+                    })
+                    .filter(|&format| {
+                        let (start, end) = if self.pack_format >= format {
+                            (*supported_formats.start(), format - 1)
+                        } else {
+                            (format, *supported_formats.end())
+                        };
+                        !self.overlays.iter().any(|overlay| {
+                            overlay.formats().start() <= &start && overlay.formats().end() >= &end
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     /// Check whether the datapack is valid with the given pack format.
+    ///
+    /// Overlays are validated against the pack formats they are applied for.
     #[must_use]
     pub fn validate(&self) -> bool {
         let pack_formats = self
@@ -152,30 +608,218 @@ impl Datapack {
         self.namespaces
             .values()
             .all(|namespace| namespace.validate(&pack_formats))
+            && self.overlays.iter().all(Overlay::validate)
+    }
+}
+
+#[allow(clippy::literal_string_with_formatting_args)]
+fn generate_readme(dp: &Datapack, template: &str, date: &str) -> VFile {
+    let content = template
+        .replace("{pack_format}", &dp.pack_format.to_string())
+        .replace("{description}", &dp.description)
+        .replace("{date}", date);
+
+    VFile::Text(content)
+}
+
+/// Get the current date in UTC formatted as `YYYY-MM-DD`.
+fn current_date() -> String {
+    let days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() / 86400);
+    let days = i64::try_from(days).unwrap_or_default();
+
+    // convert days since the unix epoch to a civil date (proleptic gregorian calendar)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Compile the namespaces into folders, keeping their order.
+#[cfg(not(feature = "parallel"))]
+fn compile_namespaces(
+    namespaces: Vec<(String, Cow<'_, Namespace>)>,
+    options: &CompileOptions,
+    state: &MutCompilerState,
+) -> Result<Vec<(String, VFolder)>, CompileError> {
+    namespaces
+        .into_iter()
+        .map(|(name, namespace)| Ok((name, compile_namespace_folder(&namespace, options, state)?)))
+        .collect()
+}
+
+/// Compile the namespaces into folders concurrently, keeping their order.
+///
+/// Functions generated while compiling are queued per namespace,
+/// so each namespace is still compiled on a single thread.
+/// Each namespace is compiled with its own compiler state and the states are joined in the order of the namespaces,
+/// so the diagnostics, generated functions and identifiers do not depend on the scheduling of the threads.
+#[cfg(feature = "parallel")]
+fn compile_namespaces(
+    namespaces: Vec<(String, Cow<'_, Namespace>)>,
+    options: &CompileOptions,
+    state: &MutCompilerState,
+) -> Result<Vec<(String, VFolder)>, CompileError> {
+    use rayon::prelude::*;
+
+    let base_state = state.lock()?.fork();
+    // collecting keeps the order of the namespaces, which are sorted by name
+    let results = namespaces
+        .into_par_iter()
+        .map(|(name, namespace)| {
+            let namespace_state = Mutex::new(base_state.fork());
+            let folder = compile_namespace_folder(&namespace, options, &namespace_state);
+            (name, folder, namespace_state)
+        })
+        .collect::<Vec<_>>();
+
+    let mut state = state.lock()?;
+    results
+        .into_iter()
+        .map(|(name, folder, namespace_state)| {
+            state.join(namespace_state.into_inner()?);
+            Ok((name, folder?))
+        })
+        .collect()
+}
+
+/// Compile a namespace into a folder.
+fn compile_namespace_folder(
+    namespace: &Namespace,
+    options: &CompileOptions,
+    state: &MutCompilerState,
+) -> Result<VFolder, CompileError> {
+    let mut folder = VFolder::new();
+    namespace.try_compile_streaming(options, state, |path, file| {
+        folder.add_file(path, file);
+    })?;
+    Ok(folder)
+}
+
+/// Compile the helper functions generated into the namespace set by [`CompileOptions::with_generated_namespace`],
+/// passing each file with its path to the sink.
+fn compile_generated_functions<F>(
+    options: &CompileOptions,
+    state: &MutCompilerState,
+    mut sink: F,
+) -> Result<(), CompileError>
+where
+    F: FnMut(&str, VFile),
+{
+    let Some(name) = options.generated_namespace.as_deref() else {
+        return Ok(());
+    };
+
+    // compiling generated functions may generate further functions
+    loop {
+        let functions = state.lock()?.take_generated_functions();
+        if functions.is_empty() {
+            break;
+        }
+
+        let mut namespace = Namespace::new(name);
+        for (_, function) in functions {
+            namespace.add_existing_function(function);
+        }
+        namespace.try_compile_streaming(options, state, |path, file| {
+            sink(&format!("data/{name}/{path}"), file);
+        })?;
     }
+
+    Ok(())
 }
 
-fn generate_mcmeta(dp: &Datapack, _options: &CompileOptions, _state: &MutCompilerState) -> VFile {
+fn generate_mcmeta(
+    dp: &Datapack,
+    _options: &CompileOptions,
+    _state: &MutCompilerState,
+) -> Result<VFile, CompileError> {
     let mut content = serde_json::json!({
         "pack": {
             "description": dp.description,
             "pack_format": dp.pack_format
         }
     });
+    if let Some(component) = &dp.description_component {
+        content["pack"]["description"] = serde_json::from_str(&component.to_json())?;
+    }
     if let Some(supported_formats) = &dp.supported_formats {
         content["pack"]["supported_formats"] = serde_json::json!({
             "min_inclusive": *supported_formats.start(),
             "max_inclusive": *supported_formats.end()
         });
     }
+    if !dp.features.is_empty() {
+        content["features"] = serde_json::json!({ "enabled": dp.features });
+    }
+    if !dp.filter_blocks.is_empty() {
+        let block = dp
+            .filter_blocks
+            .iter()
+            .map(|(namespace, path)| {
+                let mut pattern = serde_json::Map::new();
+                if let Some(namespace) = namespace {
+                    pattern.insert("namespace".to_string(), namespace.clone().into());
+                }
+                if let Some(path) = path {
+                    pattern.insert("path".to_string(), path.clone().into());
+                }
+                serde_json::Value::Object(pattern)
+            })
+            .collect::<Vec<_>>();
+        content["filter"] = serde_json::json!({ "block": block });
+    }
+    if !dp.overlays.is_empty() {
+        let entries = dp
+            .overlays
+            .iter()
+            .map(|overlay| {
+                serde_json::json!({
+                    "formats": {
+                        "min_inclusive": *overlay.formats().start(),
+                        "max_inclusive": *overlay.formats().end()
+                    },
+                    "directory": overlay.directory()
+                })
+            })
+            .collect::<Vec<_>>();
+        content["overlays"] = serde_json::json!({ "entries": entries });
+    }
+
+    match &dp.mcmeta_extension {
+        Some(serde_json::Value::Object(extension)) => {
+            for (key, value) in extension {
+                match (key.as_str(), value) {
+                    ("pack", serde_json::Value::Object(pack)) => {
+                        for (key, value) in pack {
+                            content["pack"][key] = value.clone();
+                        }
+                    }
+                    _ => content[key] = value.clone(),
+                }
+            }
+        }
+        Some(_) => tracing::warn!("Ignoring pack.mcmeta extension that is not an object"),
+        None => {}
+    }
 
-    VFile::Text(content.to_string())
+    Ok(VFile::Text(serde_json::to_string(&content)?))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(feature = "fs_access")]
     #[test]
     fn test_datapack() {
         let template_dir = tempfile::tempdir().expect("error creating tempdir");
@@ -191,11 +835,695 @@ mod tests {
         assert_eq!(dp.namespaces.len(), 1);
     }
 
+    #[test]
+    fn test_namespaces() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        assert_eq!(dp.namespaces().count(), 0);
+
+        for name in ["foo", "bar", "baz"] {
+            dp.namespace_mut(name)
+                .function_mut("main")
+                .add_command("say hi");
+        }
+
+        assert_eq!(
+            dp.namespace_names().collect::<Vec<_>>(),
+            vec!["bar", "baz", "foo"]
+        );
+        assert!(dp
+            .namespaces()
+            .all(|(name, namespace)| namespace.get_name() == name
+                && namespace.function("main").is_some()));
+    }
+
+    #[test]
+    fn test_register_function() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        let function = Function::new("foo", "init");
+        dp.register_function(&function, &[("foo", "init"), ("minecraft", "load")]);
+
+        for (namespace, name) in [("foo", "init"), ("minecraft", "load")] {
+            let tag = dp
+                .namespace(namespace)
+                .and_then(|ns| ns.tag(name, tag::TagType::Function))
+                .expect("tag not created");
+            assert_eq!(
+                tag.get_values(),
+                &vec![tag::TagValue::Simple("foo:init".to_string())]
+            );
+        }
+    }
+
+    #[test]
+    fn test_add_load_at() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        dp.add_load("foo:b");
+        dp.add_load("foo:c");
+        dp.add_load_at("foo:a", 0);
+        dp.add_load_at("foo:d", 10);
+        dp.add_tick("foo:tick");
+        dp.add_tick_at("foo:first_tick", 0);
+
+        let minecraft = dp.namespace("minecraft").unwrap();
+        assert_eq!(
+            minecraft
+                .tag("load", tag::TagType::Function)
+                .unwrap()
+                .get_values(),
+            &vec![
+                tag::TagValue::from("foo:a"),
+                tag::TagValue::from("foo:b"),
+                tag::TagValue::from("foo:c"),
+                tag::TagValue::from("foo:d")
+            ]
+        );
+        assert_eq!(
+            minecraft
+                .tag("tick", tag::TagType::Function)
+                .unwrap()
+                .get_values(),
+            &vec![
+                tag::TagValue::from("foo:first_tick"),
+                tag::TagValue::from("foo:tick")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generated_namespace() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        for namespace in ["foo", "bar"] {
+            dp.namespace_mut(namespace)
+                .function_mut("main")
+                .add_command(Command::Group(vec![
+                    "say 1".into(),
+                    Command::Execute(Execute::As(
+                        "@a".to_string(),
+                        Box::new(Execute::Run(Box::new(Command::Group(vec![
+                            "say 2".into(),
+                            "say 3".into(),
+                        ])))),
+                    )),
+                ]));
+        }
+
+        let options = CompileOptions::default().with_generated_namespace("gen");
+        let compiled = dp.compile(&options);
+
+        let generated = Datapack::list_generated_functions(&compiled, &options);
+        assert_eq!(generated.len(), 4);
+        assert!(generated.iter().all(|id| id.starts_with("gen:sb/")));
+        assert!(generated
+            .iter()
+            .any(|id| id.starts_with("gen:sb/foo/main/")));
+        assert!(generated
+            .iter()
+            .any(|id| id.starts_with("gen:sb/bar/main/")));
+
+        let main = compiled
+            .get_file("data/foo/function/main.mcfunction")
+            .and_then(VFile::as_text)
+            .unwrap();
+        assert!(main.starts_with("function gen:sb/foo/main/"));
+
+        let mut streamed = VFolder::new();
+        dp.compile_streaming(&options, |path, file| streamed.add_file(path, file));
+        assert_eq!(streamed, compiled);
+
+        let default = dp.compile(&CompileOptions::default());
+        let generated = Datapack::list_generated_functions(&default, &CompileOptions::default());
+        assert_eq!(generated.len(), 4);
+        assert!(generated.iter().any(|id| id.starts_with("foo:sb/main/")));
+    }
+
+    #[test]
+    fn test_generated_names_unique() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        for namespace in ["foo", "bar"] {
+            let function = dp.namespace_mut(namespace).function_mut("main");
+            for i in 0..200 {
+                function.add_command(Command::Execute(Execute::If(
+                    Condition::from(format!("score @s foo matches {i}").as_str()),
+                    Box::new(Execute::Runs(vec![
+                        format!("say {i}").as_str().into(),
+                        Command::Execute(Execute::As(
+                            "@a".to_string(),
+                            Box::new(Execute::Run(Box::new(Command::Group(vec![
+                                "say a".into(),
+                                "say b".into(),
+                            ])))),
+                        )),
+                    ])),
+                    Some(Box::new(Execute::Run(Box::new("say else".into())))),
+                )));
+            }
+        }
+
+        let options = CompileOptions::default()
+            .with_generated_hash_length(8)
+            .with_max_function_lines(Some(50));
+        let compiled = dp.compile(&options);
+
+        let generated = Datapack::list_generated_functions(&compiled, &options);
+        let contents = compiled
+            .flatten()
+            .into_iter()
+            .filter_map(|(_, file)| file.as_text().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        let calls = contents
+            .iter()
+            .flat_map(|content| content.lines())
+            .filter_map(|line| line.split("function ").nth(1))
+            .filter(|id| id.contains(":sb/"))
+            .collect::<Vec<_>>();
+        // each generated function is called from exactly one place, so no two commands share a name
+        let unique_calls = calls
+            .iter()
+            .copied()
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(unique_calls.len(), calls.len());
+        assert_eq!(calls.len(), generated.len());
+        assert!(calls.iter().all(|id| generated.iter().any(|g| g == id)));
+        // 2 helper functions per command and the continuation functions
+        assert!(generated.len() > 2 * 2 * 200);
+
+        let condition_keys = contents
+            .iter()
+            .flat_map(|content| content.lines())
+            .filter(|line| line.ends_with("set value true"))
+            .collect::<Vec<_>>();
+        let unique_keys = condition_keys
+            .iter()
+            .copied()
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(condition_keys.len(), 2 * 200);
+        assert_eq!(unique_keys.len(), condition_keys.len());
+    }
+
+    #[test]
+    fn test_compile_diagnostics() {
+        let mut dp = Datapack::new(10);
+        let function = dp.namespace_mut("foo").function_mut("main");
+        function.add_command("say hi");
+        function.add_command(Command::Return(ReturnValue::Value(1)));
+        function.add_command(Command::Execute(Execute::As(
+            "@e[typ=pig]".to_string(),
+            Box::new(Execute::Run(Box::new("say hi".into()))),
+        )));
+        dp.namespace_mut("foo")
+            .function_mut("empty")
+            .add_command(Command::Comment("nothing".to_string()));
+
+        let (compiled, diagnostics) = dp
+            .try_compile_with_diagnostics(&CompileOptions::default())
+            .expect("failed to compile");
+        assert_eq!(compiled, dp.compile(&CompileOptions::default()));
+        assert_eq!(
+            diagnostics.iter().map(Diagnostic::kind).collect::<Vec<_>>(),
+            vec![
+                DiagnosticKind::IncompatibleCommand,
+                DiagnosticKind::InvalidSelector
+            ]
+        );
+        assert!(diagnostics[0].message().contains("foo:main"));
+        assert!(diagnostics[0]
+            .message()
+            .contains("command `return` requires pack format >= 15"));
+
+        let (_, diagnostics) = dp
+            .try_compile_with_diagnostics(&CompileOptions::default().with_minify(true))
+            .expect("failed to compile");
+        assert!(diagnostics.iter().any(|diagnostic| diagnostic.kind()
+            == DiagnosticKind::EmptyFunction
+            && diagnostic.message().contains("foo:empty")));
+    }
+
+    #[test]
+    fn test_compile_diagnostics_order() {
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let mut dp = Datapack::new(10);
+        for namespace in names {
+            let function = dp.namespace_mut(namespace).function_mut("main");
+            function.add_command(Command::Return(ReturnValue::Value(1)));
+            function.add_command(Command::Execute(Execute::If(
+                Condition::from("entity @s") | Condition::from("entity @p"),
+                Box::new(Execute::Runs(vec!["say 1".into(), "say 2".into()])),
+                Some(Box::new(Execute::Run(Box::new("say 3".into())))),
+            )));
+        }
+        let options = CompileOptions::default().with_generated_namespace("gen");
+
+        let (compiled, diagnostics) = dp
+            .try_compile_with_diagnostics(&options)
+            .expect("failed to compile");
+        // diagnostics are reported in the order of the namespaces
+        let namespaces = diagnostics
+            .iter()
+            .filter_map(|diagnostic| {
+                names
+                    .into_iter()
+                    .find(|name| diagnostic.message().contains(&format!("{name}:main")))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(namespaces, names);
+
+        for _ in 0..5 {
+            let (recompiled, rediagnostics) = dp
+                .try_compile_with_diagnostics(&options)
+                .expect("failed to compile");
+            assert_eq!(recompiled, compiled);
+            assert_eq!(rediagnostics, diagnostics);
+        }
+    }
+
+    #[test]
+    fn test_compile_pack_format() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        dp.namespace_mut("foo")
+            .function_mut("main")
+            .add_command("say hi");
+        dp.add_load("foo:main");
+
+        let compiled = dp.compile(&CompileOptions::default().with_pack_format(16));
+        assert!(compiled
+            .get_file("data/foo/functions/main.mcfunction")
+            .is_some());
+        assert!(compiled
+            .get_file("data/minecraft/tags/functions/load.json")
+            .is_some());
+
+        let old = Datapack::new(16);
+        let state = MutCompilerState::default();
+        let options = old
+            .prepare_compile_options(&CompileOptions::default(), &state)
+            .unwrap();
+        assert_eq!(options.pack_format(), 16);
+        assert!(state.lock().unwrap().take_diagnostics().is_empty());
+        let options = old
+            .prepare_compile_options(&CompileOptions::default().with_pack_format(20), &state)
+            .unwrap();
+        assert_eq!(options.pack_format(), 20);
+        assert_eq!(
+            state
+                .lock()
+                .unwrap()
+                .take_diagnostics()
+                .iter()
+                .map(Diagnostic::kind)
+                .collect::<Vec<_>>(),
+            vec![DiagnosticKind::PackFormat]
+        );
+    }
+
+    #[test]
+    fn test_icon() {
+        let icon = b"\x89PNG\r\n\x1a\nimage data".to_vec();
+        let mut template = VFolder::new();
+        template.add_file("pack.png", VFile::Binary(vec![0]));
+        let dp = Datapack::new(Datapack::LATEST_FORMAT)
+            .with_template_vfolder(template)
+            .with_icon(icon.clone());
+
+        let compiled = dp.compile(&CompileOptions::default());
+        assert_eq!(
+            compiled.get_file("pack.png"),
+            Some(&VFile::Binary(icon.clone()))
+        );
+
+        let mut streamed = VFolder::new();
+        dp.compile_streaming(&CompileOptions::default(), |path, file| {
+            streamed.add_file(path, file);
+        });
+        assert_eq!(streamed.get_file("pack.png"), Some(&VFile::Binary(icon)));
+    }
+
+    #[test]
+    fn test_generated_subfolder_path() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        dp.namespace_mut("foo")
+            .function_mut("main")
+            .add_command(Command::Group(vec!["say 1".into(), "say 2".into()]));
+
+        let options = CompileOptions::default()
+            .with_generated_subfolder("zzz_gen")
+            .with_generated_hash_length(24);
+        let compiled = dp.compile(&options);
+
+        let main = compiled
+            .get_file("data/foo/function/main.mcfunction")
+            .and_then(VFile::as_text)
+            .unwrap();
+        let path = main
+            .strip_prefix("function foo:")
+            .expect("group not compiled to a function call");
+        let hash = path
+            .strip_prefix("zzz_gen/main/")
+            .expect("generated function not in subfolder");
+        assert_eq!(hash.len(), 24);
+        assert!(compiled
+            .get_file(&format!("data/foo/function/{path}.mcfunction"))
+            .is_some());
+        assert_eq!(
+            Datapack::list_generated_functions(&compiled, &options),
+            vec![format!("foo:{path}")]
+        );
+    }
+
+    #[test]
+    fn test_nested_generated_paths() {
+        let group = |inner: Command| {
+            Command::Group(vec![
+                "say 1".into(),
+                Command::Execute(Execute::As(
+                    "@a".to_string(),
+                    Box::new(Execute::Run(Box::new(inner))),
+                )),
+            ])
+        };
+        let nested = group(group(group(Command::Group(vec![
+            "say 2".into(),
+            "say 3".into(),
+        ]))));
+
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        let namespace = dp.namespace_mut("foo");
+        namespace.function_mut("main").add_command(nested.clone());
+        // functions inside the generated subfolder are nested under it only once as well
+        namespace.function_mut("sb/sb/own").add_command(nested);
+
+        for options in [
+            CompileOptions::default(),
+            CompileOptions::default().with_generated_namespace("gen"),
+            CompileOptions::default().with_max_function_lines(Some(1)),
+        ] {
+            let compiled = dp.compile(&options);
+            let generated = Datapack::list_generated_functions(&compiled, &options);
+            assert!(generated.len() >= 8);
+            for id in generated.iter().filter(|id| *id != "foo:sb/sb/own") {
+                assert!(!id.contains("sb/sb"), "{id}");
+                assert_eq!(id.matches("sb/").count(), 1, "{id}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_compile_deterministic() {
+        let build = || {
+            let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+            for namespace in ["c", "a", "d", "b"] {
+                for function in ["main", "init"] {
+                    dp.namespace_mut(namespace)
+                        .function_mut(function)
+                        .add_command(Command::Group(vec!["say 1".into(), "say 2".into()]));
+                }
+                dp.register_function(
+                    &Function::new(namespace, "init"),
+                    &[(namespace, "init"), ("minecraft", "load")],
+                );
+            }
+            dp
+        };
+
+        let options = CompileOptions::default().with_generated_namespace("gen");
+        let stream = |dp: &Datapack| {
+            let mut files = Vec::new();
+            dp.compile_streaming(&options, |path, file| files.push((path.to_string(), file)));
+            files
+        };
+
+        let (first, second) = (build(), build());
+        assert_eq!(first.compile(&options), second.compile(&options));
+        assert_eq!(stream(&first), stream(&second));
+    }
+
+    #[test]
+    fn test_try_compile() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        dp.namespace_mut("foo")
+            .function_mut("bar")
+            .add_command(Command::Group(vec!["say 1".into(), "say 2".into()]));
+        dp.add_load("foo:bar");
+
+        let options = CompileOptions::default().with_generated_namespace("gen");
+        assert_eq!(dp.try_compile(&options).ok(), Some(dp.compile(&options)));
+
+        let state = MutCompilerState::default();
+        let _ = std::panic::catch_unwind(|| {
+            let _guard = state.lock().unwrap();
+            panic!("poison the state");
+        });
+        assert!(matches!(
+            compile_generated_functions(&options, &state, |_, _| {}),
+            Err(CompileError::PoisonedLock)
+        ));
+        assert!(matches!(
+            dp.namespace("foo")
+                .unwrap()
+                .try_compile_streaming(&options, &state, |_, _| {}),
+            Err(CompileError::PoisonedLock)
+        ));
+    }
+
+    #[test]
+    fn test_compile_streaming() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        let function = dp.namespace_mut("foo").function_mut("bar");
+        function.add_command("say bar");
+        function.add_command(Command::Group(vec!["say 1".into(), "say 2".into()]));
+        dp.add_load("foo:bar");
+        dp.add_custom_file("pack.png", VFile::Binary(vec![1, 2, 3]));
+        dp.add_custom_file("data/foo/function/bar.mcfunction", VFile::from("say old"));
+
+        let mut streamed = VFolder::new();
+        let mut count = 0;
+        dp.compile_streaming(&CompileOptions::default(), |path, file| {
+            streamed.add_file(path, file);
+            count += 1;
+        });
+
+        assert_eq!(streamed, dp.compile(&CompileOptions::default()));
+        // custom files, mcmeta, two functions, the overwritten function and the load tag
+        assert_eq!(count, 6);
+    }
+
+    #[test]
+    fn test_empty_namespaces() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        let _ = dp.namespace_mut("empty");
+        dp.namespace_mut("foo")
+            .function_mut("bar")
+            .add_command("say bar");
+
+        let compiled = dp.compile(&CompileOptions::default());
+        assert!(compiled.get_folder("data/empty").is_none());
+        assert!(compiled.get_folder("data/foo").is_some());
+        assert!(compiled.get_folder("data/minecraft").is_none());
+
+        dp.add_load("foo:bar");
+        let compiled = dp.compile(&CompileOptions::default().with_emit_empty_namespaces(true));
+        assert!(compiled.get_folder("data/empty").is_some());
+        assert!(compiled.get_folder("data/minecraft").is_some());
+    }
+
+    #[test]
+    fn test_generated_readme() {
+        let dp = Datapack::new(Datapack::LATEST_FORMAT)
+            .with_description("foo")
+            .with_generated_readme("# {description}\nFormat {pack_format}, built on {date}");
+
+        let readme = generate_readme(&dp, dp.readme_template.as_deref().unwrap(), "2024-10-01");
+        assert_eq!(
+            readme.as_text(),
+            Some("# foo\nFormat 48, built on 2024-10-01")
+        );
+
+        let compiled = dp.compile(&CompileOptions::default());
+        assert!(compiled.get_file("README.md").is_some());
+        assert!(Datapack::new(Datapack::LATEST_FORMAT)
+            .compile(&CompileOptions::default())
+            .get_file("README.md")
+            .is_none());
+
+        let today = current_date();
+        assert_eq!(today.len(), 10);
+        assert!(today.as_str() > "2024-01-01");
+    }
+
+    #[test]
+    fn test_crossed_directory_renames() {
+        let dp = Datapack::new(48);
+        assert!(dp.crossed_directory_renames().is_empty());
+
+        let dp = Datapack::new(48).with_supported_formats(45..=48);
+        assert!(dp.crossed_directory_renames().is_empty());
+
+        let dp = Datapack::new(48).with_supported_formats(44..=48);
+        assert_eq!(dp.crossed_directory_renames(), vec![45]);
+
+        let mut dp = Datapack::new(41).with_supported_formats(41..=48);
+        assert_eq!(dp.crossed_directory_renames(), vec![43, 45]);
+
+        // an overlay with the new directory names only covers the renames it fully includes
+        dp.add_overlay("overlay_45", 45..=48);
+        assert_eq!(dp.crossed_directory_renames(), vec![43]);
+        dp.add_overlay("overlay_43", 43..=48);
+        assert!(dp.crossed_directory_renames().is_empty());
+
+        let mut dp = Datapack::new(48).with_supported_formats(41..=48);
+        dp.add_overlay("overlay_41", 41..=42);
+        assert_eq!(dp.crossed_directory_renames(), vec![45]);
+    }
+
+    #[test]
+    fn test_mcmeta_extension() {
+        let dp = Datapack::new(Datapack::LATEST_FORMAT)
+            .with_description("foo")
+            .with_mcmeta_extension(serde_json::json!({
+                "launcher": { "id": "mypack" },
+                "pack": { "description": "bar", "custom": true }
+            }));
+        let mcmeta = generate_mcmeta(
+            &dp,
+            &CompileOptions::default(),
+            &MutCompilerState::default(),
+        )
+        .unwrap();
+        let json = serde_json::from_str::<serde_json::Value>(mcmeta.as_text().unwrap()).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "launcher": { "id": "mypack" },
+                "pack": {
+                    "description": "bar",
+                    "pack_format": Datapack::LATEST_FORMAT,
+                    "custom": true
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_overlays() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT).with_supported_formats(18..=48);
+        dp.namespace_mut("foo")
+            .function_mut("main")
+            .add_command("say new");
+        let overlay = dp.add_overlay("old", 18..=41);
+        overlay
+            .namespace_mut("foo")
+            .function_mut("main")
+            .add_command("say old");
+        overlay
+            .namespace_mut("foo")
+            .tag_mut("stones", tag::TagType::Block)
+            .add_value(tag::TagValue::from("minecraft:stone"));
+        let _ = dp.add_overlay("newer", 49..=57);
+        let _ = dp.add_overlay("old", 18..=44);
+        assert_eq!(dp.overlays().len(), 2);
+        assert_eq!(dp.overlay("old").map(Overlay::formats), Some(&(18..=44)));
+
+        let compiled = dp.compile(&CompileOptions::default());
+        assert_eq!(
+            compiled
+                .get_file("data/foo/function/main.mcfunction")
+                .and_then(VFile::as_text),
+            Some("say new")
+        );
+        assert_eq!(
+            compiled
+                .get_file("old/data/foo/functions/main.mcfunction")
+                .and_then(VFile::as_text),
+            Some("say old")
+        );
+        assert!(compiled
+            .get_file("old/data/foo/tags/block/stones.json")
+            .is_some());
+
+        let mcmeta = compiled
+            .get_file("pack.mcmeta")
+            .and_then(VFile::as_text)
+            .unwrap();
+        let json = serde_json::from_str::<serde_json::Value>(mcmeta).unwrap();
+        assert_eq!(
+            json["overlays"],
+            serde_json::json!({
+                "entries": [
+                    { "formats": { "min_inclusive": 18, "max_inclusive": 44 }, "directory": "old" },
+                    { "formats": { "min_inclusive": 49, "max_inclusive": 57 }, "directory": "newer" }
+                ]
+            })
+        );
+
+        let mut streamed = VFolder::new();
+        dp.compile_streaming(&CompileOptions::default(), |path, file| {
+            streamed.add_file(path, file);
+        });
+        assert_eq!(streamed, compiled);
+    }
+
+    #[test]
+    fn test_validate_overlays() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        dp.add_overlay("old", 10..=12)
+            .namespace_mut("foo")
+            .function_mut("main")
+            .add_command(Command::Return(ReturnValue::Value(1)));
+        assert!(!dp.validate());
+
+        let _ = dp.add_overlay("old", 15..=41);
+        assert!(dp.validate());
+    }
+
+    #[test]
+    fn test_features_and_filter() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT)
+            .with_description("foo")
+            .with_features(&["minecraft:update_1_21"]);
+        dp.add_filter_block(Some("minecraft"), Some("recipes?/.*"));
+        dp.add_filter_block(None, Some("loot_table/.*"));
+        let mcmeta = generate_mcmeta(
+            &dp,
+            &CompileOptions::default(),
+            &MutCompilerState::default(),
+        )
+        .unwrap();
+        let json = serde_json::from_str::<serde_json::Value>(mcmeta.as_text().unwrap()).unwrap();
+
+        assert_eq!(
+            json["features"],
+            serde_json::json!({ "enabled": ["minecraft:update_1_21"] })
+        );
+        assert_eq!(
+            json["filter"],
+            serde_json::json!({
+                "block": [
+                    { "namespace": "minecraft", "path": "recipes?/.*" },
+                    { "path": "loot_table/.*" }
+                ]
+            })
+        );
+
+        let plain = generate_mcmeta(
+            &Datapack::new(Datapack::LATEST_FORMAT).with_description("foo"),
+            &CompileOptions::default(),
+            &MutCompilerState::default(),
+        )
+        .unwrap();
+        let json = serde_json::from_str::<serde_json::Value>(plain.as_text().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "pack": { "description": "foo", "pack_format": Datapack::LATEST_FORMAT }
+            })
+        );
+    }
+
     #[test]
     fn test_generate_mcmeta() {
         let dp = &Datapack::new(Datapack::LATEST_FORMAT).with_description("foo");
         let state = Mutex::new(CompilerState::default());
-        let mcmeta = generate_mcmeta(dp, &CompileOptions::default(), &state);
+        let mcmeta = generate_mcmeta(dp, &CompileOptions::default(), &state).unwrap();
 
         let json = if let VFile::Text(text) = mcmeta {
             serde_json::from_str::<serde_json::Value>(&text).unwrap()
@@ -223,4 +1551,33 @@ mod tests {
             Some(u64::from(Datapack::LATEST_FORMAT))
         );
     }
+
+    #[test]
+    fn test_description_component() {
+        let dp = Datapack::new(Datapack::LATEST_FORMAT)
+            .with_description_component(TextComponent::new("foo").with_color("gold"))
+            .with_generated_readme("{description}");
+        let compiled = dp.compile(&CompileOptions::default());
+
+        let mcmeta = compiled
+            .get_file("pack.mcmeta")
+            .and_then(VFile::as_text)
+            .map(|text| serde_json::from_str::<serde_json::Value>(text).unwrap())
+            .expect("no pack.mcmeta");
+        assert_eq!(
+            mcmeta["pack"]["description"],
+            serde_json::json!({ "text": "foo", "color": "gold" })
+        );
+        assert_eq!(
+            compiled.get_file("README.md").and_then(VFile::as_text),
+            Some("foo")
+        );
+
+        let dp = dp.with_description("bar");
+        let mcmeta = dp.compile(&CompileOptions::default());
+        assert!(mcmeta
+            .get_file("pack.mcmeta")
+            .and_then(VFile::as_text)
+            .is_some_and(|text| text.contains(r#""description":"bar""#)));
+    }
 }
@@ -2,15 +2,22 @@
 
 use crate::{
     util::{
-        compile::{CompileOptions, FunctionCompilerState, MutCompilerState},
+        compile::{
+            check_poisoned, try_report, CompileError, CompileOptions, DiagnosticKind,
+            FunctionCompilerState, MutCompilerState,
+        },
         pack_format, ExtendableQueue,
     },
-    virtual_fs::VFolder,
+    virtual_fs::{VFile, VFolder},
 };
 
 use super::{
+    advancement::Advancement,
     function::Function,
+    loot::{ItemModifier, LootTable},
+    predicate::Predicate,
     tag::{Tag, TagType},
+    validation::SelectorChecker,
 };
 use std::{
     collections::{HashMap, VecDeque},
@@ -24,6 +31,10 @@ pub struct Namespace {
     name: String,
     functions: HashMap<String, Function>,
     tags: HashMap<(String, TagType), Tag>,
+    predicates: HashMap<String, Predicate>,
+    loot_tables: HashMap<String, LootTable>,
+    item_modifiers: HashMap<String, ItemModifier>,
+    advancements: HashMap<String, Advancement>,
 }
 
 impl Namespace {
@@ -33,6 +44,10 @@ impl Namespace {
             name: name.to_string(),
             functions: HashMap::new(),
             tags: HashMap::new(),
+            predicates: HashMap::new(),
+            loot_tables: HashMap::new(),
+            item_modifiers: HashMap::new(),
+            advancements: HashMap::new(),
         }
     }
 
@@ -54,6 +69,41 @@ impl Namespace {
         &self.tags
     }
 
+    /// Get the predicates of the namespace.
+    #[must_use]
+    pub fn get_predicates(&self) -> &HashMap<String, Predicate> {
+        &self.predicates
+    }
+
+    /// Get the loot tables of the namespace.
+    #[must_use]
+    pub fn get_loot_tables(&self) -> &HashMap<String, LootTable> {
+        &self.loot_tables
+    }
+
+    /// Get the item modifiers of the namespace.
+    #[must_use]
+    pub fn get_item_modifiers(&self) -> &HashMap<String, ItemModifier> {
+        &self.item_modifiers
+    }
+
+    /// Get the advancements of the namespace.
+    #[must_use]
+    pub fn get_advancements(&self) -> &HashMap<String, Advancement> {
+        &self.advancements
+    }
+
+    /// Check whether the namespace contains no functions, tags or other resources.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+            && self.tags.is_empty()
+            && self.predicates.is_empty()
+            && self.loot_tables.is_empty()
+            && self.item_modifiers.is_empty()
+            && self.advancements.is_empty()
+    }
+
     /// Get a function by name.
     #[must_use]
     pub fn function(&self, name: &str) -> Option<&Function> {
@@ -68,6 +118,22 @@ impl Namespace {
             .or_insert_with(|| Function::new(&self.name, name))
     }
 
+    /// Add an existing function to the namespace, keyed by its name.
+    /// The namespace of the function is changed to this namespace.
+    ///
+    /// Returns the replaced function if one with the same name existed.
+    pub fn add_existing_function(&mut self, mut function: Function) -> Option<Function> {
+        function.set_namespace(&self.name);
+        self.functions.insert(function.name().clone(), function)
+    }
+
+    /// Remove a function by name.
+    ///
+    /// Returns the removed function if it existed.
+    pub fn remove_function(&mut self, name: &str) -> Option<Function> {
+        self.functions.remove(name)
+    }
+
     /// Get a tag by name and type.
     #[must_use]
     pub fn tag(&self, name: &str, tag_type: TagType) -> Option<&Tag> {
@@ -82,25 +148,157 @@ impl Namespace {
             .or_insert_with(|| Tag::new(false))
     }
 
+    /// Get a predicate by name.
+    #[must_use]
+    pub fn predicate(&self, name: &str) -> Option<&Predicate> {
+        self.predicates.get(name)
+    }
+
+    /// Mutably get a predicate by name or create a new one without conditions if it doesn't exist.
+    #[must_use]
+    pub fn predicate_mut(&mut self, name: &str) -> &mut Predicate {
+        self.predicates.entry(name.to_string()).or_default()
+    }
+
+    /// Get a loot table by name.
+    #[must_use]
+    pub fn loot_table(&self, name: &str) -> Option<&LootTable> {
+        self.loot_tables.get(name)
+    }
+
+    /// Mutably get a loot table by name or create a new, empty one if it doesn't exist.
+    #[must_use]
+    pub fn loot_table_mut(&mut self, name: &str) -> &mut LootTable {
+        self.loot_tables.entry(name.to_string()).or_default()
+    }
+
+    /// Get an item modifier by name.
+    #[must_use]
+    pub fn item_modifier(&self, name: &str) -> Option<&ItemModifier> {
+        self.item_modifiers.get(name)
+    }
+
+    /// Mutably get an item modifier by name or create a new one without functions if it doesn't exist.
+    #[must_use]
+    pub fn item_modifier_mut(&mut self, name: &str) -> &mut ItemModifier {
+        self.item_modifiers.entry(name.to_string()).or_default()
+    }
+
+    /// Get an advancement by name.
+    #[must_use]
+    pub fn advancement(&self, name: &str) -> Option<&Advancement> {
+        self.advancements.get(name)
+    }
+
+    /// Mutably get an advancement by name or create a new one without criteria if it doesn't exist.
+    #[must_use]
+    pub fn advancement_mut(&mut self, name: &str) -> &mut Advancement {
+        self.advancements.entry(name.to_string()).or_default()
+    }
+
+    /// Merge another namespace into this namespace.
+    ///
+    /// Functions and other resources of the other namespace replace the ones with the same name,
+    /// tags are merged with [`Tag::merge`].
+    /// Returns the sorted names of the replaced functions.
+    pub fn merge(&mut self, other: Self) -> Vec<String> {
+        self.merge_with_conflicts(other).functions
+    }
+
+    /// Merge another namespace into this namespace like [`Namespace::merge`].
+    ///
+    /// Returns the names of all resources that existed in both namespaces.
+    pub(in crate::datapack) fn merge_with_conflicts(&mut self, other: Self) -> NamespaceConflicts {
+        let mut replaced_functions = other
+            .functions
+            .into_values()
+            .filter_map(|function| {
+                let name = function.name().clone();
+                self.add_existing_function(function).map(|_| name)
+            })
+            .collect::<Vec<_>>();
+        replaced_functions.sort();
+
+        let mut merged_tags = Vec::new();
+        for (key, tag) in other.tags {
+            if let Some(existing) = self.tags.get_mut(&key) {
+                existing.merge(tag);
+                merged_tags.push(key);
+            } else {
+                self.tags.insert(key, tag);
+            }
+        }
+        merged_tags.sort_by(|(a, a_type), (b, b_type)| {
+            (a, a_type.to_string()).cmp(&(b, b_type.to_string()))
+        });
+
+        NamespaceConflicts {
+            functions: replaced_functions,
+            tags: merged_tags,
+            predicates: extend_replacing(&mut self.predicates, other.predicates),
+            loot_tables: extend_replacing(&mut self.loot_tables, other.loot_tables),
+            item_modifiers: extend_replacing(&mut self.item_modifiers, other.item_modifiers),
+            advancements: extend_replacing(&mut self.advancements, other.advancements),
+        }
+    }
+
     /// Compile the namespace into a virtual folder.
     #[tracing::instrument(level = "debug", skip_all)]
     pub fn compile(&self, options: &CompileOptions, state: &MutCompilerState) -> VFolder {
-        tracing::debug!("Compiling namespace");
-
         let mut root_folder = VFolder::new();
+        self.compile_streaming(options, state, |path, file| {
+            root_folder.add_file(path, file);
+        });
+        root_folder
+    }
+
+    /// Compile the namespace, passing each compiled file with its path relative to the namespace folder to the sink
+    /// as soon as it is compiled.
+    ///
+    /// Panics if a file cannot be serialized, use [`Namespace::try_compile_streaming`] to handle the error instead.
+    pub fn compile_streaming<F>(&self, options: &CompileOptions, state: &MutCompilerState, sink: F)
+    where
+        F: FnMut(&str, VFile),
+    {
+        self.try_compile_streaming(options, state, sink)
+            .expect("Failed to compile namespace");
+    }
 
-        // collect functions
-        let functions = self
+    /// Compile the namespace, passing each compiled file with its path relative to the namespace folder to the sink
+    /// as soon as it is compiled.
+    ///
+    /// # Errors
+    /// - If a tag cannot be serialized
+    /// - If the compiler state was poisoned
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn try_compile_streaming<F>(
+        &self,
+        options: &CompileOptions,
+        state: &MutCompilerState,
+        mut sink: F,
+    ) -> Result<(), CompileError>
+    where
+        F: FnMut(&str, VFile),
+    {
+        tracing::debug!("Compiling namespace");
+
+        // collect functions, sorted by name to make the generated function names deterministic
+        let mut functions = self
             .functions
             .iter()
             .map(|(name, content)| (name.clone(), content.clone()))
-            .collect::<VecDeque<_>>();
+            .collect::<Vec<_>>();
+        functions.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (_, function) in &functions {
+            self.diagnose_commands(function, options, state)?;
+        }
+        let functions = VecDeque::from(functions);
 
         // compile all functions, allow adding new functions while compiling
         let mut functions = ExtendableQueue::from(functions);
         while let Some((path, function)) = functions.next() {
             let function_state = FunctionCompilerState::new(&path, &self.name, functions.clone());
-            root_folder.add_file(
+            sink(
                 &format!(
                     "{directory_name}/{path}.mcfunction",
                     directory_name = pack_format::function_directory_name(options.pack_format)
@@ -109,10 +307,14 @@ impl Namespace {
             );
         }
 
-        // compile tags
-        for ((path, tag_type), tag) in &self.tags {
-            let vfile = tag.compile(options, state);
-            root_folder.add_file(
+        // compile tags, sorted to make the output order deterministic
+        let mut tags = self.tags.iter().collect::<Vec<_>>();
+        tags.sort_by(|((a, a_type), _), ((b, b_type), _)| {
+            (a, a_type.to_string()).cmp(&(b, b_type.to_string()))
+        });
+        for ((path, tag_type), tag) in tags {
+            let vfile = tag.try_compile(options, state)?;
+            sink(
                 &format!(
                     "tags/{tag_directory}/{path}.json",
                     tag_directory = tag_type.get_directory_name(options.pack_format)
@@ -121,7 +323,77 @@ impl Namespace {
             );
         }
 
-        root_folder
+        compile_resources(
+            &self.predicates,
+            "predicate",
+            Predicate::compile,
+            options,
+            &mut sink,
+        );
+        compile_resources(
+            &self.loot_tables,
+            "loot_table",
+            LootTable::compile,
+            options,
+            &mut sink,
+        );
+        compile_resources(
+            &self.item_modifiers,
+            "item_modifier",
+            ItemModifier::compile,
+            options,
+            &mut sink,
+        );
+        compile_resources(
+            &self.advancements,
+            "advancement",
+            Advancement::compile,
+            options,
+            &mut sink,
+        );
+
+        check_poisoned(state)
+    }
+
+    /// Report commands of the function that are incompatible with the pack format or contain invalid selectors.
+    ///
+    /// Only functions of the namespace are checked, as generated functions contain the same commands.
+    fn diagnose_commands(
+        &self,
+        function: &Function,
+        options: &CompileOptions,
+        state: &MutCompilerState,
+    ) -> Result<(), CompileError> {
+        let pack_formats = options.pack_format..=options.pack_format;
+        for (index, command) in function.get_commands().iter().enumerate() {
+            for reason in command.incompatibilities(&pack_formats) {
+                try_report(
+                    state,
+                    DiagnosticKind::IncompatibleCommand,
+                    format!(
+                        "Command {index} of function '{}:{}' is not supported by pack format {}: {reason}",
+                        self.name,
+                        function.name(),
+                        options.pack_format
+                    ),
+                )?;
+            }
+
+            let mut checker = SelectorChecker::default();
+            command.visit(&mut checker);
+            for err in checker.errors {
+                try_report(
+                    state,
+                    DiagnosticKind::InvalidSelector,
+                    format!(
+                        "Command {index} of function '{}:{}' has an invalid selector: {err}",
+                        self.name,
+                        function.name()
+                    ),
+                )?;
+            }
+        }
+        Ok(())
     }
 
     /// Check whether the namespace is valid with the given pack format.
@@ -133,8 +405,58 @@ impl Namespace {
     }
 }
 
+/// Names of the resources that existed in both namespaces merged with [`Namespace::merge_with_conflicts`], each sorted.
+#[derive(Debug, Default)]
+pub(in crate::datapack) struct NamespaceConflicts {
+    /// Names of the replaced functions.
+    pub functions: Vec<String>,
+    /// Keys of the merged tags.
+    pub tags: Vec<(String, TagType)>,
+    /// Names of the replaced predicates.
+    pub predicates: Vec<String>,
+    /// Names of the replaced loot tables.
+    pub loot_tables: Vec<String>,
+    /// Names of the replaced item modifiers.
+    pub item_modifiers: Vec<String>,
+    /// Names of the replaced advancements.
+    pub advancements: Vec<String>,
+}
+
+/// Insert the resources into the map, returning the sorted names of the replaced resources.
+fn extend_replacing<T>(
+    resources: &mut HashMap<String, T>,
+    other: HashMap<String, T>,
+) -> Vec<String> {
+    let mut replaced = other
+        .into_iter()
+        .filter_map(|(name, resource)| resources.insert(name.clone(), resource).map(|_| name))
+        .collect::<Vec<_>>();
+    replaced.sort();
+    replaced
+}
+
+/// Compile the JSON resources into the directory of the resource, sorted by path to make the output order deterministic.
+fn compile_resources<T, F>(
+    resources: &HashMap<String, T>,
+    singular_directory: &str,
+    compile: impl Fn(&T) -> VFile,
+    options: &CompileOptions,
+    sink: &mut F,
+) where
+    F: FnMut(&str, VFile),
+{
+    let directory = pack_format::resource_directory_name(singular_directory, options.pack_format);
+    let mut resources = resources.iter().collect::<Vec<_>>();
+    resources.sort_by_key(|(path, _)| *path);
+    for (path, resource) in resources {
+        sink(&format!("{directory}/{path}.json"), compile(resource));
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::datapack::{advancement::Criterion, Command};
+
     use super::*;
 
     #[test]
@@ -151,4 +473,355 @@ mod tests {
         assert!(namespace.function("bar").is_some());
         assert!(namespace.function("baz").is_none());
     }
+
+    #[test]
+    fn test_add_existing_function() {
+        let mut namespace = Namespace::new("foo");
+
+        let mut function = Function::new("other", "bar");
+        function.add_command("say bar");
+        assert!(namespace.add_existing_function(function).is_none());
+
+        let added = namespace.function("bar").expect("function not added");
+        assert_eq!(added.namespace(), "foo");
+        assert_eq!(added.get_commands().len(), 1);
+
+        let replaced = namespace
+            .add_existing_function(Function::new("foo", "bar"))
+            .expect("function not replaced");
+        assert_eq!(replaced.get_commands().len(), 1);
+        assert_eq!(namespace.function("bar").unwrap().get_commands().len(), 0);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let mut namespace = Namespace::new("foo");
+        assert!(namespace.is_empty());
+        namespace.predicate_mut("chance").add_random_chance(0.25);
+        assert!(!namespace.is_empty());
+        assert!(namespace.predicate("chance").is_some());
+
+        let compiled = namespace.compile(&CompileOptions::default(), &MutCompilerState::default());
+        assert_eq!(
+            compiled
+                .get_file("predicate/chance.json")
+                .and_then(VFile::as_text),
+            Some(r#"{"chance":0.25,"condition":"minecraft:random_chance"}"#)
+        );
+
+        let old = namespace.compile(
+            &CompileOptions::default().with_pack_format(26),
+            &MutCompilerState::default(),
+        );
+        assert!(old.get_file("predicates/chance.json").is_some());
+        assert!(old.get_file("predicate/chance.json").is_none());
+    }
+
+    #[test]
+    fn test_resource_directory_rename() {
+        let mut namespace = Namespace::new("foo");
+        namespace.predicate_mut("chance").add_random_chance(0.25);
+        let _ = namespace.loot_table_mut("chest");
+        let _ = namespace.item_modifier_mut("empty");
+        let _ = namespace.advancement_mut("root");
+
+        let compile = |pack_format| {
+            namespace.compile(
+                &CompileOptions::default().with_pack_format(pack_format),
+                &MutCompilerState::default(),
+            )
+        };
+        let files = [
+            "predicate/chance.json",
+            "loot_table/chest.json",
+            "item_modifier/empty.json",
+            "advancement/root.json",
+        ];
+
+        let before = compile(44);
+        for file in files {
+            let (directory, name) = file.split_once('/').unwrap();
+            assert!(before.get_file(&format!("{directory}s/{name}")).is_some());
+            assert!(before.get_file(file).is_none());
+        }
+
+        let after = compile(45);
+        for file in files {
+            let (directory, name) = file.split_once('/').unwrap();
+            assert!(after.get_file(file).is_some());
+            assert!(after.get_file(&format!("{directory}s/{name}")).is_none());
+        }
+    }
+
+    #[test]
+    fn test_loot_tables_and_item_modifiers() {
+        let mut namespace = Namespace::new("foo");
+        *namespace.loot_table_mut("blocks/ore") = LootTable(serde_json::json!({
+            "type": "minecraft:block",
+            "pools": []
+        }));
+        let _ = namespace.item_modifier_mut("empty");
+        assert!(namespace.loot_table("blocks/ore").is_some());
+        assert!(namespace.item_modifier("empty").is_some());
+
+        for (pack_format, loot_tables, item_modifiers) in [
+            (26, "loot_tables", "item_modifiers"),
+            (48, "loot_table", "item_modifier"),
+        ] {
+            let compiled = namespace.compile(
+                &CompileOptions::default().with_pack_format(pack_format),
+                &MutCompilerState::default(),
+            );
+            assert_eq!(
+                compiled
+                    .get_file(&format!("{loot_tables}/blocks/ore.json"))
+                    .and_then(VFile::as_text),
+                Some(r#"{"pools":[],"type":"minecraft:block"}"#)
+            );
+            assert_eq!(
+                compiled
+                    .get_file(&format!("{item_modifiers}/empty.json"))
+                    .and_then(VFile::as_text),
+                Some("[]")
+            );
+        }
+    }
+
+    #[test]
+    fn test_advancements() {
+        let mut namespace = Namespace::new("foo");
+        *namespace.advancement_mut("on_tick") =
+            Advancement::new().with_criterion("tick", Criterion::new("minecraft:tick"));
+        assert!(namespace.advancement("on_tick").is_some());
+
+        for (pack_format, directory) in [(26, "advancements"), (48, "advancement")] {
+            let compiled = namespace.compile(
+                &CompileOptions::default().with_pack_format(pack_format),
+                &MutCompilerState::default(),
+            );
+            assert_eq!(
+                compiled
+                    .get_file(&format!("{directory}/on_tick.json"))
+                    .and_then(VFile::as_text),
+                Some(r#"{"criteria":{"tick":{"trigger":"minecraft:tick"}}}"#)
+            );
+        }
+
+        assert_eq!(
+            Command::advancement_grant("@s", "foo:on_tick"),
+            Command::raw("advancement grant @s only foo:on_tick")
+        );
+    }
+
+    #[test]
+    fn test_remove_function() {
+        let mut namespace = Namespace::new("foo");
+        namespace.function_mut("bar").add_command("say bar");
+
+        let removed = namespace
+            .remove_function("bar")
+            .expect("function not removed");
+        assert_eq!(removed.get_commands().len(), 1);
+        assert!(namespace.function("bar").is_none());
+        assert!(namespace.remove_function("bar").is_none());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut namespace = Namespace::new("foo");
+        namespace.function_mut("a").add_command("say a");
+        namespace.function_mut("b").add_command("say b");
+        namespace
+            .tag_mut("load", TagType::Function)
+            .add_value("foo:a".into());
+
+        let mut other = Namespace::new("other");
+        other.function_mut("b").add_command("say other b");
+        other.function_mut("c").add_command("say c");
+        let tag = other.tag_mut("load", TagType::Function);
+        tag.add_value("foo:a".into());
+        tag.add_value("foo:c".into());
+        other
+            .tag_mut("tick", TagType::Function)
+            .add_value("foo:b".into());
+
+        let replaced = namespace.merge(other);
+        assert_eq!(replaced, vec!["b".to_string()]);
+        assert_eq!(namespace.get_functions().len(), 3);
+        let b = namespace.function("b").expect("function missing");
+        assert_eq!(b.get_commands(), &vec!["say other b".into()]);
+        assert_eq!(b.namespace(), "foo");
+
+        let load = namespace
+            .tag("load", TagType::Function)
+            .expect("tag missing");
+        assert_eq!(load.get_values(), &vec!["foo:a".into(), "foo:c".into()]);
+        assert!(namespace.tag("tick", TagType::Function).is_some());
+
+        let mut other = Namespace::new("other");
+        let tag = other.tag_mut("load", TagType::Function);
+        tag.set_replace(true);
+        tag.add_value("foo:b".into());
+        assert!(namespace.merge(other).is_empty());
+        assert_eq!(
+            namespace
+                .tag("load", TagType::Function)
+                .map(Tag::get_values),
+            Some(&vec!["foo:b".into()])
+        );
+    }
+
+    #[test]
+    fn test_compile_deterministic() {
+        let mut namespace = Namespace::new("foo");
+        for name in ["c", "a", "d", "b", "e"] {
+            namespace
+                .function_mut(name)
+                .add_command(crate::datapack::Command::Group(vec![
+                    "say 1".into(),
+                    "say 2".into(),
+                ]));
+        }
+
+        let options = CompileOptions::default();
+        let first = namespace.compile(&options, &MutCompilerState::default());
+        let second = namespace.compile(&options, &MutCompilerState::default());
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_max_function_lines() {
+        use crate::datapack::{Command, Execute};
+
+        let mut namespace = Namespace::new("foo");
+        let function = namespace.function_mut("main");
+        for i in 0..4 {
+            function.add_command(format!("say {i}").as_str());
+        }
+        // compiles into two lines, which must not be split
+        function.add_command(Command::Execute(Execute::As(
+            "@a".to_string(),
+            Box::new(Execute::Runs(vec!["say a".into(), "say b".into()])),
+        )));
+        for i in 4..7 {
+            function.add_command(format!("say {i}").as_str());
+        }
+
+        let options = CompileOptions::default().with_max_function_lines(Some(3));
+        let compiled = namespace.compile(&options, &MutCompilerState::default());
+
+        // follow the continuation chain
+        let mut path = "main".to_string();
+        let mut chunks = Vec::new();
+        loop {
+            let content = compiled
+                .get_file(&format!("function/{path}.mcfunction"))
+                .and_then(VFile::as_text)
+                .expect("continuation function missing");
+            let mut lines = content.lines().collect::<Vec<_>>();
+            assert!(lines.len() <= 3);
+            let next = lines
+                .last()
+                .and_then(|line| line.strip_prefix("return run function foo:"))
+                .map(ToString::to_string);
+            if next.is_some() {
+                lines.pop();
+            }
+            chunks.push(lines.join("\n"));
+            match next {
+                Some(next) => path = next,
+                None => break,
+            }
+        }
+
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0], "say 0\nsay 1");
+        assert_eq!(chunks[1], "say 2\nsay 3");
+        assert_eq!(
+            chunks[2],
+            "execute as @a run say a\nexecute as @a run say b"
+        );
+        // the last part fits into the limit without another continuation
+        assert_eq!(chunks[3], "say 4\nsay 5\nsay 6");
+
+        let unsplit = namespace.compile(&CompileOptions::default(), &MutCompilerState::default());
+        assert_eq!(
+            unsplit
+                .get_file("function/main.mcfunction")
+                .and_then(VFile::as_text)
+                .map(|content| content.lines().count()),
+            Some(9)
+        );
+    }
+
+    #[test]
+    fn test_max_function_lines_return() {
+        use crate::datapack::{Command, ReturnValue};
+
+        let mut namespace = Namespace::new("foo");
+        let function = namespace.function_mut("main");
+        function.add_command("say 0");
+        function.add_command("say 1");
+        function.add_command(Command::Return(ReturnValue::Value(1)));
+        function.add_command("say 2");
+
+        let compile = |pack_format| {
+            let options = CompileOptions::default()
+                .with_pack_format(pack_format)
+                .with_max_function_lines(Some(2));
+            let state = MutCompilerState::default();
+            let compiled = namespace.compile(&options, &state);
+            let diagnostics = state.lock().unwrap().take_diagnostics();
+            (compiled, diagnostics)
+        };
+        let main = |compiled: &VFolder, directory: &str| {
+            compiled
+                .get_file(&format!("{directory}/main.mcfunction"))
+                .and_then(VFile::as_text)
+                .map(ToString::to_string)
+                .unwrap()
+        };
+
+        // the return in the continuation also returns from the parent
+        let (compiled, diagnostics) = compile(48);
+        let main_content = main(&compiled, "function");
+        let mut lines = Vec::new();
+        let mut content = main_content;
+        // every continuation is called with `return run`
+        while let Some((kept, call)) = content.split_once("\nreturn run function foo:") {
+            lines.extend(kept.lines().map(ToString::to_string));
+            content = compiled
+                .get_file(&format!("function/{call}.mcfunction"))
+                .and_then(VFile::as_text)
+                .map(ToString::to_string)
+                .expect("continuation function missing");
+        }
+        lines.extend(content.lines().map(ToString::to_string));
+        assert_eq!(lines, vec!["say 0", "say 1", "return 1", "say 2"]);
+        assert!(diagnostics.is_empty());
+
+        // `return run` is not available, so the function is not split
+        let (compiled, diagnostics) = compile(15);
+        assert_eq!(
+            main(&compiled, "functions"),
+            "say 0\nsay 1\nreturn 1\nsay 2"
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind(), DiagnosticKind::OversizedFunction);
+
+        // without returns after the split point, the continuation is called directly
+        let mut namespace_without_return = Namespace::new("foo");
+        let function = namespace_without_return.function_mut("main");
+        for i in 0..4 {
+            function.add_command(format!("say {i}").as_str());
+        }
+        let options = CompileOptions::default()
+            .with_pack_format(15)
+            .with_max_function_lines(Some(2));
+        let compiled = namespace_without_return.compile(&options, &MutCompilerState::default());
+        assert!(main(&compiled, "functions")
+            .lines()
+            .last()
+            .is_some_and(|line| line.starts_with("function foo:")));
+    }
 }
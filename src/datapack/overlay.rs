@@ -0,0 +1,134 @@
+//! Overlays of a datapack
+
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use crate::{
+    util::compile::{CompileError, CompileOptions, MutCompilerState},
+    virtual_fs::VFile,
+};
+
+use super::{compile_generated_functions, namespace::Namespace};
+
+/// Overlay of a datapack, containing namespaces that are only loaded for some pack formats.
+///
+/// The overlay is compiled into a folder named by its directory in the root of the datapack.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlay {
+    directory: String,
+    formats: RangeInclusive<u8>,
+    namespaces: HashMap<String, Namespace>,
+}
+
+impl Overlay {
+    /// Create a new, empty overlay.
+    pub(in crate::datapack) fn new(directory: &str, formats: RangeInclusive<u8>) -> Self {
+        Self {
+            directory: directory.to_string(),
+            formats,
+            namespaces: HashMap::new(),
+        }
+    }
+
+    /// Get the directory of the overlay.
+    #[must_use]
+    pub fn directory(&self) -> &str {
+        &self.directory
+    }
+
+    /// Get the pack formats the overlay is applied for.
+    #[must_use]
+    pub fn formats(&self) -> &RangeInclusive<u8> {
+        &self.formats
+    }
+
+    /// Set the pack formats the overlay is applied for.
+    pub fn set_formats(&mut self, formats: RangeInclusive<u8>) {
+        self.formats = formats;
+    }
+
+    /// Get the namespaces of the overlay.
+    #[must_use]
+    pub fn get_namespaces(&self) -> &HashMap<String, Namespace> {
+        &self.namespaces
+    }
+
+    /// Get a namespace by name.
+    #[must_use]
+    pub fn namespace(&self, name: &str) -> Option<&Namespace> {
+        self.namespaces.get(name)
+    }
+
+    /// Mutably get a namespace by name or create a new one if it doesn't exist.
+    pub fn namespace_mut(&mut self, name: &str) -> &mut Namespace {
+        self.namespaces
+            .entry(name.to_string())
+            .or_insert_with(|| Namespace::new(name))
+    }
+
+    /// Mutably get the namespaces of the overlay.
+    pub(in crate::datapack) fn namespaces_mut(&mut self) -> &mut HashMap<String, Namespace> {
+        &mut self.namespaces
+    }
+
+    /// Take the namespaces of the overlay.
+    pub(in crate::datapack) fn into_namespaces(self) -> HashMap<String, Namespace> {
+        self.namespaces
+    }
+
+    /// Get the pack format used for the directory names inside of the overlay.
+    ///
+    /// This is the pack format of the datapack if the overlay applies to it,
+    /// otherwise the closest format the overlay applies to.
+    pub(in crate::datapack) fn effective_pack_format(&self, pack_format: u8) -> u8 {
+        pack_format
+            .max(*self.formats.start())
+            .min(*self.formats.end())
+    }
+
+    /// Compile the overlay, passing each compiled file with its path relative to the overlay folder to the sink.
+    pub(in crate::datapack) fn try_compile_streaming<F>(
+        &self,
+        options: &CompileOptions,
+        state: &MutCompilerState,
+        mut sink: F,
+    ) -> Result<(), CompileError>
+    where
+        F: FnMut(&str, VFile),
+    {
+        let options = CompileOptions {
+            pack_format: self.effective_pack_format(options.pack_format),
+            ..options.clone()
+        };
+
+        let mut namespaces = self.namespaces.iter().collect::<Vec<_>>();
+        namespaces.sort_by_key(|(name, _)| *name);
+        for (name, namespace) in namespaces {
+            namespace.try_compile_streaming(&options, state, |path, file| {
+                sink(&format!("data/{name}/{path}"), file);
+            })?;
+        }
+        compile_generated_functions(&options, state, sink)
+    }
+
+    /// Check whether the overlay is valid with the pack formats it is applied for.
+    #[must_use]
+    pub fn validate(&self) -> bool {
+        self.namespaces
+            .values()
+            .all(|namespace| namespace.validate(&self.formats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_pack_format() {
+        let overlay = Overlay::new("old", 18..=41);
+        assert_eq!(overlay.effective_pack_format(48), 41);
+        assert_eq!(overlay.effective_pack_format(26), 26);
+        assert_eq!(overlay.effective_pack_format(10), 18);
+    }
+}
@@ -0,0 +1,524 @@
+//! Parsing of compiled datapacks.
+
+use crate::{
+    util::pack_format,
+    virtual_fs::{VFile, VFolder},
+};
+
+use super::{
+    function::parse_commands,
+    tag::{Tag, TagType},
+    text::TextComponent,
+    Datapack, Function,
+};
+
+/// Error that can occur while parsing a compiled datapack.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    /// The `pack.mcmeta` file is missing.
+    #[error("missing pack.mcmeta file")]
+    MissingMcmeta,
+    /// The `pack.mcmeta` file is invalid.
+    #[error("invalid pack.mcmeta file: {0}")]
+    InvalidMcmeta(String),
+    /// A file is not valid JSON.
+    #[error("invalid JSON in file {path}: {source}")]
+    InvalidJson {
+        /// Path of the file.
+        path: String,
+        /// Underlying JSON error.
+        source: serde_json::Error,
+    },
+    /// A tag file does not have the expected structure.
+    #[error("invalid tag file {0}")]
+    InvalidTag(String),
+    /// A text file is not valid UTF-8.
+    #[error("file {0} is not valid UTF-8")]
+    InvalidUtf8(String),
+}
+
+impl Datapack {
+    /// Read a datapack from a virtual folder, e.g. one loaded from the file system.
+    ///
+    /// See [`Datapack::from_compiled`] for details.
+    ///
+    /// # Errors
+    /// - If the `pack.mcmeta` file is missing or invalid
+    /// - If a function or tag file cannot be parsed
+    #[deprecated(note = "use `Datapack::from_compiled` instead")]
+    pub fn from_vfolder(folder: &VFolder) -> Result<Self, ParseError> {
+        Self::from_compiled(folder)
+    }
+
+    /// Parse a compiled datapack, so it can be edited and compiled again.
+    ///
+    /// Functions are read as raw commands and comments, tags are parsed from their JSON representation.
+    /// Overlays declared in the `pack.mcmeta` file are read the same way. All other files are kept as custom files.
+    ///
+    /// # Errors
+    /// - If the `pack.mcmeta` file is missing or invalid
+    /// - If a function or tag file cannot be parsed
+    pub fn from_compiled(folder: &VFolder) -> Result<Self, ParseError> {
+        let mcmeta = folder
+            .get_file("pack.mcmeta")
+            .ok_or(ParseError::MissingMcmeta)?;
+        let mcmeta = parse_json("pack.mcmeta", mcmeta)?;
+        let pack = mcmeta
+            .get("pack")
+            .ok_or_else(|| ParseError::InvalidMcmeta("missing pack section".to_string()))?;
+
+        let pack_format = pack
+            .get("pack_format")
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|format| u8::try_from(format).ok())
+            .ok_or_else(|| ParseError::InvalidMcmeta("invalid pack_format".to_string()))?;
+        let mut datapack = Self::new(pack_format);
+
+        if let Some(description) = pack.get("description") {
+            let invalid = || ParseError::InvalidMcmeta("invalid description".to_string());
+            datapack = match description {
+                serde_json::Value::String(description) => datapack.with_description(description),
+                serde_json::Value::Object(_) | serde_json::Value::Array(_) => datapack
+                    .with_description_component(
+                        TextComponent::from_json(description).ok_or_else(invalid)?,
+                    ),
+                _ => return Err(invalid()),
+            };
+        }
+        if let Some(supported_formats) = pack.get("supported_formats") {
+            let supported_formats =
+                parse_supported_formats(supported_formats).ok_or_else(|| {
+                    ParseError::InvalidMcmeta("invalid supported_formats".to_string())
+                })?;
+            datapack = datapack.with_supported_formats(supported_formats);
+        }
+
+        // keep unknown keys of the mcmeta file
+        let extension = parse_mcmeta_extension(&mcmeta, pack);
+        if !extension.is_empty() {
+            datapack = datapack.with_mcmeta_extension(extension.into());
+        }
+
+        if let Some(features) = mcmeta.get("features") {
+            let features = parse_features(features)
+                .ok_or_else(|| ParseError::InvalidMcmeta("invalid features".to_string()))?;
+            datapack = datapack.with_features(&features);
+        }
+        if let Some(filter) = mcmeta.get("filter") {
+            for (namespace, path) in parse_filter_blocks(filter)
+                .ok_or_else(|| ParseError::InvalidMcmeta("invalid filter".to_string()))?
+            {
+                datapack.add_filter_block(namespace, path);
+            }
+        }
+
+        if let Some(overlays) = mcmeta.get("overlays") {
+            for (directory, formats) in parse_overlays(overlays)
+                .ok_or_else(|| ParseError::InvalidMcmeta("invalid overlays".to_string()))?
+            {
+                let _ = datapack.add_overlay(directory, formats);
+            }
+        }
+
+        for (path, file) in folder.flatten() {
+            if path == "pack.mcmeta" {
+                continue;
+            }
+
+            // find the overlay containing the file, if any
+            let overlay_index = datapack.overlays.iter().position(|overlay| {
+                path.strip_prefix(overlay.directory())
+                    .is_some_and(|rest| rest.starts_with("/data/"))
+            });
+            let (data_path, file_pack_format) =
+                overlay_index.map_or((path.as_str(), pack_format), |index| {
+                    let overlay = &datapack.overlays[index];
+                    (
+                        &path[overlay.directory().len() + 1..],
+                        overlay.effective_pack_format(pack_format),
+                    )
+                });
+
+            let Some((namespace, rest)) = data_path
+                .strip_prefix("data/")
+                .and_then(|path| path.split_once('/'))
+            else {
+                datapack.add_custom_file(&path, file.clone());
+                continue;
+            };
+
+            let Some(data_file) = parse_data_file(&path, namespace, rest, file, file_pack_format)?
+            else {
+                datapack.add_custom_file(&path, file.clone());
+                continue;
+            };
+            let namespace = match overlay_index {
+                Some(index) => datapack.overlays[index].namespace_mut(namespace),
+                None => datapack.namespace_mut(namespace),
+            };
+            match data_file {
+                DataFile::Function(function) => {
+                    namespace.add_existing_function(function);
+                }
+                DataFile::Tag(name, tag_type, tag) => {
+                    *namespace.tag_mut(name, tag_type) = tag;
+                }
+            }
+        }
+
+        Ok(datapack)
+    }
+}
+
+/// Parsed file of a namespace folder.
+enum DataFile<'a> {
+    Function(Function),
+    Tag(&'a str, TagType, Tag),
+}
+
+/// Parse a file inside of the namespace folder, `rest` being the path relative to it.
+///
+/// Returns `None` if the file is neither a function nor a tag.
+fn parse_data_file<'a>(
+    path: &str,
+    namespace: &str,
+    rest: &'a str,
+    file: &VFile,
+    pack_format: u8,
+) -> Result<Option<DataFile<'a>>, ParseError> {
+    if let Some(name) = rest
+        .strip_prefix(pack_format::function_directory_name(pack_format))
+        .and_then(|rest| rest.strip_prefix('/'))
+        .and_then(|rest| rest.strip_suffix(".mcfunction"))
+    {
+        let mut function = Function::new(namespace, name);
+        function
+            .get_commands_mut()
+            .extend(parse_commands(file_text(path, file)?));
+        Ok(Some(DataFile::Function(function)))
+    } else if let Some(tag_path) = rest
+        .strip_prefix("tags/")
+        .and_then(|rest| rest.strip_suffix(".json"))
+    {
+        let (name, tag_type) = split_tag_path(tag_path, pack_format);
+        let tag = Tag::from_json(&parse_json(path, file)?)
+            .ok_or_else(|| ParseError::InvalidTag(path.to_string()))?;
+        Ok(Some(DataFile::Tag(name, tag_type, tag)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Collect the unknown keys of the `pack.mcmeta` file and its `pack` section.
+fn parse_mcmeta_extension(
+    mcmeta: &serde_json::Value,
+    pack: &serde_json::Value,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut extension = mcmeta
+        .as_object()
+        .map(|mcmeta| {
+            mcmeta
+                .iter()
+                .filter(|(key, _)| {
+                    !matches!(key.as_str(), "pack" | "overlays" | "features" | "filter")
+                })
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect::<serde_json::Map<_, _>>()
+        })
+        .unwrap_or_default();
+    let pack_extension = pack
+        .as_object()
+        .map(|pack| {
+            pack.iter()
+                .filter(|(key, _)| {
+                    !matches!(
+                        key.as_str(),
+                        "pack_format" | "description" | "supported_formats"
+                    )
+                })
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect::<serde_json::Map<_, _>>()
+        })
+        .unwrap_or_default();
+    if !pack_extension.is_empty() {
+        extension.insert("pack".to_string(), pack_extension.into());
+    }
+    extension
+}
+
+/// Get the text content of a file.
+fn file_text<'a>(path: &str, file: &'a VFile) -> Result<&'a str, ParseError> {
+    match file {
+        VFile::Text(text) => Ok(text),
+        VFile::Binary(data) => {
+            std::str::from_utf8(data).map_err(|_| ParseError::InvalidUtf8(path.to_string()))
+        }
+    }
+}
+
+/// Parse the JSON content of a file.
+fn parse_json(path: &str, file: &VFile) -> Result<serde_json::Value, ParseError> {
+    serde_json::from_str(file_text(path, file)?).map_err(|source| ParseError::InvalidJson {
+        path: path.to_string(),
+        source,
+    })
+}
+
+/// Parse the supported formats, which can be a single format, a list of two formats or an object.
+fn parse_supported_formats(value: &serde_json::Value) -> Option<std::ops::RangeInclusive<u8>> {
+    let to_u8 = |value: &serde_json::Value| value.as_u64().and_then(|v| u8::try_from(v).ok());
+    match value {
+        serde_json::Value::Number(_) => to_u8(value).map(|format| format..=format),
+        serde_json::Value::Array(formats) => match formats.as_slice() {
+            [min, max] => Some(to_u8(min)?..=to_u8(max)?),
+            _ => None,
+        },
+        serde_json::Value::Object(formats) => {
+            Some(to_u8(formats.get("min_inclusive")?)?..=to_u8(formats.get("max_inclusive")?)?)
+        }
+        _ => None,
+    }
+}
+
+/// Parse the enabled features of the `pack.mcmeta` file.
+fn parse_features(value: &serde_json::Value) -> Option<Vec<&str>> {
+    value
+        .get("enabled")?
+        .as_array()?
+        .iter()
+        .map(serde_json::Value::as_str)
+        .collect()
+}
+
+/// Parse the block patterns of the filter section of the `pack.mcmeta` file into their namespace and path.
+fn parse_filter_blocks(value: &serde_json::Value) -> Option<Vec<(Option<&str>, Option<&str>)>> {
+    value
+        .get("block")?
+        .as_array()?
+        .iter()
+        .map(|pattern| {
+            pattern.as_object()?;
+            let namespace = pattern.get("namespace").map(serde_json::Value::as_str);
+            let path = pattern.get("path").map(serde_json::Value::as_str);
+            match (namespace, path) {
+                (Some(None), _) | (_, Some(None)) => None,
+                (namespace, path) => Some((namespace.flatten(), path.flatten())),
+            }
+        })
+        .collect()
+}
+
+/// Parse the overlay entries of the `pack.mcmeta` file into their directories and formats.
+fn parse_overlays(value: &serde_json::Value) -> Option<Vec<(&str, std::ops::RangeInclusive<u8>)>> {
+    value
+        .get("entries")?
+        .as_array()?
+        .iter()
+        .map(|entry| {
+            Some((
+                entry.get("directory")?.as_str()?,
+                parse_supported_formats(entry.get("formats")?)?,
+            ))
+        })
+        .collect()
+}
+
+/// Split the path of a tag inside the `tags` folder into its name and type.
+fn split_tag_path(path: &str, pack_format: u8) -> (&str, TagType) {
+    [
+        TagType::Block,
+        TagType::Fluid,
+        TagType::Item,
+        TagType::Entity,
+        TagType::GameEvent,
+        TagType::Function,
+        TagType::DamageType,
+        TagType::Biome,
+        TagType::BannerPattern,
+        TagType::CatVariant,
+        TagType::PaintingVariant,
+        TagType::Instrument,
+    ]
+    .into_iter()
+    .find_map(|tag_type| {
+        path.strip_prefix(tag_type.get_directory_name(pack_format))
+            .and_then(|rest| rest.strip_prefix('/'))
+            .map(|name| (name, tag_type.clone()))
+    })
+    .unwrap_or_else(|| {
+        path.split_once('/')
+            .map_or((path, TagType::Other(String::new())), |(registry, name)| {
+                (name, TagType::Other(registry.to_string()))
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        datapack::{tag::TagValue, Command},
+        util::compile::CompileOptions,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_from_compiled() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT)
+            .with_description("foo")
+            .with_supported_formats(40..=48)
+            .with_features(&["minecraft:update_1_21"])
+            .with_mcmeta_extension(serde_json::json!({
+                "launcher": { "id": "foo" },
+                "pack": { "custom": true }
+            }));
+        let function = dp.namespace_mut("foo").function_mut("bar/baz");
+        function.add_command(Command::Comment("comment".to_string()));
+        function.add_command("say hi");
+        function.add_command(Command::MacroLine("say $(name)".to_string()));
+        dp.add_load("foo:bar/baz");
+        dp.add_filter_block(Some("minecraft"), Some("recipes?/.*"));
+        dp.namespace_mut("foo")
+            .tag_mut("stones", TagType::Block)
+            .add_value(TagValue::from("minecraft:stone"));
+        dp.add_custom_file("data/foo/loot_table/bar.json", VFile::from("{}"));
+        dp.add_custom_file("pack.png", VFile::Binary(vec![1, 2, 3]));
+        let overlay = dp.add_overlay("old", 18..=41);
+        overlay
+            .namespace_mut("foo")
+            .function_mut("bar/baz")
+            .add_command("say old");
+        overlay
+            .namespace_mut("foo")
+            .tag_mut("stones", TagType::Block)
+            .add_value(TagValue::from("minecraft:cobblestone"));
+        dp.add_custom_file("old/pack.png", VFile::Binary(vec![4, 5, 6]));
+
+        let compiled = dp.compile(&CompileOptions::default());
+        let parsed = Datapack::from_compiled(&compiled).expect("failed to parse datapack");
+
+        assert_eq!(parsed, dp);
+        assert_eq!(parsed.compile(&CompileOptions::default()), compiled);
+    }
+
+    #[test]
+    fn test_from_compiled_description_component() {
+        let dp = Datapack::new(Datapack::LATEST_FORMAT).with_description_component(
+            TextComponent::new("foo")
+                .with_color("gold")
+                .with_extra(TextComponent::new(" bar").with_bold(true)),
+        );
+        let compiled = dp.compile(&CompileOptions::default());
+        let parsed = Datapack::from_compiled(&compiled).expect("failed to parse datapack");
+        assert_eq!(parsed, dp);
+        assert_eq!(parsed.compile(&CompileOptions::default()), compiled);
+
+        let mut folder = VFolder::new();
+        folder.add_file(
+            "pack.mcmeta",
+            VFile::from(
+                r#"{"pack":{"pack_format":48,"description":["foo",{"text":"bar","color":"red"}]}}"#,
+            ),
+        );
+        let parsed = Datapack::from_compiled(&folder).expect("failed to parse datapack");
+        assert_eq!(
+            parsed.description_component,
+            Some(TextComponent::new("foo").with_extra(TextComponent::new("bar").with_color("red")))
+        );
+
+        folder.add_file(
+            "pack.mcmeta",
+            VFile::from(r#"{"pack":{"pack_format":48,"description":1}}"#),
+        );
+        assert!(matches!(
+            Datapack::from_compiled(&folder),
+            Err(ParseError::InvalidMcmeta(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_compiled_old_format() {
+        let mut folder = VFolder::new();
+        folder.add_file(
+            "pack.mcmeta",
+            VFile::from(r#"{"pack":{"pack_format":26,"description":"old"}}"#),
+        );
+        folder.add_file("data/foo/functions/bar.mcfunction", VFile::from("say bar"));
+        folder.add_file(
+            "data/foo/tags/blocks/stones.json",
+            VFile::from(r#"{"values":["minecraft:stone"]}"#),
+        );
+        folder.add_file(
+            "data/minecraft/tags/functions/load.json",
+            VFile::from(r#"{"values":["foo:bar"]}"#),
+        );
+        folder.add_file(
+            "data/foo/tags/worldgen/biome/warm.json",
+            VFile::from(r#"{"values":["minecraft:desert"]}"#),
+        );
+
+        let dp = Datapack::from_compiled(&folder).expect("failed to parse datapack");
+        assert_eq!(dp.pack_format, 26);
+        assert_eq!(dp.description, "old");
+        assert_eq!(
+            dp.namespace("foo")
+                .and_then(|ns| ns.function("bar"))
+                .map(Function::get_commands),
+            Some(&vec![Command::raw("say bar")])
+        );
+        assert!(dp
+            .namespace("foo")
+            .and_then(|ns| ns.tag("stones", TagType::Block))
+            .is_some());
+        assert!(dp
+            .namespace("foo")
+            .and_then(|ns| ns.tag("warm", TagType::Biome))
+            .is_some());
+        assert!(dp
+            .namespace("minecraft")
+            .and_then(|ns| ns.tag("load", TagType::Function))
+            .is_some());
+        assert!(dp.custom_files.is_empty());
+
+        #[allow(deprecated)]
+        let from_vfolder = Datapack::from_vfolder(&folder).expect("failed to parse datapack");
+        assert_eq!(from_vfolder, dp);
+    }
+
+    #[test]
+    fn test_from_compiled_errors() {
+        assert!(matches!(
+            Datapack::from_compiled(&VFolder::new()),
+            Err(ParseError::MissingMcmeta)
+        ));
+
+        let mut folder = VFolder::new();
+        folder.add_file("pack.mcmeta", VFile::from(r#"{"pack":{"pack_format":48}}"#));
+        folder.add_file(
+            "data/foo/tags/block/bar.json",
+            VFile::from(r#"{"values":1}"#),
+        );
+        assert!(matches!(
+            Datapack::from_compiled(&folder),
+            Err(ParseError::InvalidTag(path)) if path == "data/foo/tags/block/bar.json"
+        ));
+    }
+
+    #[test]
+    fn test_parse_supported_formats() {
+        assert_eq!(
+            parse_supported_formats(&serde_json::json!(12)),
+            Some(12..=12)
+        );
+        assert_eq!(
+            parse_supported_formats(&serde_json::json!([12, 15])),
+            Some(12..=15)
+        );
+        assert_eq!(
+            parse_supported_formats(&serde_json::json!({"min_inclusive": 12, "max_inclusive": 15})),
+            Some(12..=15)
+        );
+        assert_eq!(parse_supported_formats(&serde_json::json!("12")), None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Predicates that can be checked with the `predicate` condition.
+
+use serde_json::{json, Value};
+
+use crate::virtual_fs::VFile;
+
+/// Predicate consisting of conditions that all have to pass.
+///
+/// Check it with [`Condition::predicate`](super::Condition::predicate) and the id of the predicate.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Predicate {
+    conditions: Vec<Value>,
+}
+
+impl Predicate {
+    /// Create a new predicate without conditions, which always passes.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the conditions of the predicate.
+    #[must_use]
+    pub fn get_conditions(&self) -> &Vec<Value> {
+        &self.conditions
+    }
+
+    /// Add a condition given as JSON object, e.g. `{"condition": "minecraft:weather_check", "raining": true}`.
+    pub fn add_condition(&mut self, condition: Value) {
+        self.conditions.push(condition);
+    }
+
+    /// Add a condition that passes with the probability, from `0.0` to `1.0`.
+    pub fn add_random_chance(&mut self, chance: f32) {
+        self.add_condition(json!({
+            "condition": "minecraft:random_chance",
+            "chance": chance
+        }));
+    }
+
+    /// Add a condition that checks the properties of the entity, e.g. `this`, `attacker` or `killer`,
+    /// against the entity predicate.
+    pub fn add_entity_properties(&mut self, entity: &str, predicate: Value) {
+        let mut condition = json!({
+            "condition": "minecraft:entity_properties",
+            "entity": entity
+        });
+        condition["predicate"] = predicate;
+        self.add_condition(condition);
+    }
+
+    /// Add a condition that checks the current location against the location predicate.
+    pub fn add_location_check(&mut self, predicate: Value) {
+        let mut condition = json!({ "condition": "minecraft:location_check" });
+        condition["predicate"] = predicate;
+        self.add_condition(condition);
+    }
+
+    /// Compile the predicate into a virtual file.
+    ///
+    /// A single condition is written as a bare object instead of an array.
+    #[must_use]
+    pub fn compile(&self) -> VFile {
+        let json = if let [condition] = self.conditions.as_slice() {
+            condition.clone()
+        } else {
+            Value::Array(self.conditions.clone())
+        };
+        VFile::Text(json.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        let mut predicate = Predicate::new();
+        assert_eq!(predicate.compile().as_text(), Some("[]"));
+
+        predicate.add_random_chance(0.5);
+        assert_eq!(
+            predicate.compile().as_text(),
+            Some(r#"{"chance":0.5,"condition":"minecraft:random_chance"}"#)
+        );
+
+        predicate.add_entity_properties("this", json!({ "flags": { "is_sneaking": true } }));
+        predicate.add_location_check(json!({ "biomes": "minecraft:plains" }));
+        let compiled = predicate
+            .compile()
+            .as_text()
+            .map(|text| serde_json::from_str::<Value>(text).unwrap())
+            .unwrap();
+        assert_eq!(compiled.as_array().map(Vec::len), Some(3));
+        assert_eq!(compiled[1]["condition"], "minecraft:entity_properties");
+        assert_eq!(compiled[1]["entity"], "this");
+        assert_eq!(compiled[2]["predicate"]["biomes"], "minecraft:plains");
+    }
+}
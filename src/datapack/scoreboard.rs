@@ -0,0 +1,227 @@
+//! Scoreboard objectives and constants of a datapack.
+
+use std::borrow::Cow;
+
+use crate::util::compile::{
+    try_report, CompileError, CompileOptions, DiagnosticKind, MutCompilerState,
+};
+
+use super::{tag, Command, Datapack, Function, Namespace};
+
+/// Name of the function in the internal namespace that sets up objectives and constants.
+const LOAD_FUNCTION_NAME: &str = "load";
+/// Objective holding the constants of the datapack.
+pub const CONSTANT_OBJECTIVE: &str = "sb_const";
+
+/// Scoreboard objective.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Objective {
+    /// Name of the objective.
+    pub name: String,
+    /// Criteria of the objective, e.g. `dummy` or `minecraft.custom:minecraft.jump`.
+    pub criteria: String,
+}
+
+impl Objective {
+    /// Create a new objective.
+    #[must_use]
+    pub fn new(name: &str, criteria: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            criteria: criteria.to_string(),
+        }
+    }
+
+    /// Create a new objective with the `dummy` criteria, which is only changed by commands.
+    #[must_use]
+    pub fn dummy(name: &str) -> Self {
+        Self::new(name, "dummy")
+    }
+
+    /// Get the command creating the objective.
+    #[must_use]
+    pub fn create_command(&self) -> Command {
+        Command::Raw(format!(
+            "scoreboard objectives add {} {}",
+            self.name, self.criteria
+        ))
+    }
+}
+
+impl Datapack {
+    /// Register an objective, which is created in the load function of the datapack.
+    ///
+    /// The load function `load` is generated in the namespace set by [`CompileOptions::with_internal_namespace`]
+    /// when compiling and runs first in the `minecraft:load` tag.
+    /// Objectives with an already registered name are ignored.
+    pub fn register_objective(&mut self, objective: Objective) {
+        if self
+            .objectives
+            .iter()
+            .any(|registered| registered.name == objective.name)
+        {
+            return;
+        }
+
+        self.objectives.push(objective);
+    }
+
+    /// Get the registered objectives in the order they were registered.
+    #[must_use]
+    pub fn objectives(&self) -> &[Objective] {
+        &self.objectives
+    }
+
+    /// Get the name of the fake player holding the constant value in the objective [`CONSTANT_OBJECTIVE`].
+    ///
+    /// The constant is set in the load function of the datapack, see [`Datapack::register_objective`].
+    pub fn use_constant(&mut self, value: i32) -> String {
+        if self.constants.insert(value) {
+            self.register_objective(Objective::dummy(CONSTANT_OBJECTIVE));
+        }
+        constant_name(value)
+    }
+
+    /// Get the used constants in ascending order.
+    pub fn constants(&self) -> impl Iterator<Item = i32> + '_ {
+        self.constants.iter().copied()
+    }
+
+    /// Get the namespaces to compile sorted by name, with the load function setting up the objectives
+    /// and constants added to the internal namespace and the `minecraft:load` tag.
+    ///
+    /// Only the namespaces that are changed are cloned.
+    ///
+    /// # Errors
+    /// - If the compiler state was poisoned
+    pub(super) fn namespaces_with_load_function(
+        &self,
+        options: &CompileOptions,
+        state: &MutCompilerState,
+    ) -> Result<Vec<(String, Cow<'_, Namespace>)>, CompileError> {
+        let mut namespaces = self
+            .sorted_namespaces()
+            .into_iter()
+            .map(|(name, namespace)| (name.clone(), Cow::Borrowed(namespace)))
+            .collect::<Vec<_>>();
+        if self.objectives.is_empty() {
+            return Ok(namespaces);
+        }
+
+        let internal_namespace = options.internal_namespace.as_str();
+        let load_namespace = namespace_mut(&mut namespaces, internal_namespace);
+        if load_namespace.function(LOAD_FUNCTION_NAME).is_some() {
+            try_report(
+                state,
+                DiagnosticKind::Internal,
+                format!(
+                    "Function '{internal_namespace}:{LOAD_FUNCTION_NAME}' is replaced by the function setting up objectives and constants"
+                ),
+            )?;
+        }
+        let mut load_function = Function::new(internal_namespace, LOAD_FUNCTION_NAME);
+        for objective in &self.objectives {
+            load_function.add_command(objective.create_command());
+        }
+        for value in &self.constants {
+            load_function.add_command(Command::Raw(format!(
+                "scoreboard players set {name} {CONSTANT_OBJECTIVE} {value}",
+                name = constant_name(*value)
+            )));
+        }
+        load_namespace.add_existing_function(load_function);
+
+        namespace_mut(&mut namespaces, "minecraft")
+            .tag_mut("load", tag::TagType::Function)
+            .insert_value(
+                0,
+                tag::TagValue::Simple(format!("{internal_namespace}:{LOAD_FUNCTION_NAME}")),
+            );
+
+        Ok(namespaces)
+    }
+}
+
+/// Get the namespace with the name from the sorted namespaces, cloning it or creating it if needed.
+fn namespace_mut<'n>(
+    namespaces: &'n mut Vec<(String, Cow<'_, Namespace>)>,
+    name: &str,
+) -> &'n mut Namespace {
+    let index = namespaces
+        .binary_search_by(|(existing, _)| existing.as_str().cmp(name))
+        .unwrap_or_else(|index| {
+            namespaces.insert(index, (name.to_string(), Cow::Owned(Namespace::new(name))));
+            index
+        });
+    namespaces[index].1.to_mut()
+}
+
+/// Get the name of the fake player holding the constant.
+fn constant_name(value: i32) -> String {
+    format!("#const_{value}")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{datapack::tag::TagType, util::compile::CompileOptions, virtual_fs::VFile};
+
+    use super::*;
+
+    #[test]
+    fn test_objectives_and_constants() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        dp.register_objective(Objective::dummy("foo"));
+        dp.register_objective(Objective::new("jumps", "minecraft.custom:minecraft.jump"));
+        dp.register_objective(Objective::dummy("foo"));
+
+        assert_eq!(dp.use_constant(5), "#const_5");
+        assert_eq!(dp.use_constant(-1), "#const_-1");
+        assert_eq!(dp.use_constant(5), "#const_5");
+        assert_eq!(dp.objectives().len(), 3);
+
+        assert_eq!(dp.constants().collect::<Vec<_>>(), vec![-1, 5]);
+        dp.add_load("foo:init");
+
+        let compiled = dp.compile(&CompileOptions::default());
+        assert_eq!(
+            compiled
+                .get_file("data/shulkerbox/function/load.mcfunction")
+                .and_then(VFile::as_text),
+            Some(
+                "scoreboard objectives add foo dummy\n\
+                scoreboard objectives add jumps minecraft.custom:minecraft.jump\n\
+                scoreboard objectives add sb_const dummy\n\
+                scoreboard players set #const_-1 sb_const -1\n\
+                scoreboard players set #const_5 sb_const 5"
+            )
+        );
+        assert_eq!(
+            compiled
+                .get_file("data/minecraft/tags/function/load.json")
+                .and_then(VFile::as_text),
+            Some(r#"{"replace":false,"values":["shulkerbox:load","foo:init"]}"#)
+        );
+        // the load function is only added when compiling
+        assert!(dp.namespace("shulkerbox").is_none());
+        assert_eq!(
+            dp.namespace("minecraft")
+                .and_then(|namespace| namespace.tag("load", TagType::Function))
+                .map(|tag| tag.get_values().len()),
+            Some(1)
+        );
+
+        let options = CompileOptions::default().with_internal_namespace("internal");
+        let compiled = dp.compile(&options);
+        assert!(compiled
+            .get_file("data/internal/function/load.mcfunction")
+            .is_some());
+        assert!(compiled.get_folder("data/shulkerbox").is_none());
+        assert_eq!(
+            compiled
+                .get_file("data/minecraft/tags/function/load.json")
+                .and_then(VFile::as_text),
+            Some(r#"{"replace":false,"values":["internal:load","foo:init"]}"#)
+        );
+    }
+}
@@ -1,9 +1,12 @@
 //! A tag for various types.
 
-use std::fmt::Display;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::Display,
+};
 
 use crate::{
-    util::compile::{CompileOptions, MutCompilerState},
+    util::compile::{try_report, CompileError, CompileOptions, DiagnosticKind, MutCompilerState},
     virtual_fs::VFile,
 };
 
@@ -13,6 +16,8 @@ use crate::{
 pub struct Tag {
     replace: bool,
     values: Vec<TagValue>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    remove: Vec<TagValue>,
 }
 impl Tag {
     /// Create a new tag.
@@ -21,6 +26,7 @@ impl Tag {
         Self {
             replace,
             values: Vec::new(),
+            remove: Vec::new(),
         }
     }
 
@@ -46,19 +52,203 @@ impl Tag {
         self.values.push(value);
     }
 
+    /// Insert a value at the index of the values of the tag, or at the end if the index is out of bounds.
+    pub fn insert_value(&mut self, index: usize, value: TagValue) {
+        self.values.insert(index.min(self.values.len()), value);
+    }
+
+    /// Remove all values with the id, regardless of whether they are required.
+    ///
+    /// Returns whether a value was removed.
+    pub fn remove_value(&mut self, id: &str) -> bool {
+        let len = self.values.len();
+        self.values.retain(|value| value.id() != id);
+        self.values.len() != len
+    }
+
+    /// Check whether the tag contains a value with the id.
+    #[must_use]
+    pub fn contains(&self, id: &str) -> bool {
+        self.values.iter().any(|value| value.id() == id)
+    }
+
+    /// Get the values that are removed from the tag.
+    #[must_use]
+    pub fn get_remove_values(&self) -> &Vec<TagValue> {
+        &self.remove
+    }
+
+    /// Add a value that is removed from the tag, even if it was added by another datapack.
+    ///
+    /// Requires pack format 40 or higher, otherwise the values are omitted when compiling.
+    pub fn add_remove_value(&mut self, value: TagValue) {
+        self.remove.push(value);
+    }
+
+    /// Remove values and removed values whose id occurred before, keeping the first occurrence.
+    ///
+    /// A simple value and a required advanced value with the same id are equivalent,
+    /// so the first occurrence is kept and made required if any of the duplicates is required.
+    pub fn dedup(&mut self) {
+        fn dedup_values(values: &mut Vec<TagValue>) {
+            let mut first_indices = HashMap::<String, usize>::new();
+            let mut deduped: Vec<TagValue> = Vec::with_capacity(values.len());
+            for value in values.drain(..) {
+                match first_indices.entry(value.id().to_string()) {
+                    Entry::Occupied(entry) => {
+                        if value.is_required() {
+                            deduped[*entry.get()].set_required();
+                        }
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(deduped.len());
+                        deduped.push(value);
+                    }
+                }
+            }
+            *values = deduped;
+        }
+
+        dedup_values(&mut self.values);
+        dedup_values(&mut self.remove);
+    }
+
+    /// Merge another tag into this tag.
+    ///
+    /// If the other tag replaces existing values, its values replace the values of this tag,
+    /// otherwise the values are appended, skipping duplicates. Removed values are always combined.
+    pub fn merge(&mut self, other: Self) {
+        if other.replace {
+            self.replace = true;
+            self.values = other.values;
+        } else {
+            for value in other.values {
+                if !self.values.contains(&value) {
+                    self.values.push(value);
+                }
+            }
+        }
+        for value in other.remove {
+            if !self.remove.contains(&value) {
+                self.remove.push(value);
+            }
+        }
+    }
+
+    /// Parse a tag from its JSON representation.
+    ///
+    /// Returns `None` if the JSON is not a valid tag.
+    #[must_use]
+    pub fn from_json(json: &serde_json::Value) -> Option<Self> {
+        let object = json.as_object()?;
+        let replace = match object.get("replace") {
+            Some(replace) => replace.as_bool()?,
+            None => false,
+        };
+        let values = object
+            .get("values")?
+            .as_array()?
+            .iter()
+            .map(TagValue::from_json)
+            .collect::<Option<Vec<_>>>()?;
+        let remove = match object.get("remove") {
+            Some(remove) => remove
+                .as_array()?
+                .iter()
+                .map(TagValue::from_json)
+                .collect::<Option<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+
+        Some(Self {
+            replace,
+            values,
+            remove,
+        })
+    }
+
     /// Compile the tag into a virtual file without state
-    pub fn compile_no_state(&self, _options: &CompileOptions) -> VFile {
-        let json = serde_json::json!({
+    ///
+    /// Panics if the tag cannot be serialized, use [`Tag::try_compile_no_state`] to handle the error instead.
+    #[must_use]
+    pub fn compile_no_state(&self, options: &CompileOptions) -> VFile {
+        self.try_compile_no_state(options)
+            .expect("Failed to serialize tag")
+    }
+
+    /// Compile the tag into a virtual file without state
+    ///
+    /// # Errors
+    /// - If the tag cannot be serialized
+    pub fn try_compile_no_state(&self, options: &CompileOptions) -> Result<VFile, CompileError> {
+        if let Some(message) = self.omitted_remove_values(options) {
+            tracing::warn!("{message}");
+        }
+        self.serialize(options)
+    }
+
+    /// Compile the tag into a virtual file.
+    ///
+    /// Panics if the tag cannot be serialized, use [`Tag::try_compile`] to handle the error instead.
+    pub fn compile(&self, options: &CompileOptions, state: &MutCompilerState) -> VFile {
+        self.try_compile(options, state)
+            .expect("Failed to compile tag")
+    }
+
+    /// Compile the tag into a virtual file.
+    ///
+    /// # Errors
+    /// - If the tag cannot be serialized
+    /// - If the compiler state was poisoned
+    pub fn try_compile(
+        &self,
+        options: &CompileOptions,
+        state: &MutCompilerState,
+    ) -> Result<VFile, CompileError> {
+        if let Some(message) = self.omitted_remove_values(options) {
+            try_report(state, DiagnosticKind::PackFormat, message)?;
+        }
+        self.serialize(options)
+    }
+
+    /// Get the warning about omitted remove values if the pack format does not support them.
+    fn omitted_remove_values(&self, options: &CompileOptions) -> Option<String> {
+        (!self.remove.is_empty() && options.pack_format < 40).then(|| {
+            format!(
+                "Omitting remove values of tag, they require pack format 40 or higher but the pack format is {}",
+                options.pack_format
+            )
+        })
+    }
+
+    /// Serialize the tag, omitting the remove values if the pack format does not support them.
+    fn serialize(&self, options: &CompileOptions) -> Result<VFile, CompileError> {
+        if options.sorted_tags {
+            let mut sorted = self.clone();
+            sorted.dedup();
+            sorted.values.sort_by(|a, b| a.id().cmp(b.id()));
+            sorted.remove.sort_by(|a, b| a.id().cmp(b.id()));
+            return sorted.serialize(&CompileOptions {
+                sorted_tags: false,
+                ..options.clone()
+            });
+        }
+
+        let mut json = serde_json::json!({
             "replace": self.replace,
             "values": self.values.iter().map(TagValue::compile).collect::<Vec<_>>()
         });
 
-        VFile::Text(serde_json::to_string(&json).expect("Failed to serialize tag"))
-    }
+        if !self.remove.is_empty() && options.pack_format >= 40 {
+            json["remove"] = self
+                .remove
+                .iter()
+                .map(TagValue::compile)
+                .collect::<Vec<_>>()
+                .into();
+        }
 
-    /// Compile the tag into a virtual file.
-    pub fn compile(&self, options: &CompileOptions, _state: &MutCompilerState) -> VFile {
-        self.compile_no_state(options)
+        Ok(VFile::Text(serde_json::to_string(&json)?))
     }
 }
 
@@ -79,40 +269,51 @@ pub enum TagType {
     GameEvent,
     /// A tag for functions.
     Function,
+    /// A tag for damage types.
+    DamageType,
+    /// A tag for biomes.
+    Biome,
+    /// A tag for banner patterns.
+    BannerPattern,
+    /// A tag for cat variants.
+    CatVariant,
+    /// A tag for painting variants.
+    PaintingVariant,
+    /// A tag for instruments.
+    Instrument,
     /// A custom tag type.
     /// `Other(<registry path>)` => `data/<namespace>/tags/<registry path>`
     Other(String),
 }
 
 impl TagType {
+    /// Get the name of the directory inside of `tags` containing tags of this type for the pack format.
+    ///
+    /// Only the registries that existed before 1.21 were renamed from plural to singular,
+    /// the directories of the data driven registries have always been singular.
     #[must_use]
     pub fn get_directory_name(&self, pack_format: u8) -> &str {
-        if pack_format < 43 {
-            match self {
-                Self::Block => "blocks",
-                Self::Fluid => "fluids",
-                Self::Item => "items",
-                Self::Entity => "entity_types",
-                Self::GameEvent => "game_events",
-                Self::Function => "functions",
-                Self::Other(path) => path,
-            }
-        } else {
-            match self {
-                Self::Block => "block",
-                Self::Fluid => "fluid",
-                Self::Item => "item",
-                Self::Entity => "entity_type",
-                Self::GameEvent => "game_event",
-                Self::Function => {
-                    if pack_format < 45 {
-                        "functions"
-                    } else {
-                        "function"
-                    }
-                }
-                Self::Other(path) => path,
-            }
+        let plural = pack_format < 43;
+        match self {
+            Self::Block if plural => "blocks",
+            Self::Block => "block",
+            Self::Fluid if plural => "fluids",
+            Self::Fluid => "fluid",
+            Self::Item if plural => "items",
+            Self::Item => "item",
+            Self::Entity if plural => "entity_types",
+            Self::Entity => "entity_type",
+            Self::GameEvent if plural => "game_events",
+            Self::GameEvent => "game_event",
+            Self::Function if pack_format < 45 => "functions",
+            Self::Function => "function",
+            Self::DamageType => "damage_type",
+            Self::Biome => "worldgen/biome",
+            Self::BannerPattern => "banner_pattern",
+            Self::CatVariant => "cat_variant",
+            Self::PaintingVariant => "painting_variant",
+            Self::Instrument => "instrument",
+            Self::Other(path) => path,
         }
     }
 }
@@ -126,7 +327,13 @@ impl Display for TagType {
             Self::Entity => "entity_type".to_string(),
             Self::GameEvent => "game_event".to_string(),
             Self::Function => "function".to_string(),
-            Self::Other(path) => path.to_string(),
+            Self::DamageType => "damage_type".to_string(),
+            Self::Biome => "worldgen/biome".to_string(),
+            Self::BannerPattern => "banner_pattern".to_string(),
+            Self::CatVariant => "cat_variant".to_string(),
+            Self::PaintingVariant => "painting_variant".to_string(),
+            Self::Instrument => "instrument".to_string(),
+            Self::Other(path) => path.clone(),
         };
         f.write_str(&str)
     }
@@ -152,7 +359,70 @@ impl From<&str> for TagValue {
         Self::Simple(value.to_string())
     }
 }
+impl From<(&str, bool)> for TagValue {
+    fn from((id, required): (&str, bool)) -> Self {
+        Self::Advanced {
+            id: id.to_string(),
+            required,
+        }
+    }
+}
 impl TagValue {
+    /// Create an advanced value that makes the loading of the tag fail when the entry is not found.
+    #[must_use]
+    pub fn required(id: &str) -> Self {
+        Self::from((id, true))
+    }
+
+    /// Create an advanced value that is skipped when the entry is not found.
+    #[must_use]
+    pub fn optional(id: &str) -> Self {
+        Self::from((id, false))
+    }
+
+    /// Get the id of the tag value.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Simple(id) | Self::Advanced { id, .. } => id,
+        }
+    }
+
+    /// Check whether the loading of the tag fails when the entry is not found.
+    #[must_use]
+    pub fn is_required(&self) -> bool {
+        match self {
+            Self::Simple(_) => true,
+            Self::Advanced { required, .. } => *required,
+        }
+    }
+
+    /// Make the loading of the tag fail when the entry is not found.
+    fn set_required(&mut self) {
+        if let Self::Advanced { required, .. } = self {
+            *required = true;
+        }
+    }
+
+    /// Parse a tag value from its JSON representation.
+    ///
+    /// Returns `None` if the JSON is not a valid tag value.
+    #[must_use]
+    pub fn from_json(json: &serde_json::Value) -> Option<Self> {
+        match json {
+            serde_json::Value::String(value) => Some(Self::Simple(value.clone())),
+            serde_json::Value::Object(object) => {
+                let id = object.get("id")?.as_str()?.to_string();
+                let required = match object.get("required") {
+                    Some(required) => required.as_bool()?,
+                    None => true,
+                };
+                Some(Self::Advanced { id, required })
+            }
+            _ => None,
+        }
+    }
+
     /// Compile the tag value into a JSON value.
     #[must_use]
     pub fn compile(&self) -> serde_json::Value {
@@ -208,4 +478,170 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_remove_values() {
+        let mut tag = Tag::new(false);
+        tag.add_value(TagValue::from("foo:bar"));
+        tag.add_remove_value(TagValue::from("minecraft:stone"));
+        assert_eq!(tag.get_remove_values().len(), 1);
+
+        let compile = |pack_format| {
+            let options = CompileOptions {
+                pack_format,
+                ..CompileOptions::default()
+            };
+            let compiled = tag.compile(&options, &MutCompilerState::default());
+            serde_json::from_str::<serde_json::Value>(compiled.as_text().unwrap())
+                .expect("Failed to deserialize tag")
+        };
+
+        let json = compile(40);
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "replace": false,
+                "values": ["foo:bar"],
+                "remove": ["minecraft:stone"]
+            })
+        );
+        assert_eq!(Tag::from_json(&json), Some(tag.clone()));
+
+        assert!(compile(39).get("remove").is_none());
+
+        let state = MutCompilerState::default();
+        let options = CompileOptions::default().with_pack_format(39);
+        assert!(tag.try_compile(&options, &state).is_ok());
+        let diagnostics = state.lock().unwrap().take_diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind(), DiagnosticKind::PackFormat);
+    }
+
+    #[test]
+    fn test_tag_value_constructors() {
+        assert_eq!(
+            TagValue::required("foo:bar"),
+            TagValue::Advanced {
+                id: "foo:bar".to_string(),
+                required: true
+            }
+        );
+        assert_eq!(
+            TagValue::optional("foo:bar"),
+            TagValue::Advanced {
+                id: "foo:bar".to_string(),
+                required: false
+            }
+        );
+        assert_eq!(
+            TagValue::from(("foo:bar", false)),
+            TagValue::optional("foo:bar")
+        );
+    }
+
+    #[test]
+    fn test_remove_value() {
+        let mut tag = Tag::new(false);
+        tag.add_value(TagValue::from("foo:a"));
+        tag.add_value(TagValue::optional("foo:b"));
+        tag.add_value(TagValue::required("foo:a"));
+        assert!(tag.contains("foo:a"));
+        assert!(tag.contains("foo:b"));
+        assert!(!tag.contains("foo:c"));
+
+        assert!(tag.remove_value("foo:a"));
+        assert!(!tag.contains("foo:a"));
+        assert!(tag.remove_value("foo:b"));
+        assert!(!tag.remove_value("foo:b"));
+        assert!(tag.get_values().is_empty());
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut tag = Tag::new(false);
+        tag.add_value(TagValue::optional("foo:a"));
+        tag.add_value(TagValue::from("foo:b"));
+        tag.add_value(TagValue::required("foo:a"));
+        tag.add_value(TagValue::required("foo:b"));
+        tag.add_remove_value(TagValue::from("foo:c"));
+        tag.add_remove_value(TagValue::from("foo:c"));
+        tag.dedup();
+
+        // the optional entry is kept at its position, but required by the later duplicate
+        assert_eq!(
+            tag.get_values(),
+            &vec![TagValue::required("foo:a"), TagValue::from("foo:b")]
+        );
+        assert_eq!(tag.get_remove_values(), &vec![TagValue::from("foo:c")]);
+
+        let mut tag = Tag::new(false);
+        tag.add_value(TagValue::optional("foo:a"));
+        tag.add_value(TagValue::optional("foo:a"));
+        tag.dedup();
+        assert_eq!(tag.get_values(), &vec![TagValue::optional("foo:a")]);
+    }
+
+    #[test]
+    fn test_sorted_tags() {
+        let mut tag = Tag::new(false);
+        tag.add_value(TagValue::from("foo:c"));
+        tag.add_value(TagValue::from("foo:a"));
+        tag.add_value(TagValue::optional("foo:b"));
+        tag.add_value(TagValue::from("foo:a"));
+
+        let options = CompileOptions::default().with_sorted_tags(true);
+        let compiled = tag.compile_no_state(&options);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(compiled.as_text().unwrap()).unwrap(),
+            serde_json::json!({
+                "replace": false,
+                "values": ["foo:a", { "id": "foo:b", "required": false }, "foo:c"]
+            })
+        );
+        assert_eq!(tag.compile_no_state(&options), compiled);
+        assert_eq!(tag.get_values().len(), 4);
+    }
+
+    #[test]
+    fn test_directory_names() {
+        assert_eq!(TagType::Block.get_directory_name(41), "blocks");
+        assert_eq!(TagType::Block.get_directory_name(48), "block");
+        assert_eq!(TagType::Function.get_directory_name(44), "functions");
+        assert_eq!(TagType::Function.get_directory_name(45), "function");
+        assert_eq!(TagType::DamageType.get_directory_name(26), "damage_type");
+        assert_eq!(TagType::DamageType.get_directory_name(48), "damage_type");
+        assert_eq!(TagType::Biome.get_directory_name(26), "worldgen/biome");
+        assert_eq!(TagType::Biome.to_string(), "worldgen/biome");
+    }
+
+    #[test]
+    fn test_from_json() {
+        let json = serde_json::json!({
+            "values": [
+                "foo:bar",
+                { "id": "bar:baz", "required": false },
+                { "id": "#baz:foo" }
+            ]
+        });
+
+        let tag = Tag::from_json(&json).expect("failed to parse tag");
+        assert!(!tag.get_replace());
+        assert_eq!(
+            tag.get_values(),
+            &vec![
+                TagValue::from("foo:bar"),
+                TagValue::Advanced {
+                    id: "bar:baz".to_string(),
+                    required: false
+                },
+                TagValue::Advanced {
+                    id: "#baz:foo".to_string(),
+                    required: true
+                },
+            ]
+        );
+
+        assert!(Tag::from_json(&serde_json::json!({ "replace": true })).is_none());
+        assert!(Tag::from_json(&serde_json::json!({ "values": [1] })).is_none());
+    }
 }
@@ -0,0 +1,337 @@
+//! Text components for commands like `tellraw` and `title`.
+
+use serde::{Deserialize, Serialize, Serializer};
+
+/// A text component that is displayed in chat, titles and other places.
+///
+/// It is serialized with serde into the JSON representation Minecraft expects.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TextComponent {
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bold: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hover_event: Option<HoverEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    click_event: Option<ClickEvent>,
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "deserialize_extra"
+    )]
+    extra: Vec<Self>,
+}
+
+impl TextComponent {
+    /// Create a new text component with the given text.
+    #[must_use]
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            color: None,
+            bold: None,
+            hover_event: None,
+            click_event: None,
+            extra: Vec::new(),
+        }
+    }
+
+    /// Parse a component from its JSON representation.
+    ///
+    /// Besides objects, plain strings and arrays are accepted, the components following the first one
+    /// of an array become its extra components.
+    /// Returns `None` if the value is not a text component or uses properties that are not supported.
+    #[must_use]
+    pub fn from_json(value: &serde_json::Value) -> Option<Self> {
+        match value {
+            serde_json::Value::String(text) => Some(Self::new(text)),
+            serde_json::Value::Array(components) => {
+                let (first, rest) = components.split_first()?;
+                let mut component = Self::from_json(first)?;
+                for extra in rest {
+                    component.extra.push(Self::from_json(extra)?);
+                }
+                Some(component)
+            }
+            serde_json::Value::Object(_) => serde_json::from_value(value.clone()).ok(),
+            _ => None,
+        }
+    }
+
+    /// Set the color of the text.
+    #[must_use]
+    pub fn with_color(self, color: &str) -> Self {
+        Self {
+            color: Some(color.to_string()),
+            ..self
+        }
+    }
+
+    /// Set whether the text is bold.
+    #[must_use]
+    pub fn with_bold(self, bold: bool) -> Self {
+        Self {
+            bold: Some(bold),
+            ..self
+        }
+    }
+
+    /// Set the event that is triggered when hovering over the text.
+    #[must_use]
+    pub fn with_hover_event(self, hover_event: HoverEvent) -> Self {
+        Self {
+            hover_event: Some(hover_event),
+            ..self
+        }
+    }
+
+    /// Set the event that is triggered when clicking on the text.
+    #[must_use]
+    pub fn with_click_event(self, click_event: ClickEvent) -> Self {
+        Self {
+            click_event: Some(click_event),
+            ..self
+        }
+    }
+
+    /// Add a component that is displayed after this one and inherits its style.
+    #[must_use]
+    pub fn with_extra(mut self, component: Self) -> Self {
+        self.extra.push(component);
+        self
+    }
+
+    /// Get the text of the component.
+    #[must_use]
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Serialize the component into its JSON representation.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        to_json_string(self)
+    }
+
+    /// Serialize a list of components into its JSON representation.
+    ///
+    /// A single component is serialized as a bare object instead of an array.
+    #[must_use]
+    pub fn list_to_json(components: &[Self]) -> String {
+        to_json_string(&ComponentList(components))
+    }
+}
+
+impl From<&str> for TextComponent {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+/// Event that is triggered when hovering over a text component.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "action", content = "value", rename_all = "snake_case")]
+pub enum HoverEvent {
+    /// Show the text components.
+    ShowText(#[serde(with = "component_list")] Vec<TextComponent>),
+}
+
+impl HoverEvent {
+    /// Serialize the event into its JSON representation.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        to_json_string(self)
+    }
+}
+
+/// Event that is triggered when clicking on a text component.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "action", content = "value", rename_all = "snake_case")]
+pub enum ClickEvent {
+    /// Run the command as the player clicking.
+    RunCommand(String),
+    /// Insert the command into the chat input.
+    SuggestCommand(String),
+    /// Open the URL in the browser.
+    OpenUrl(String),
+    /// Copy the text to the clipboard.
+    CopyToClipboard(String),
+}
+
+impl ClickEvent {
+    /// Serialize the event into its JSON representation.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        to_json_string(self)
+    }
+}
+
+/// List of components that is serialized as a bare object if it contains a single component.
+struct ComponentList<'a>(&'a [TextComponent]);
+
+impl Serialize for ComponentList<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        component_list::serialize(self.0, serializer)
+    }
+}
+
+/// Serde functions for lists of components, see [`ComponentList`].
+mod component_list {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::TextComponent;
+
+    pub fn serialize<S: Serializer>(
+        components: &[TextComponent],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if let [component] = components {
+            component.serialize(serializer)
+        } else {
+            components.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<TextComponent>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(TextComponent),
+            Many(Vec<TextComponent>),
+        }
+
+        Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(component) => vec![component],
+            OneOrMany::Many(components) => components,
+        })
+    }
+}
+
+/// Deserialize the extra components, which may be given as plain strings as well.
+fn deserialize_extra<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<TextComponent>, D::Error> {
+    Vec::<serde_json::Value>::deserialize(deserializer)?
+        .iter()
+        .map(|value| {
+            TextComponent::from_json(value)
+                .ok_or_else(|| serde::de::Error::custom("invalid extra text component"))
+        })
+        .collect()
+}
+
+/// Serialize the value into a JSON string.
+///
+/// Text components only contain string keys and plain values, so serializing them cannot fail.
+fn to_json_string<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let component = TextComponent::new("Click \"here\"\nnow")
+            .with_color("gold")
+            .with_bold(true)
+            .with_hover_event(HoverEvent::ShowText(vec!["hint".into()]))
+            .with_click_event(ClickEvent::RunCommand("/say hi".to_string()));
+
+        let json = component.to_json();
+        assert_eq!(
+            json,
+            r#"{"text":"Click \"here\"\nnow","color":"gold","bold":true,"hoverEvent":{"action":"show_text","value":{"text":"hint"}},"clickEvent":{"action":"run_command","value":"/say hi"}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()["text"],
+            "Click \"here\"\nnow"
+        );
+    }
+
+    #[test]
+    fn test_serde() {
+        let component = TextComponent::new("a")
+            .with_hover_event(HoverEvent::ShowText(vec!["b".into(), "c".into()]))
+            .with_click_event(ClickEvent::OpenUrl("https://example.com".to_string()));
+
+        let value = serde_json::to_value(&component).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "text": "a",
+                "hoverEvent": {"action": "show_text", "value": [{"text": "b"}, {"text": "c"}]},
+                "clickEvent": {"action": "open_url", "value": "https://example.com"}
+            })
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&component.to_json()).unwrap(),
+            value
+        );
+        assert_eq!(
+            serde_json::from_value::<TextComponent>(value).unwrap(),
+            component
+        );
+
+        let hover = HoverEvent::ShowText(vec!["b".into()]);
+        assert_eq!(
+            hover.to_json(),
+            r#"{"action":"show_text","value":{"text":"b"}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<HoverEvent>(&hover.to_json()).unwrap(),
+            hover
+        );
+    }
+
+    #[test]
+    fn test_from_json() {
+        let component = TextComponent::new("a")
+            .with_color("gold")
+            .with_extra(TextComponent::new("b").with_bold(true));
+        let json = serde_json::from_str(&component.to_json()).unwrap();
+        assert_eq!(TextComponent::from_json(&json), Some(component.clone()));
+
+        assert_eq!(
+            TextComponent::from_json(
+                &serde_json::json!([{"text": "a", "color": "gold"}, {"text": "b", "bold": true}])
+            ),
+            Some(component)
+        );
+        assert_eq!(
+            TextComponent::from_json(&serde_json::json!({"text": "a", "extra": ["b"]})),
+            Some(TextComponent::new("a").with_extra("b".into()))
+        );
+        assert_eq!(
+            TextComponent::from_json(&serde_json::json!("a")),
+            Some(TextComponent::new("a"))
+        );
+        assert_eq!(TextComponent::from_json(&serde_json::json!([])), None);
+        assert_eq!(
+            TextComponent::from_json(&serde_json::json!({"text": "a", "italic": true})),
+            None
+        );
+    }
+
+    #[test]
+    fn test_list_to_json() {
+        let a = TextComponent::new("a");
+        let b = TextComponent::new("b").with_color("red");
+
+        assert_eq!(
+            TextComponent::list_to_json(std::slice::from_ref(&a)),
+            a.to_json()
+        );
+        assert_eq!(
+            TextComponent::list_to_json(&[a, b]),
+            r#"[{"text":"a"},{"text":"b","color":"red"}]"#
+        );
+    }
+}
@@ -0,0 +1,601 @@
+//! Detailed validation of datapacks.
+
+use std::ops::RangeInclusive;
+
+use getset::CopyGetters;
+
+use crate::util::{
+    selector::{Selector, SelectorError},
+    tokenize::split_words,
+};
+
+use super::{
+    tag::{TagType, TagValue},
+    CommandVisitor, Datapack, Function, Namespace, NbtType, OnRelation,
+};
+
+/// Server commands that require a higher permission level than functions have.
+const FUNCTION_FORBIDDEN_CMDS: &[&str] = &[
+    "ban",
+    "ban-ip",
+    "banlist",
+    "deop",
+    "kick",
+    "op",
+    "pardon",
+    "pardon-ip",
+    "publish",
+    "save-all",
+    "save-off",
+    "save-on",
+    "setidletimeout",
+    "stop",
+    "whitelist",
+];
+
+/// Problem found while validating a datapack.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum ValidationError {
+    /// A command of a function is not valid for the supported pack formats.
+    #[error("command {index} of function {namespace}:{function} is not valid for the supported pack formats: {reason}")]
+    IncompatibleCommand {
+        /// Namespace of the function.
+        namespace: String,
+        /// Name of the function.
+        function: String,
+        /// Index of the command in the function.
+        index: usize,
+        /// Why the command is not valid, e.g. ``command `attribute` requires pack format >= 6``.
+        reason: String,
+    },
+    /// A function contains a server command that cannot be run from functions.
+    #[error("function {namespace}:{function} contains the server command `{command}`, which cannot be run from functions")]
+    ForbiddenCommand {
+        /// Namespace of the function.
+        namespace: String,
+        /// Name of the function.
+        function: String,
+        /// The forbidden command.
+        command: String,
+    },
+    /// An `on` subcommand of a function has a relation that does not exist.
+    #[error("function {namespace}:{function} uses the unknown relation `{relation}` in an `on` subcommand")]
+    UnknownOnRelation {
+        /// Namespace of the function.
+        namespace: String,
+        /// Name of the function.
+        function: String,
+        /// The unknown relation.
+        relation: String,
+    },
+    /// A `store` subcommand of a function stores into NBT with an invalid type or scale.
+    #[error(
+        "function {namespace}:{function} has an invalid `store` subcommand `{arguments}`: {reason}"
+    )]
+    InvalidStore {
+        /// Namespace of the function.
+        namespace: String,
+        /// Name of the function.
+        function: String,
+        /// Arguments of the `store` subcommand.
+        arguments: String,
+        /// Why the arguments are invalid.
+        reason: String,
+    },
+    /// A function tag references a function or function tag that does not exist in the datapack.
+    #[error("function tag {namespace}:{tag} references {reference}, which does not exist")]
+    DanglingFunctionReference {
+        /// Namespace of the tag.
+        namespace: String,
+        /// Name of the tag.
+        tag: String,
+        /// The referenced function or tag (prefixed with `#`).
+        reference: String,
+    },
+}
+
+/// Options for [`Datapack::validate_with`].
+#[allow(clippy::module_name_repetitions)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, CopyGetters)]
+pub struct ValidateOptions {
+    /// Whether to check the syntax of the selectors of `as` and `at` subcommands.
+    #[get_copy = "pub"]
+    pub(crate) check_selectors: bool,
+}
+
+impl ValidateOptions {
+    /// Set whether to check the syntax and argument keys of the selectors of `as` and `at` subcommands
+    /// with [`Selector::parse`]. Defaults to `false`.
+    #[must_use]
+    pub fn with_check_selectors(self, check_selectors: bool) -> Self {
+        Self { check_selectors }
+    }
+}
+
+impl Datapack {
+    /// Check whether the datapack is valid with the given pack format, applying the stricter checks enabled in the options.
+    ///
+    /// Overlays are validated against the pack formats they are applied for.
+    #[must_use]
+    pub fn validate_with(&self, options: &ValidateOptions) -> bool {
+        let valid_selectors = || {
+            self.overlays
+                .iter()
+                .flat_map(|overlay| overlay.get_namespaces().values())
+                .chain(self.namespaces.values())
+                .flat_map(|namespace| namespace.get_functions().values())
+                .flat_map(Function::get_commands)
+                .all(|command| {
+                    let mut checker = SelectorChecker::default();
+                    command.visit(&mut checker);
+                    for err in &checker.errors {
+                        tracing::warn!("Invalid selector: {err}");
+                    }
+                    checker.errors.is_empty()
+                })
+        };
+
+        self.validate() && (!options.check_selectors || valid_selectors())
+    }
+
+    /// Validate the datapack and return all problems found.
+    ///
+    /// In addition to the pack format checks of [`Datapack::validate`], this checks that
+    /// no function contains server commands like `op` or `stop` that cannot be run from functions and
+    /// all required entries of function tags reference functions or function tags of the datapack.
+    /// References into the `minecraft` namespace are allowed, as they may be provided by the game.
+    /// Functions of overlays are checked against the pack formats the overlay is applied for.
+    #[must_use]
+    pub fn validate_detailed(&self) -> Vec<ValidationError> {
+        let pack_formats = self
+            .supported_formats
+            .clone()
+            .unwrap_or(self.pack_format..=self.pack_format);
+
+        let mut namespaces = self.namespaces.iter().collect::<Vec<_>>();
+        namespaces.sort_by_key(|(name, _)| *name);
+
+        let mut errors = Vec::new();
+
+        let overlays = self
+            .overlays
+            .iter()
+            .map(|overlay| (overlay.get_namespaces(), overlay.formats().clone()));
+        // overlays are validated against the formats they are applied for
+        for (namespaces, pack_formats) in
+            std::iter::once((&self.namespaces, pack_formats)).chain(overlays)
+        {
+            let mut namespaces = namespaces.iter().collect::<Vec<_>>();
+            namespaces.sort_by_key(|(name, _)| *name);
+            for (namespace_name, namespace) in namespaces {
+                validate_functions(namespace_name, namespace, &pack_formats, &mut errors);
+            }
+        }
+
+        for (namespace_name, namespace) in &namespaces {
+            let mut tags = namespace
+                .get_tags()
+                .iter()
+                .filter(|((_, tag_type), _)| *tag_type == TagType::Function)
+                .collect::<Vec<_>>();
+            tags.sort_by_key(|((name, _), _)| name);
+            for ((tag_name, _), tag) in tags {
+                for value in tag.get_values() {
+                    let reference = match value {
+                        TagValue::Simple(id) | TagValue::Advanced { id, required: true } => id,
+                        TagValue::Advanced {
+                            required: false, ..
+                        } => continue,
+                    };
+                    if !self.resolves_function_reference(reference) {
+                        errors.push(ValidationError::DanglingFunctionReference {
+                            namespace: (*namespace_name).clone(),
+                            tag: tag_name.clone(),
+                            reference: reference.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Check whether the reference to a function or function tag (prefixed with `#`) can be resolved.
+    fn resolves_function_reference(&self, reference: &str) -> bool {
+        let (is_tag, id) = reference
+            .strip_prefix('#')
+            .map_or((false, reference), |id| (true, id));
+        let (namespace, name) = id.split_once(':').unwrap_or(("minecraft", id));
+
+        namespace == "minecraft"
+            || self.namespace(namespace).is_some_and(|namespace| {
+                if is_tag {
+                    namespace.tag(name, TagType::Function).is_some()
+                } else {
+                    namespace.function(name).is_some()
+                }
+            })
+    }
+}
+
+/// Validate the functions of the namespace against the pack formats.
+fn validate_functions(
+    namespace_name: &str,
+    namespace: &Namespace,
+    pack_formats: &RangeInclusive<u8>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let mut functions = namespace.get_functions().iter().collect::<Vec<_>>();
+    functions.sort_by_key(|(name, _)| *name);
+    for (function_name, function) in functions {
+        for (index, command) in function.get_commands().iter().enumerate() {
+            errors.extend(
+                command
+                    .incompatibilities(pack_formats)
+                    .into_iter()
+                    .map(|reason| ValidationError::IncompatibleCommand {
+                        namespace: namespace_name.to_string(),
+                        function: function_name.clone(),
+                        index,
+                        reason,
+                    }),
+            );
+        }
+
+        let mut collector = ForbiddenCommandCollector::default();
+        for command in function.get_commands() {
+            command.visit(&mut collector);
+        }
+        errors.extend(collector.commands.into_iter().map(|command| {
+            ValidationError::ForbiddenCommand {
+                namespace: namespace_name.to_string(),
+                function: function_name.clone(),
+                command,
+            }
+        }));
+        errors.extend(collector.unknown_relations.into_iter().map(|relation| {
+            ValidationError::UnknownOnRelation {
+                namespace: namespace_name.to_string(),
+                function: function_name.clone(),
+                relation,
+            }
+        }));
+        errors.extend(
+            collector
+                .invalid_stores
+                .into_iter()
+                .map(|(arguments, reason)| ValidationError::InvalidStore {
+                    namespace: namespace_name.to_string(),
+                    function: function_name.clone(),
+                    arguments,
+                    reason,
+                }),
+        );
+    }
+}
+
+/// Collects raw commands that cannot be run from functions, unknown relations of `on` subcommands
+/// and invalid `store` subcommands.
+#[derive(Debug, Default)]
+struct ForbiddenCommandCollector {
+    commands: Vec<String>,
+    unknown_relations: Vec<String>,
+    invalid_stores: Vec<(String, String)>,
+}
+
+impl CommandVisitor for ForbiddenCommandCollector {
+    fn visit_raw(&mut self, command: &str) {
+        for line in command.lines() {
+            let forbidden = line
+                .split_ascii_whitespace()
+                .next()
+                .is_some_and(|cmd| FUNCTION_FORBIDDEN_CMDS.contains(&cmd));
+            if forbidden {
+                self.commands.push(line.trim().to_string());
+            }
+        }
+    }
+
+    fn visit_subcommand(&mut self, name: &str, argument: &str) {
+        if name == "on" && OnRelation::from_name(argument).is_none() {
+            self.unknown_relations.push(argument.to_string());
+        }
+        if name == "store" {
+            if let Some(reason) = store_problem(argument) {
+                self.invalid_stores.push((argument.to_string(), reason));
+            }
+        }
+    }
+}
+
+/// Check the type and scale of `store` subcommands storing into NBT.
+///
+/// Returns why the arguments are invalid, if they are.
+/// Arguments containing macro arguments are only known when running the function and are not checked.
+fn store_problem(arguments: &str) -> Option<String> {
+    if arguments.contains("$(") {
+        return None;
+    }
+    let Some(words) = split_words(arguments) else {
+        return Some("brackets, braces or quotes of the NBT path are not closed".to_string());
+    };
+    let parts = words
+        .into_iter()
+        .map(|range| &arguments[range])
+        .collect::<Vec<_>>();
+    // kind of the value, kind of the destination, arguments of the destination, type and scale
+    let min_parts = match parts.get(1).copied() {
+        Some("storage" | "entity") => 6,
+        Some("block") => 8,
+        _ => return None,
+    };
+    if parts.len() < min_parts {
+        return Some("missing NBT path, type or scale".to_string());
+    }
+
+    let (ty, scale) = (parts[parts.len() - 2], parts[parts.len() - 1]);
+    if NbtType::from_name(ty).is_none() {
+        Some(format!("`{ty}` is not a numeric NBT type"))
+    } else if !scale.parse::<f64>().is_ok_and(f64::is_finite) {
+        Some(format!("scale `{scale}` is not a finite number"))
+    } else {
+        None
+    }
+}
+
+/// Collects the errors of invalid selectors of `as` and `at` subcommands.
+#[derive(Debug, Default)]
+pub(super) struct SelectorChecker {
+    pub(super) errors: Vec<SelectorError>,
+}
+
+impl CommandVisitor for SelectorChecker {
+    fn visit_subcommand(&mut self, name: &str, argument: &str) {
+        // player names and UUIDs are valid targets as well
+        if matches!(name, "as" | "at" | "as_at") && argument.starts_with('@') {
+            if let Err(err) = Selector::parse(argument) {
+                self.errors.push(err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datapack::{Command, ReturnValue};
+
+    use super::*;
+
+    #[test]
+    fn test_validate_selectors() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        let as_ = |selector: &str| {
+            Command::Execute(crate::datapack::Execute::As(
+                selector.to_string(),
+                Box::new(crate::datapack::Execute::Run(Box::new("say hi".into()))),
+            ))
+        };
+        let function = dp.namespace_mut("foo").function_mut("bar");
+        function.add_command(as_("@e[type=minecraft:pig,limit=1]"));
+        function.add_command(as_("Steve"));
+
+        let strict = ValidateOptions::default().with_check_selectors(true);
+        assert!(dp.validate_with(&strict));
+
+        dp.add_overlay("overlay", 40..=48)
+            .namespace_mut("foo")
+            .function_mut("baz")
+            .add_command(as_("@e[typ=pig]"));
+        assert!(dp.validate_with(&ValidateOptions::default()));
+        assert!(!dp.validate_with(&strict));
+    }
+
+    #[test]
+    fn test_dangling_function_references() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        dp.namespace_mut("foo")
+            .function_mut("bar")
+            .add_command("say bar");
+        dp.namespace_mut("foo")
+            .tag_mut("init", TagType::Function)
+            .add_value(TagValue::from("foo:bar"));
+
+        dp.add_load("foo:bar");
+        dp.add_load("#foo:init");
+        dp.add_load("minecraft:vanilla");
+        dp.add_tick("vanilla");
+        assert!(dp.validate_detailed().is_empty());
+
+        dp.add_tick("foo:baz");
+        dp.add_tick("#foo:missing");
+        dp.namespace_mut("minecraft")
+            .tag_mut("tick", TagType::Function)
+            .add_value(TagValue::optional("foo:optional"));
+
+        assert_eq!(
+            dp.validate_detailed(),
+            vec![
+                ValidationError::DanglingFunctionReference {
+                    namespace: "minecraft".to_string(),
+                    tag: "tick".to_string(),
+                    reference: "foo:baz".to_string(),
+                },
+                ValidationError::DanglingFunctionReference {
+                    namespace: "minecraft".to_string(),
+                    tag: "tick".to_string(),
+                    reference: "#foo:missing".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forbidden_command() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        let function = dp.namespace_mut("foo").function_mut("bar");
+        function.add_command("say op");
+        function.add_command("op @s");
+        function.add_command(crate::datapack::Command::Execute(
+            crate::datapack::Execute::As(
+                "@a".to_string(),
+                Box::new(crate::datapack::Execute::Run(Box::new("stop".into()))),
+            ),
+        ));
+
+        assert_eq!(
+            dp.validate_detailed(),
+            vec![
+                ValidationError::ForbiddenCommand {
+                    namespace: "foo".to_string(),
+                    function: "bar".to_string(),
+                    command: "op @s".to_string(),
+                },
+                ValidationError::ForbiddenCommand {
+                    namespace: "foo".to_string(),
+                    function: "bar".to_string(),
+                    command: "stop".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_on_relation() {
+        use crate::datapack::{Command, Execute};
+
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        let function = dp.namespace_mut("foo").function_mut("bar");
+        function.add_command(Command::Execute(Execute::on(
+            OnRelation::Vehicle,
+            Execute::Run(Box::new("say hi".into())),
+        )));
+        function.add_command(Command::Execute(Execute::On(
+            "rider".to_string(),
+            Box::new(Execute::Run(Box::new("say hi".into()))),
+        )));
+
+        assert!(dp.validate());
+        assert_eq!(
+            dp.validate_detailed(),
+            vec![ValidationError::UnknownOnRelation {
+                namespace: "foo".to_string(),
+                function: "bar".to_string(),
+                relation: "rider".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_store() {
+        use crate::datapack::{Execute, StoreDestination, StoreTarget};
+
+        let store = |target: &str| {
+            Command::Execute(Execute::builder().store(target).run("time query gametime"))
+        };
+        let storage = |ty: NbtType, scale: f64| {
+            Command::Execute(Execute::store(
+                &StoreTarget::Result(StoreDestination::Storage {
+                    resource: "foo:bar".to_string(),
+                    path: "time".to_string(),
+                    ty,
+                    scale,
+                }),
+                Execute::Run(Box::new("time query gametime".into())),
+            ))
+        };
+
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        let function = dp.namespace_mut("foo").function_mut("bar");
+        function.add_command(storage(NbtType::Int, 1.0));
+        function.add_command(storage(NbtType::Double, 0.001));
+        function.add_command(store("success block ~ ~ ~ Items[0].Count byte 1"));
+        function.add_command(store("result score @s time"));
+        function.add_command(store(
+            "result entity @s Inventory[{Slot: 0b}].tag.\"some value\" int 1",
+        ));
+        function.add_command(store("result storage foo:bar $(path) $(type) $(scale)"));
+        assert!(dp.validate_detailed().is_empty());
+
+        let function = dp.namespace_mut("foo").function_mut("bar");
+        function.add_command(storage(NbtType::Float, f64::NAN));
+        function.add_command(store("result entity @s Health string 1"));
+        function.add_command(store("result storage foo:bar time int"));
+        function.add_command(store("result entity @s Inventory[{Slot: 0b}] int"));
+        function.add_command(store("result entity @s Inventory[{Slot: 0b} int 1"));
+
+        let reasons = dp
+            .validate_detailed()
+            .into_iter()
+            .map(|err| match err {
+                ValidationError::InvalidStore { reason, .. } => reason,
+                err => panic!("unexpected error: {err}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            vec![
+                "scale `NaN` is not a finite number".to_string(),
+                "`string` is not a numeric NBT type".to_string(),
+                "missing NBT path, type or scale".to_string(),
+                "missing NBT path, type or scale".to_string(),
+                "brackets, braces or quotes of the NBT path are not closed".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_incompatible_function() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT).with_supported_formats(5..=48);
+        let function = dp.namespace_mut("foo").function_mut("bar");
+        function.add_command("say hi");
+        function.add_command("attribute @s minecraft:generic.armor base set 1");
+        function.add_command(Command::Group(vec![
+            "say hi".into(),
+            "replaceitem entity @s armor.head minecraft:stone".into(),
+        ]));
+
+        assert_eq!(
+            dp.validate_detailed(),
+            vec![
+                ValidationError::IncompatibleCommand {
+                    namespace: "foo".to_string(),
+                    function: "bar".to_string(),
+                    index: 1,
+                    reason: "command `attribute` requires pack format >= 6".to_string(),
+                },
+                ValidationError::IncompatibleCommand {
+                    namespace: "foo".to_string(),
+                    function: "bar".to_string(),
+                    index: 2,
+                    reason: "command `replaceitem` requires pack format <= 6".to_string(),
+                }
+            ]
+        );
+        assert!(!dp.validate());
+    }
+
+    #[test]
+    fn test_overlay_validation() {
+        let mut dp = Datapack::new(Datapack::LATEST_FORMAT);
+        dp.namespace_mut("foo")
+            .function_mut("bar")
+            .add_command(Command::Return(ReturnValue::Value(1)));
+        dp.add_overlay("old", 10..=12)
+            .namespace_mut("foo")
+            .function_mut("bar")
+            .add_command(Command::Return(ReturnValue::Value(1)));
+
+        assert_eq!(
+            dp.validate_detailed(),
+            vec![ValidationError::IncompatibleCommand {
+                namespace: "foo".to_string(),
+                function: "bar".to_string(),
+                index: 0,
+                reason: "command `return` requires pack format >= 15".to_string(),
+            }]
+        );
+    }
+}
@@ -1,37 +1,296 @@
 //! Compile options for the compiler.
 
-use std::sync::Mutex;
+use std::sync::{Mutex, PoisonError};
 
-use getset::Getters;
+use getset::{CopyGetters, Getters};
 
 use crate::datapack::{Datapack, Function};
 
 use super::extendable_queue::ExtendableQueue;
 
 /// Compile options for the compiler.
-#[allow(missing_copy_implementations, clippy::module_name_repetitions)]
+#[allow(
+    missing_copy_implementations,
+    clippy::module_name_repetitions,
+    clippy::struct_excessive_bools
+)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Getters, CopyGetters)]
 pub struct CompileOptions {
     /// The pack format of the target datapack.
+    #[get_copy = "pub"]
     pub(crate) pack_format: u8,
+    /// Whether the pack format was set with [`CompileOptions::with_pack_format`].
+    pub(crate) pack_format_set: bool,
     /// Whether to compile in debug mode.
+    #[get_copy = "pub"]
     pub(crate) debug: bool,
+    /// Format of the debug messages.
+    #[get = "pub"]
+    pub(crate) debug_format: DebugFormat,
+    /// Whether to emit folders for namespaces without functions and tags.
+    #[get_copy = "pub"]
+    pub(crate) emit_empty_namespaces: bool,
+    /// Whether to omit comments, empty lines and debug messages.
+    #[get_copy = "pub"]
+    pub(crate) minify: bool,
+    /// Maximum number of lines of a compiled function before the rest is moved into a continuation function.
+    #[get_copy = "pub"]
+    pub(crate) max_function_lines: Option<usize>,
+    /// Whether to deduplicate and sort the values of tags.
+    #[get_copy = "pub"]
+    pub(crate) sorted_tags: bool,
+    /// Name of the subfolder generated helper functions are placed in.
+    #[get = "pub"]
+    pub(crate) generated_subfolder: String,
+    /// Number of hex characters of the hashes used in generated names.
+    #[get_copy = "pub"]
+    pub(crate) generated_hash_length: usize,
+    /// Namespace of the storage used for tracking the success of conditions.
+    #[get = "pub"]
+    pub(crate) internal_namespace: String,
+    /// Namespace generated helper functions are placed in, defaults to the namespace of the function they are generated from.
+    #[get = "pub"]
+    pub(crate) generated_namespace: Option<String>,
 }
 
 impl CompileOptions {
+    /// Set the pack format of the target datapack, which determines directory names and available features.
+    ///
+    /// Defaults to [`Datapack::LATEST_FORMAT`]. If not set, [`Datapack::compile`] uses the pack format of the datapack.
+    #[must_use]
+    pub fn with_pack_format(self, pack_format: u8) -> Self {
+        Self {
+            pack_format,
+            pack_format_set: true,
+            ..self
+        }
+    }
+
     /// Set whether to compile in debug mode.
     #[must_use]
     pub fn with_debug(self, debug: bool) -> Self {
         Self { debug, ..self }
     }
+
+    /// Set whether to emit folders for namespaces without functions and tags.
+    ///
+    /// By default, empty namespaces are skipped. The `minecraft` namespace is only
+    /// emitted if it contains the tags created by [`Datapack::add_tick`] or [`Datapack::add_load`].
+    #[must_use]
+    pub fn with_emit_empty_namespaces(self, emit_empty_namespaces: bool) -> Self {
+        Self {
+            emit_empty_namespaces,
+            ..self
+        }
+    }
+
+    /// Set whether to omit comments, empty lines and debug messages from the compiled functions.
+    ///
+    /// Debug messages are omitted regardless of [`CompileOptions::with_debug`].
+    #[must_use]
+    pub fn with_minify(self, minify: bool) -> Self {
+        Self { minify, ..self }
+    }
+
+    /// Set the maximum number of lines of a compiled function.
+    ///
+    /// Longer functions are split on command boundaries, the remaining commands are moved into a
+    /// continuation function in the generated subfolder, which is called in the last line.
+    /// The limit is raised to at least 2 lines. Functions containing macro lines are not split,
+    /// as the macro arguments cannot be passed on. Defaults to `None`, which disables splitting.
+    #[must_use]
+    pub fn with_max_function_lines(self, max_function_lines: Option<usize>) -> Self {
+        Self {
+            max_function_lines: max_function_lines.map(|max| max.max(2)),
+            ..self
+        }
+    }
+
+    /// Set whether to deduplicate the values of tags with [`Tag::dedup`](crate::datapack::tag::Tag::dedup)
+    /// and sort them by their id when compiling.
+    ///
+    /// Values with the same id keep their order, so the output is deterministic. Defaults to `false`.
+    #[must_use]
+    pub fn with_sorted_tags(self, sorted_tags: bool) -> Self {
+        Self {
+            sorted_tags,
+            ..self
+        }
+    }
+
+    /// Set the name of the subfolder generated helper functions are placed in.
+    ///
+    /// Defaults to `sb`.
+    #[must_use]
+    pub fn with_generated_subfolder(self, generated_subfolder: &str) -> Self {
+        Self {
+            generated_subfolder: generated_subfolder.to_string(),
+            ..self
+        }
+    }
+
+    /// Set the number of hex characters of the hashes used in the names of generated functions
+    /// and the storage keys of conditions. The length is clamped to `8..=32` and defaults to 16.
+    ///
+    /// Shorter hashes are more readable but more likely to collide in large packs.
+    /// Changing the length changes all generated names.
+    #[must_use]
+    pub fn with_generated_hash_length(self, generated_hash_length: usize) -> Self {
+        Self {
+            generated_hash_length: generated_hash_length.clamp(8, 32),
+            ..self
+        }
+    }
+
+    /// Set the namespace of the storage `<namespace>:cond` used for tracking the success of conditions.
+    ///
+    /// Defaults to `shulkerbox`.
+    #[must_use]
+    pub fn with_internal_namespace(self, internal_namespace: &str) -> Self {
+        Self {
+            internal_namespace: internal_namespace.to_string(),
+            ..self
+        }
+    }
+
+    /// Set the namespace generated helper functions are placed in.
+    ///
+    /// By default, helper functions are generated in the namespace of the function they are generated from.
+    /// Helper functions generated into another namespace are only emitted by [`Datapack::compile`] and
+    /// [`Datapack::compile_streaming`], not by compiling a single namespace.
+    #[must_use]
+    pub fn with_generated_namespace(self, namespace: &str) -> Self {
+        Self {
+            generated_namespace: Some(namespace.to_string()),
+            ..self
+        }
+    }
+
+    /// Set the format of the debug messages.
+    #[must_use]
+    pub fn with_debug_format(self, debug_format: DebugFormat) -> Self {
+        Self {
+            debug_format,
+            ..self
+        }
+    }
 }
 
 impl Default for CompileOptions {
     fn default() -> Self {
         Self {
             pack_format: Datapack::LATEST_FORMAT,
+            pack_format_set: false,
             debug: true,
+            debug_format: DebugFormat::default(),
+            emit_empty_namespaces: false,
+            minify: false,
+            max_function_lines: None,
+            sorted_tags: false,
+            generated_subfolder: String::from("sb"),
+            generated_hash_length: 16,
+            internal_namespace: String::from("shulkerbox"),
+            generated_namespace: None,
+        }
+    }
+}
+
+/// Format of the messages generated by [`Command::Debug`](crate::datapack::Command::Debug).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Getters, CopyGetters)]
+pub struct DebugFormat {
+    /// Label shown in brackets in front of the message.
+    #[get = "pub"]
+    pub(crate) label: String,
+    /// Color of the label.
+    #[get = "pub"]
+    pub(crate) label_color: String,
+    /// Color of the brackets around the label.
+    #[get = "pub"]
+    pub(crate) bracket_color: String,
+    /// Color of the message.
+    #[get = "pub"]
+    pub(crate) message_color: String,
+    /// Players that receive the message.
+    #[get_copy = "pub"]
+    pub(crate) target: DebugTarget,
+}
+
+impl DebugFormat {
+    /// Set the label shown in brackets in front of the message.
+    #[must_use]
+    pub fn with_label(self, label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            ..self
+        }
+    }
+
+    /// Set the color of the label.
+    #[must_use]
+    pub fn with_label_color(self, color: &str) -> Self {
+        Self {
+            label_color: color.to_string(),
+            ..self
+        }
+    }
+
+    /// Set the color of the brackets around the label.
+    #[must_use]
+    pub fn with_bracket_color(self, color: &str) -> Self {
+        Self {
+            bracket_color: color.to_string(),
+            ..self
+        }
+    }
+
+    /// Set the color of the message.
+    #[must_use]
+    pub fn with_message_color(self, color: &str) -> Self {
+        Self {
+            message_color: color.to_string(),
+            ..self
+        }
+    }
+
+    /// Set the players that receive the message.
+    #[must_use]
+    pub fn with_target(self, target: DebugTarget) -> Self {
+        Self { target, ..self }
+    }
+}
+
+impl Default for DebugFormat {
+    fn default() -> Self {
+        Self {
+            label: String::from("DEBUG"),
+            label_color: String::from("dark_green"),
+            bracket_color: String::from("dark_blue"),
+            message_color: String::from("black"),
+            target: DebugTarget::All,
+        }
+    }
+}
+
+/// Players that receive debug messages.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DebugTarget {
+    /// All players (`@a`).
+    #[default]
+    All,
+    /// The entity executing the function (`@s`).
+    Executor,
+}
+
+impl DebugTarget {
+    /// Get the target selector.
+    #[must_use]
+    pub fn selector(self) -> &'static str {
+        match self {
+            Self::All => "@a",
+            Self::Executor => "@s",
         }
     }
 }
@@ -40,10 +299,186 @@ impl Default for CompileOptions {
 #[allow(missing_copy_implementations)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Default)]
-pub struct CompilerState {}
+pub struct CompilerState {
+    /// Helper functions to be generated in the namespace set by [`CompileOptions::with_generated_namespace`].
+    generated_functions: Vec<(String, Function)>,
+    /// Non-fatal issues found while compiling.
+    diagnostics: Vec<Diagnostic>,
+    /// Next unique identifier of the compilation.
+    uid_counter: usize,
+}
+
+impl CompilerState {
+    /// Add a helper function to be generated in the generated namespace.
+    pub fn add_generated_function(&mut self, path: &str, function: Function) {
+        self.generated_functions.push((path.to_string(), function));
+    }
+
+    /// Take all helper functions to be generated in the generated namespace.
+    #[must_use]
+    pub fn take_generated_functions(&mut self) -> Vec<(String, Function)> {
+        std::mem::take(&mut self.generated_functions)
+    }
+
+    /// Add a non-fatal issue found while compiling.
+    pub fn add_diagnostic(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Take all non-fatal issues found while compiling.
+    #[must_use]
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// Request an identifier that is unique among all identifiers requested during the compilation.
+    #[must_use]
+    pub fn request_uid(&mut self) -> usize {
+        let uid = self.uid_counter;
+        self.uid_counter += 1;
+        uid
+    }
+
+    /// Create an empty state continuing the identifiers of this state,
+    /// used to compile a namespace on its own thread.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn fork(&self) -> Self {
+        Self {
+            uid_counter: self.uid_counter,
+            ..Self::default()
+        }
+    }
+
+    /// Append the generated functions and diagnostics of a forked state.
+    ///
+    /// Identifiers requested afterwards are unique among the identifiers of both states.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn join(&mut self, forked: Self) {
+        self.generated_functions.extend(forked.generated_functions);
+        self.diagnostics.extend(forked.diagnostics);
+        self.uid_counter = self.uid_counter.max(forked.uid_counter);
+    }
+}
 /// Mutex for the compiler state.
 pub type MutCompilerState = Mutex<CompilerState>;
 
+/// Log the non-fatal issue and add it to the diagnostics of the compiler state.
+///
+/// # Errors
+/// - If the compiler state was poisoned
+pub(crate) fn try_report(
+    global_state: &MutCompilerState,
+    kind: DiagnosticKind,
+    message: String,
+) -> Result<(), CompileError> {
+    log_diagnostic(kind, &message);
+    global_state
+        .lock()?
+        .add_diagnostic(Diagnostic { kind, message });
+    Ok(())
+}
+
+/// Log the non-fatal issue and add it to the diagnostics of the compiler state while compiling commands,
+/// which cannot fail.
+///
+/// A poisoned compiler state is used anyway, the `try_*` compile methods return
+/// [`CompileError::PoisonedLock`] afterwards.
+pub(crate) fn report(global_state: &MutCompilerState, kind: DiagnosticKind, message: String) {
+    log_diagnostic(kind, &message);
+    global_state
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .add_diagnostic(Diagnostic { kind, message });
+}
+
+fn log_diagnostic(kind: DiagnosticKind, message: &str) {
+    if kind == DiagnosticKind::Internal {
+        tracing::error!("{message}");
+    } else {
+        tracing::warn!("{message}");
+    }
+}
+
+/// Check that the compiler state was not poisoned while compiling commands.
+///
+/// # Errors
+/// - If the compiler state was poisoned
+pub(crate) fn check_poisoned(global_state: &MutCompilerState) -> Result<(), CompileError> {
+    if global_state.is_poisoned() {
+        Err(CompileError::PoisonedLock)
+    } else {
+        Ok(())
+    }
+}
+
+/// Non-fatal issue found while compiling a datapack, see [`Datapack::try_compile_with_diagnostics`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Getters, CopyGetters)]
+pub struct Diagnostic {
+    /// Kind of the issue.
+    #[get_copy = "pub"]
+    kind: DiagnosticKind,
+    /// Description of the issue.
+    #[get = "pub"]
+    message: String,
+}
+
+impl Diagnostic {
+    /// Create a new diagnostic.
+    #[must_use]
+    pub fn new(kind: DiagnosticKind, message: &str) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Kind of a [`Diagnostic`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticKind {
+    /// A command is not supported by the pack format it is compiled for.
+    IncompatibleCommand,
+    /// A selector of an `as` or `at` subcommand is invalid.
+    InvalidSelector,
+    /// A function compiles into an empty file.
+    EmptyFunction,
+    /// A function exceeds the maximum number of lines but cannot be split.
+    OversizedFunction,
+    /// The pack format the datapack is compiled for does not fit the datapack or parts of it.
+    PackFormat,
+    /// The compiler reached an unexpected state but could continue.
+    Internal,
+}
+
+/// Error that can occur while compiling a datapack.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, thiserror::Error)]
+pub enum CompileError {
+    /// A JSON file could not be serialized.
+    #[error("failed to serialize JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The compiler state was poisoned by a panic while it was locked.
+    ///
+    /// Compiling commands continues with the poisoned state, the error is returned once the compilation of the
+    /// current namespace or datapack ends.
+    #[error("compiler state lock was poisoned")]
+    PoisonedLock,
+}
+
+impl<T> From<PoisonError<T>> for CompileError {
+    fn from(_: PoisonError<T>) -> Self {
+        Self::PoisonedLock
+    }
+}
+
 /// State of the compiler for each function that can change during compilation.
 #[derive(Debug, Getters, Default)]
 pub struct FunctionCompilerState {
@@ -80,9 +515,71 @@ impl FunctionCompilerState {
 
     #[must_use]
     pub fn request_uid(&self) -> usize {
-        let mut guard = self.uid_counter.lock().unwrap();
+        let mut guard = self
+            .uid_counter
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
         let uid = *guard;
         *guard += 1;
         uid
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_uid() {
+        let mut state = CompilerState::default();
+        let uids = (0..3).map(|_| state.request_uid()).collect::<Vec<_>>();
+        assert_eq!(uids, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_compile_options_getters() {
+        let options = CompileOptions::default()
+            .with_debug(false)
+            .with_debug_format(DebugFormat::default().with_label("FOO"));
+
+        assert_eq!(options.pack_format(), Datapack::LATEST_FORMAT);
+        assert!(!options.debug());
+        assert_eq!(options.debug_format().label(), "FOO");
+        assert_eq!(options.debug_format().target(), DebugTarget::All);
+        assert!(!options.emit_empty_namespaces());
+        assert!(!options.minify());
+        assert_eq!(options.max_function_lines(), None);
+        assert_eq!(options.generated_subfolder(), "sb");
+        assert_eq!(options.generated_hash_length(), 16);
+        assert_eq!(options.generated_namespace(), &None);
+        assert_eq!(
+            options
+                .clone()
+                .with_generated_hash_length(4)
+                .generated_hash_length(),
+            8
+        );
+        assert_eq!(
+            options
+                .with_generated_hash_length(64)
+                .generated_hash_length(),
+            32
+        );
+    }
+
+    #[test]
+    fn test_compile_options_eq() {
+        use std::collections::HashSet;
+
+        let set = [
+            CompileOptions::default(),
+            CompileOptions::default(),
+            CompileOptions::default().with_debug(false),
+        ]
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&CompileOptions::default().with_debug(false)));
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::{
     collections::VecDeque,
-    sync::{Arc, RwLock, Weak},
+    sync::{Arc, PoisonError, RwLock, Weak},
 };
 
 /// A queue that can be extended while iterating over it.
@@ -33,7 +33,10 @@ where
 impl<T> ExtendableQueue<T> {
     /// Add an element to the queue.
     pub fn push(&self, value: T) {
-        self.queue.write().unwrap().push_back(value);
+        self.queue
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push_back(value);
     }
 
     /// Get the queue.
@@ -50,31 +53,46 @@ impl<T> ExtendableQueue<T> {
 
     /// Clear the queue.
     pub fn clear(&self) {
-        self.queue.write().unwrap().clear();
+        self.queue
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
     }
 
     /// Get the length of the queue.
     #[must_use]
     pub fn len(&self) -> usize {
-        self.queue.read().unwrap().len()
+        self.queue
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
     }
 
     /// Check if the queue is empty.
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.queue.read().unwrap().is_empty()
+        self.queue
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_empty()
     }
 
     /// Get and remove the next item without needing mutable access.
     #[must_use]
     pub fn pop_front(&self) -> Option<T> {
-        self.queue.write().unwrap().pop_front()
+        self.queue
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front()
     }
 }
 
 impl<A> Extend<A> for ExtendableQueue<A> {
     fn extend<T: IntoIterator<Item = A>>(&mut self, iter: T) {
-        self.queue.write().unwrap().extend(iter);
+        self.queue
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(iter);
     }
 }
 
@@ -82,7 +100,10 @@ impl<T> Iterator for ExtendableQueue<T> {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.queue.write().unwrap().pop_front()
+        self.queue
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front()
     }
 }
 
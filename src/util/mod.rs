@@ -2,7 +2,9 @@
 
 pub mod compile;
 mod extendable_queue;
-pub(crate) mod pack_format;
+pub mod pack_format;
+pub mod selector;
+pub(crate) mod tokenize;
 
 #[doc(inline)]
 pub use extendable_queue::ExtendableQueue;
@@ -1,3 +1,38 @@
+//! Information about data pack formats and the Minecraft versions using them.
+
+/// Pack formats at which directories inside of the data folder were renamed.
+///
+/// - 43: Tag directories were changed to singular names.
+/// - 45: Function and resource directories were changed to singular names.
+pub const DIRECTORY_RENAME_FORMATS: [u8; 2] = [43, 45];
+
+/// Data pack formats of full releases and the Minecraft versions using them.
+const MINECRAFT_VERSIONS: &[(u8, &[&str])] = &[
+    (
+        4,
+        &[
+            "1.13", "1.13.1", "1.13.2", "1.14", "1.14.1", "1.14.2", "1.14.3", "1.14.4",
+        ],
+    ),
+    (5, &["1.15", "1.15.1", "1.15.2", "1.16", "1.16.1"]),
+    (6, &["1.16.2", "1.16.3", "1.16.4", "1.16.5"]),
+    (7, &["1.17", "1.17.1"]),
+    (8, &["1.18", "1.18.1"]),
+    (9, &["1.18.2"]),
+    (10, &["1.19", "1.19.1", "1.19.2", "1.19.3"]),
+    (12, &["1.19.4"]),
+    (15, &["1.20", "1.20.1"]),
+    (18, &["1.20.2"]),
+    (26, &["1.20.3", "1.20.4"]),
+    (41, &["1.20.5", "1.20.6"]),
+    (48, &["1.21", "1.21.1"]),
+    (57, &["1.21.2", "1.21.3"]),
+    (61, &["1.21.4"]),
+    (71, &["1.21.5"]),
+    (80, &["1.21.6"]),
+    (81, &["1.21.7", "1.21.8"]),
+];
+
 /// Get the name of the function directory depending on the pack format.
 #[must_use]
 pub const fn function_directory_name(pack_format: u8) -> &'static str {
@@ -7,3 +42,69 @@ pub const fn function_directory_name(pack_format: u8) -> &'static str {
         "function"
     }
 }
+
+/// Get the name of the directory of a resource like `predicate` or `loot_table` depending on the pack format.
+///
+/// The singular name is used from pack format 45 on, before the name is pluralized with an `s`.
+#[must_use]
+pub fn resource_directory_name(singular: &str, pack_format: u8) -> String {
+    if pack_format < 45 {
+        format!("{singular}s")
+    } else {
+        singular.to_string()
+    }
+}
+
+/// Get the Minecraft versions using the given pack format.
+///
+/// Returns an empty slice if no full release uses the pack format, e.g. for snapshot formats.
+#[must_use]
+pub fn minecraft_versions(pack_format: u8) -> &'static [&'static str] {
+    MINECRAFT_VERSIONS
+        .iter()
+        .find(|(format, _)| *format == pack_format)
+        .map_or(&[], |(_, versions)| versions)
+}
+
+/// Get the pack format used by the given Minecraft version, e.g. `"1.21"`.
+#[must_use]
+pub fn from_minecraft_version(version: &str) -> Option<u8> {
+    MINECRAFT_VERSIONS
+        .iter()
+        .find(|(_, versions)| versions.contains(&version))
+        .map(|(format, _)| *format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minecraft_versions() {
+        assert_eq!(minecraft_versions(48), &["1.21", "1.21.1"]);
+        assert_eq!(minecraft_versions(41), &["1.20.5", "1.20.6"]);
+        assert_eq!(minecraft_versions(15), &["1.20", "1.20.1"]);
+        assert_eq!(minecraft_versions(12), &["1.19.4"]);
+        assert!(minecraft_versions(11).is_empty());
+    }
+
+    #[test]
+    fn test_from_minecraft_version() {
+        assert_eq!(from_minecraft_version("1.21"), Some(48));
+        assert_eq!(from_minecraft_version("1.21.1"), Some(48));
+        assert_eq!(from_minecraft_version("1.20.6"), Some(41));
+        assert_eq!(from_minecraft_version("1.20"), Some(15));
+        assert_eq!(from_minecraft_version("1.13"), Some(4));
+        assert_eq!(from_minecraft_version("1.12.2"), None);
+        assert_eq!(from_minecraft_version("foo"), None);
+    }
+
+    #[test]
+    fn test_table_consistent() {
+        for (format, versions) in MINECRAFT_VERSIONS {
+            for version in *versions {
+                assert_eq!(from_minecraft_version(version), Some(*format));
+            }
+        }
+    }
+}
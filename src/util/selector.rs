@@ -0,0 +1,190 @@
+//! Parsing and validation of target selectors.
+
+use getset::{CopyGetters, Getters};
+
+use super::tokenize::split_top_level;
+
+/// Variables of target selectors, e.g. `a` for `@a`.
+const VARIABLES: &[char] = &['a', 'e', 'n', 'p', 'r', 's'];
+
+/// Argument keys of target selectors.
+const ARGUMENT_KEYS: &[&str] = &[
+    "advancements",
+    "distance",
+    "dx",
+    "dy",
+    "dz",
+    "gamemode",
+    "level",
+    "limit",
+    "name",
+    "nbt",
+    "predicate",
+    "scores",
+    "sort",
+    "tag",
+    "team",
+    "type",
+    "x",
+    "x_rotation",
+    "y",
+    "y_rotation",
+    "z",
+];
+
+/// Target selector like `@e[type=minecraft:pig,limit=1]`.
+///
+/// Only the syntax and the argument keys are validated, not the argument values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Getters, CopyGetters)]
+pub struct Selector {
+    /// Variable of the selector, e.g. `a` for `@a`.
+    #[get_copy = "pub"]
+    variable: char,
+    /// Arguments of the selector as pairs of key and value, values can start with `!` to negate them.
+    #[get = "pub"]
+    arguments: Vec<(String, String)>,
+}
+
+impl Selector {
+    /// Parse a target selector.
+    ///
+    /// # Errors
+    /// - If the selector does not start with `@` followed by a valid variable
+    /// - If the brackets, braces or quotes of the arguments are not closed
+    /// - If an argument has no key, no `=` or an unknown key
+    pub fn parse(selector: &str) -> Result<Self, SelectorError> {
+        let rest = selector
+            .strip_prefix('@')
+            .ok_or_else(|| SelectorError::MissingAt(selector.to_string()))?;
+        let mut chars = rest.chars();
+        let variable = chars
+            .next()
+            .filter(|variable| VARIABLES.contains(variable))
+            .ok_or_else(|| SelectorError::InvalidVariable(selector.to_string()))?;
+        let rest = chars.as_str();
+
+        if rest.is_empty() {
+            return Ok(Self {
+                variable,
+                arguments: Vec::new(),
+            });
+        }
+        let arguments = rest
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .ok_or_else(|| SelectorError::UnclosedArguments(selector.to_string()))?;
+
+        let arguments = split_arguments(arguments)
+            .ok_or_else(|| SelectorError::UnclosedArguments(selector.to_string()))?
+            .into_iter()
+            .map(|argument| {
+                let (key, value) = argument
+                    .split_once('=')
+                    .ok_or_else(|| SelectorError::MissingValue(argument.trim().to_string()))?;
+                let key = key.trim();
+                if !ARGUMENT_KEYS.contains(&key) {
+                    return Err(SelectorError::UnknownArgument(key.to_string()));
+                }
+                Ok((key.to_string(), value.trim().to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            variable,
+            arguments,
+        })
+    }
+}
+
+/// Split the arguments of a selector on the commas that are not nested in braces, brackets or quotes.
+///
+/// Returns `None` if a brace, bracket or quote is not closed.
+fn split_arguments(arguments: &str) -> Option<Vec<&str>> {
+    let mut parts = split_top_level(arguments, |c| c == ',')?
+        .into_iter()
+        .map(|range| &arguments[range])
+        .collect::<Vec<_>>();
+    // a trailing comma or empty arguments are allowed
+    if parts.last().is_some_and(|last| last.trim().is_empty()) {
+        parts.pop();
+    }
+    Some(parts)
+}
+
+/// Error of parsing a target selector with [`Selector::parse`].
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum SelectorError {
+    /// The selector does not start with `@`.
+    #[error("selector `{0}` does not start with `@`")]
+    MissingAt(String),
+    /// The variable of the selector is unknown.
+    #[error("selector `{0}` has an unknown variable")]
+    InvalidVariable(String),
+    /// The brackets, braces or quotes of the arguments are not closed.
+    #[error("arguments of selector `{0}` are not closed")]
+    UnclosedArguments(String),
+    /// An argument has no `=` separating the key and value.
+    #[error("selector argument `{0}` has no value")]
+    MissingValue(String),
+    /// An argument has an unknown key.
+    #[error("unknown selector argument `{0}`")]
+    UnknownArgument(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_selectors() {
+        let selector = Selector::parse("@a").expect("failed to parse selector");
+        assert_eq!(selector.variable(), 'a');
+        assert!(selector.arguments().is_empty());
+
+        let selector = Selector::parse("@e[type=!minecraft:pig, distance=..5,limit=1]")
+            .expect("failed to parse selector");
+        assert_eq!(selector.variable(), 'e');
+        assert_eq!(
+            selector.arguments(),
+            &vec![
+                ("type".to_string(), "!minecraft:pig".to_string()),
+                ("distance".to_string(), "..5".to_string()),
+                ("limit".to_string(), "1".to_string()),
+            ]
+        );
+
+        let selector = Selector::parse(r#"@s[scores={foo=1,bar=..2},nbt={Tags:["a,b"]},]"#)
+            .expect("failed to parse selector");
+        assert_eq!(selector.arguments().len(), 2);
+        assert!(Selector::parse("@p[]").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_selectors() {
+        assert!(matches!(
+            Selector::parse("Steve"),
+            Err(SelectorError::MissingAt(_))
+        ));
+        assert!(matches!(
+            Selector::parse("@x"),
+            Err(SelectorError::InvalidVariable(_))
+        ));
+        assert!(matches!(
+            Selector::parse("@e[type=pig"),
+            Err(SelectorError::UnclosedArguments(_))
+        ));
+        assert!(matches!(
+            Selector::parse("@e[scores={foo=1]"),
+            Err(SelectorError::UnclosedArguments(_))
+        ));
+        assert!(matches!(
+            Selector::parse("@e[limit]"),
+            Err(SelectorError::MissingValue(_))
+        ));
+        assert_eq!(
+            Selector::parse("@e[typ=pig]"),
+            Err(SelectorError::UnknownArgument("typ".to_string()))
+        );
+    }
+}
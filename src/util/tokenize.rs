@@ -0,0 +1,87 @@
+//! Splitting of command arguments that may contain NBT, JSON or selectors.
+
+use std::ops::Range;
+
+/// Split the string on the separators that are not nested in braces, brackets or quotes,
+/// returning the byte ranges of all parts, including empty ones.
+///
+/// Returns `None` if a brace, bracket or quote is not closed.
+pub fn split_top_level(s: &str, is_separator: impl Fn(char) -> bool) -> Option<Vec<Range<usize>>> {
+    let mut parts = Vec::new();
+    let mut closing = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (index, c) in s.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '{' => closing.push('}'),
+            '[' => closing.push(']'),
+            '}' | ']' if closing.pop() != Some(c) => return None,
+            c if closing.is_empty() && is_separator(c) => {
+                parts.push(start..index);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    if quote.is_some() || !closing.is_empty() {
+        return None;
+    }
+    parts.push(start..s.len());
+    Some(parts)
+}
+
+/// Split the string into the byte ranges of its words, separated by whitespace that is not nested in braces, brackets or quotes.
+///
+/// Returns `None` if a brace, bracket or quote is not closed.
+pub fn split_words(s: &str) -> Option<Vec<Range<usize>>> {
+    split_top_level(s, char::is_whitespace)
+        .map(|parts| parts.into_iter().filter(|part| !part.is_empty()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_top_level() {
+        let s = r#"a,{b:1,c:[2,3]},"d,\",e",,f"#;
+        let parts = split_top_level(s, |c| c == ',')
+            .expect("should be closed")
+            .into_iter()
+            .map(|range| &s[range])
+            .collect::<Vec<_>>();
+        assert_eq!(parts, vec!["a", "{b:1,c:[2,3]}", r#""d,\",e""#, "", "f"]);
+
+        assert_eq!(split_top_level("a,{b", |c| c == ','), None);
+        assert_eq!(split_top_level("a,'b", |c| c == ','), None);
+        assert_eq!(split_top_level("a]", |c| c == ','), None);
+    }
+
+    #[test]
+    fn test_split_words() {
+        let s = "  if  entity @e[tag=a b] data {x: 'y z'} ";
+        let words = split_words(s)
+            .expect("should be closed")
+            .into_iter()
+            .map(|range| &s[range])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            words,
+            vec!["if", "entity", "@e[tag=a b]", "data", "{x: 'y z'}"]
+        );
+        assert_eq!(split_words(""), Some(Vec::new()));
+    }
+}
@@ -1,6 +1,10 @@
 //! Virtual file system for creating and manipulating files and folders in memory.
 
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
+
+#[cfg(feature = "fs_access")]
+use getset::CopyGetters;
+use getset::Getters;
 
 #[cfg(feature = "zip")]
 use zip::ZipWriter;
@@ -9,10 +13,14 @@ use zip::ZipWriter;
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct VFolder {
-    folders: HashMap<String, VFolder>,
+    folders: HashMap<String, Self>,
     files: HashMap<String, VFile>,
 }
 impl VFolder {
+    /// Maximum number of files written at the same time by [`VFolder::place_async`].
+    #[cfg(all(feature = "fs_access", feature = "tokio"))]
+    pub const MAX_CONCURRENT_WRITES: usize = 64;
+
     /// Create a new, empty virtual folder.
     #[must_use]
     pub fn new() -> Self {
@@ -38,25 +46,46 @@ impl VFolder {
         self.add_existing_folder(path, Self::new());
     }
     /// Recursively add an existing folder to the folder.
+    ///
+    /// The path is normalized like in [`VFolder::add_file`].
     pub fn add_existing_folder(&mut self, path: &str, folder: Self) {
+        self.insert_folder(&normalize_path_lossy(path), folder);
+    }
+    fn insert_folder(&mut self, path: &str, folder: Self) {
         // extract first folder name and the rest of the path
         let (head, tail) = path
             .split_once('/')
             .map_or((path, None), |(h, t)| (h, (!t.is_empty()).then_some(t)));
         if let Some(tail) = tail {
             if let Some(subfolder) = self.get_folder_mut(head) {
-                subfolder.add_existing_folder(tail, folder);
+                subfolder.insert_folder(tail, folder);
             } else {
                 let mut new_folder = Self::new();
-                new_folder.add_existing_folder(tail, folder);
-                self.add_existing_folder(head, new_folder);
+                new_folder.insert_folder(tail, folder);
+                self.insert_folder(head, new_folder);
             }
         } else {
             self.folders.insert(path.to_string(), folder);
         }
     }
     /// Recursively add a new file to the folder.
+    ///
+    /// Leading, trailing and duplicate slashes as well as `.` segments are ignored.
+    /// `..` segments remove the preceding segment but never leave this folder,
+    /// use [`VFolder::try_add_file`] to reject them instead.
     pub fn add_file(&mut self, path: &str, file: VFile) {
+        self.insert_file(&normalize_path_lossy(path), file);
+    }
+    /// Recursively add a new file to the folder, normalizing the path like [`VFolder::add_file`].
+    ///
+    /// # Errors
+    /// - If the path contains a `..` segment
+    /// - If the path contains no file name
+    pub fn try_add_file(&mut self, path: &str, file: VFile) -> std::io::Result<()> {
+        self.insert_file(&normalize_path(path)?, file);
+        Ok(())
+    }
+    fn insert_file(&mut self, path: &str, file: VFile) {
         // extract first folder name and the rest of the path
         let (head, tail) = path
             .split_once('/')
@@ -64,16 +93,43 @@ impl VFolder {
         if let Some(tail) = tail {
             // if the folder already exists, add the file to it
             if let Some(subfolder) = self.get_folder_mut(head) {
-                subfolder.add_file(tail, file);
+                subfolder.insert_file(tail, file);
             } else {
                 let mut new_folder = Self::new();
-                new_folder.add_file(tail, file);
-                self.add_existing_folder(head, new_folder);
+                new_folder.insert_file(tail, file);
+                self.insert_folder(head, new_folder);
             }
         } else {
             self.files.insert(path.to_string(), file);
         }
     }
+    /// Recursively add a file to the folder, created from the existing file at the path if there is one.
+    ///
+    /// The path is normalized like in [`VFolder::add_file`].
+    pub fn add_file_with<F>(&mut self, path: &str, f: F)
+    where
+        F: FnOnce(Option<VFile>) -> VFile,
+    {
+        self.insert_file_with(&normalize_path_lossy(path), f);
+    }
+    fn insert_file_with<F>(&mut self, path: &str, f: F)
+    where
+        F: FnOnce(Option<VFile>) -> VFile,
+    {
+        // extract first folder name and the rest of the path
+        let (head, tail) = path
+            .split_once('/')
+            .map_or((path, None), |(h, t)| (h, (!t.is_empty()).then_some(t)));
+        if let Some(tail) = tail {
+            self.folders
+                .entry(head.to_string())
+                .or_default()
+                .insert_file_with(tail, f);
+        } else {
+            let existing = self.files.remove(path);
+            self.files.insert(path.to_string(), f(existing));
+        }
+    }
 
     /// Recursively get a subfolder by path.
     #[must_use]
@@ -113,6 +169,22 @@ impl VFolder {
             self.files.get(path)
         }
     }
+    /// Recursively get a file by path, ignoring the case of the folder and file names.
+    ///
+    /// Names matching exactly are preferred, otherwise the lexicographically first name
+    /// matching case-insensitively is used.
+    #[must_use]
+    pub fn get_file_ci(&self, path: &str) -> Option<&VFile> {
+        // extract first folder name and the rest of the path
+        let (head, tail) = path
+            .split_once('/')
+            .map_or((path, None), |(h, t)| (h, (!t.is_empty()).then_some(t)));
+        if let Some(tail) = tail {
+            find_ci(&self.folders, head)?.get_file_ci(tail)
+        } else {
+            find_ci(&self.files, path)
+        }
+    }
     /// Recursively get a mutable file by path.
     pub fn get_file_mut(&mut self, path: &str) -> Option<&mut VFile> {
         // extract first folder name and the rest of the path
@@ -126,6 +198,69 @@ impl VFolder {
         }
     }
 
+    /// Recursively remove a file by path, returning the removed file.
+    ///
+    /// Folders that become empty by removing the file are removed as well.
+    pub fn remove_file(&mut self, path: &str) -> Option<VFile> {
+        // extract first folder name and the rest of the path
+        let (head, tail) = path
+            .split_once('/')
+            .map_or((path, None), |(h, t)| (h, (!t.is_empty()).then_some(t)));
+        if let Some(tail) = tail {
+            let subfolder = self.folders.get_mut(head)?;
+            let removed = subfolder.remove_file(tail)?;
+            if subfolder.is_empty() {
+                self.folders.remove(head);
+            }
+            Some(removed)
+        } else {
+            self.files.remove(path)
+        }
+    }
+    /// Recursively remove a folder by path, returning the removed folder.
+    ///
+    /// Folders that become empty by removing the folder are removed as well.
+    pub fn remove_folder(&mut self, path: &str) -> Option<Self> {
+        // extract first folder name and the rest of the path
+        let (head, tail) = path
+            .split_once('/')
+            .map_or((path, None), |(h, t)| (h, (!t.is_empty()).then_some(t)));
+        if let Some(tail) = tail {
+            let subfolder = self.folders.get_mut(head)?;
+            let removed = subfolder.remove_folder(tail)?;
+            if subfolder.is_empty() {
+                self.folders.remove(head);
+            }
+            Some(removed)
+        } else {
+            self.folders.remove(path)
+        }
+    }
+
+    /// Check whether the folder contains neither files nor folders.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.folders.is_empty()
+    }
+
+    /// Get the number of files in the folder and all subfolders.
+    #[must_use]
+    pub fn file_count(&self) -> usize {
+        self.files.len() + self.folders.values().map(Self::file_count).sum::<usize>()
+    }
+
+    /// Get the total size in bytes of the files in the folder and all subfolders.
+    ///
+    /// Text files are counted with the length of their UTF-8 encoding.
+    #[must_use]
+    pub fn total_size(&self) -> usize {
+        self.files
+            .values()
+            .map(|file| file.as_bytes().len())
+            .sum::<usize>()
+            + self.folders.values().map(Self::total_size).sum::<usize>()
+    }
+
     /// Place the folder and its contents on the file system.
     ///
     /// # Errors
@@ -157,6 +292,169 @@ impl VFolder {
         Ok(())
     }
 
+    /// Place the folder and its contents on the file system asynchronously.
+    ///
+    /// The folders are created first, afterwards up to [`VFolder::MAX_CONCURRENT_WRITES`] files are written concurrently.
+    /// The content of a file is only copied for the task writing it, so at most that many copies exist at a time.
+    /// Must be called from within a tokio runtime.
+    ///
+    /// # Errors
+    /// - If the folder cannot be written
+    #[cfg(all(feature = "fs_access", feature = "tokio"))]
+    pub async fn place_async(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let mut folders = vec![path.to_path_buf()];
+        let mut files = Vec::new();
+        let mut stack = vec![(path.to_path_buf(), self)];
+        while let Some((path, folder)) = stack.pop() {
+            for (name, subfolder) in &folder.folders {
+                folders.push(path.join(name));
+                stack.push((path.join(name), subfolder));
+            }
+            for (name, file) in &folder.files {
+                files.push((path.join(name), file));
+            }
+        }
+
+        for folder in folders {
+            tokio::fs::create_dir_all(folder).await?;
+        }
+
+        let mut writes = tokio::task::JoinSet::new();
+        for (path, file) in files {
+            if writes.len() >= Self::MAX_CONCURRENT_WRITES {
+                if let Some(result) = writes.join_next().await {
+                    result??;
+                }
+            }
+            let data = file.as_bytes().to_vec();
+            writes.spawn(async move { tokio::fs::write(path, data).await });
+        }
+        while let Some(result) = writes.join_next().await {
+            result??;
+        }
+        Ok(())
+    }
+
+    /// Place the folder and its contents on the file system, compressing files with one of the extensions with gzip.
+    ///
+    /// The extensions are given without the leading dot, e.g. `nbt`. Compressed files keep their name.
+    ///
+    /// # Errors
+    /// - If a file cannot be compressed
+    /// - If the folder cannot be written
+    #[cfg(all(feature = "fs_access", feature = "gzip"))]
+    pub fn place_gzipped<P>(&self, path: P, extensions: &[&str]) -> std::io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        use std::fs;
+        let path = path.as_ref();
+
+        fs::create_dir_all(path)?;
+        for (name, folder) in &self.folders {
+            folder.place_gzipped(path.join(name), extensions)?;
+        }
+        for (name, file) in &self.files {
+            let compress = std::path::Path::new(name)
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| extensions.contains(&extension));
+            if compress {
+                fs::write(path.join(name), file.gzip()?.as_bytes())?;
+            } else {
+                fs::write(path.join(name), file.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Place the folder and its contents on the file system, only writing files that changed since the last placement.
+    ///
+    /// The manifest file stores the hashes of all placed files. Files that are listed in the manifest
+    /// but are not part of the folder anymore are deleted, together with directories left empty.
+    /// The manifest is updated afterwards.
+    ///
+    /// # Errors
+    /// - If the manifest cannot be read or is invalid
+    /// - If the manifest lists a path that is absolute, not normalized or contains `..`
+    /// - If a file cannot be written or deleted
+    #[cfg(feature = "fs_access")]
+    pub fn place_incremental<P, M>(&self, path: P, manifest_path: M) -> std::io::Result<PlaceReport>
+    where
+        P: AsRef<std::path::Path>,
+        M: AsRef<std::path::Path>,
+    {
+        use chksum_md5 as md5;
+        use std::{collections::BTreeMap, fs, io};
+
+        let path = path.as_ref();
+        let manifest_path = manifest_path.as_ref();
+
+        // read the hashes of the previous placement
+        let previous = if manifest_path.exists() {
+            let content = fs::read_to_string(manifest_path)?;
+            serde_json::from_str::<HashMap<String, String>>(&content)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+        } else {
+            HashMap::new()
+        };
+        // only delete inside the target directory, even if the manifest was tampered with
+        if let Some(file_path) = previous.keys().find(|p| !is_manifest_path(p)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid path in manifest: {file_path}"),
+            ));
+        }
+
+        let mut report = PlaceReport::default();
+        let mut current = BTreeMap::new();
+
+        for (file_path, file) in self.flatten() {
+            let hash = md5::hash(file.as_bytes()).to_hex_lowercase();
+            let target = path.join(&file_path);
+
+            if previous.get(&file_path) == Some(&hash) && target.exists() {
+                report.unchanged += 1;
+            } else {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, file.as_bytes())?;
+                report.written += 1;
+            }
+
+            current.insert(file_path, hash);
+        }
+
+        // delete files that are not part of the folder anymore
+        for file_path in previous.keys().filter(|p| !current.contains_key(*p)) {
+            let target = path.join(file_path);
+            match fs::remove_file(&target) {
+                Ok(()) => report.deleted += 1,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+
+            // remove parent directories that are empty now, stopping at the first non-empty one
+            let mut directory = target.parent();
+            while let Some(dir) = directory.filter(|dir| *dir != path && dir.starts_with(path)) {
+                if fs::remove_dir(dir).is_err() {
+                    break;
+                }
+                directory = dir.parent();
+            }
+        }
+
+        let manifest = serde_json::to_string_pretty(&current)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if let Some(parent) = manifest_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(manifest_path, manifest)?;
+
+        Ok(report)
+    }
+
     /// Zip the folder and its contents into a zip archive.
     ///
     /// # Errors
@@ -233,38 +531,175 @@ impl VFolder {
         Ok(())
     }
 
+    /// Read a folder from a zip archive.
+    ///
+    /// Files that are valid UTF-8 are read as [`VFile::Text`], all others as [`VFile::Binary`].
+    /// Directory entries are added as (possibly empty) folders.
+    ///
+    /// # Errors
+    /// - If the zip archive cannot be read
+    /// - If an entry has an unsafe path, e.g. one containing `..`
+    #[cfg(feature = "zip")]
+    pub fn from_zip<R>(reader: R) -> std::io::Result<Self>
+    where
+        R: std::io::Read + std::io::Seek,
+    {
+        use std::io::{self, Read};
+
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let mut folder = Self::new();
+
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            if entry.enclosed_name().is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsafe path in zip archive: {}", entry.name()),
+                ));
+            }
+            let path = entry.name().trim_end_matches('/').to_string();
+            if path.is_empty() {
+                continue;
+            }
+
+            if entry.is_dir() {
+                if folder.get_folder(&path).is_none() {
+                    folder.add_folder(&path);
+                }
+            } else {
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                let file = match String::from_utf8(data) {
+                    Ok(text) => VFile::Text(text),
+                    Err(err) => VFile::Binary(err.into_bytes()),
+                };
+                folder.add_file(&path, file);
+            }
+        }
+
+        Ok(folder)
+    }
+
     /// Flatten the folder and its contents into a list of files with full paths.
+    ///
+    /// The files of a folder are ordered by name and come before the contents of its subfolders,
+    /// so root files like `pack.mcmeta` and `pack.png` are listed before the `data` folder.
     #[must_use]
     pub fn flatten(&self) -> Vec<(String, &VFile)> {
+        self.iter().collect()
+    }
+
+    /// Get all files whose full path matches the glob pattern, in the same order as by [`VFolder::flatten`].
+    ///
+    /// `*` matches any characters except `/`, `?` matches a single character except `/`
+    /// and `**` matches any number of folders.
+    #[must_use]
+    pub fn glob(&self, pattern: &str) -> Vec<(String, &VFile)> {
+        self.iter()
+            .filter(|(path, _)| glob_matches(pattern, path))
+            .collect()
+    }
+
+    /// Iterate over the files of the folder and its subfolders with their full paths.
+    ///
+    /// The files are yielded lazily in the same order as by [`VFolder::flatten`].
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            stack: vec![IterFrame::new(String::new(), self)],
+        }
+    }
+
+    /// Iterate mutably over the files of the folder and its subfolders with their full paths.
+    ///
+    /// The files are yielded lazily in the same order as by [`VFolder::flatten`].
+    #[must_use]
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut {
+            stack: vec![IterMutFrame::new(String::new(), self)],
+        }
+    }
+
+    /// Convert the folder into a flat manifest of all files, sorted by their full paths.
+    #[must_use]
+    pub fn to_manifest(&self) -> Manifest {
         let mut files = self
-            .files
-            .iter()
-            .map(|(k, v)| (k.to_owned(), v))
+            .flatten()
+            .into_iter()
+            .map(|(path, file)| (path, file.clone()))
             .collect::<Vec<_>>();
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-        for (name, folder) in &self.folders {
-            let sub_files = folder
+        Manifest { files }
+    }
+
+    /// Create a folder from a flat manifest of files.
+    #[must_use]
+    pub fn from_manifest(manifest: Manifest) -> Self {
+        let mut folder = Self::new();
+        for (path, file) in manifest.files {
+            folder.add_file(&path, file);
+        }
+        folder
+    }
+
+    /// Check whether two folders contain the same files, skipping all paths matching one of the ignore patterns.
+    ///
+    /// Patterns support `*` and `?` within a path segment and `**` for any number of segments.
+    #[must_use]
+    pub fn eq_ignoring(&self, other: &Self, ignore: &[&str]) -> bool {
+        fn collect<'a>(folder: &'a VFolder, ignore: &[&str]) -> HashMap<String, &'a VFile> {
+            folder
                 .flatten()
                 .into_iter()
-                .map(|(path, file)| (format!("{name}/{path}"), file))
-                .collect::<Vec<_>>();
-            files.extend(sub_files);
+                .filter(|(path, _)| !ignore.iter().any(|pattern| glob_matches(pattern, path)))
+                .collect()
+        }
+
+        collect(self, ignore) == collect(other, ignore)
+    }
+
+    /// Compare the folder with another folder, e.g. a freshly compiled datapack with the previously placed one.
+    ///
+    /// Files only in this folder are listed as added, files only in the other folder as removed
+    /// and files in both folders with different contents as modified. All paths are full paths, sorted.
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> VFolderDiff {
+        let other_files = other.flatten().into_iter().collect::<HashMap<_, _>>();
+
+        let mut diff = VFolderDiff::default();
+        for (path, file) in self.flatten() {
+            match other_files.get(&path) {
+                None => diff.added.push(path),
+                Some(other_file) if other_file.as_bytes() != file.as_bytes() => {
+                    diff.modified.push(path);
+                }
+                Some(_) => {}
+            }
         }
+        diff.removed = other_files
+            .into_keys()
+            .filter(|path| self.get_file(path).is_none())
+            .collect();
 
-        files
+        diff.added.sort();
+        diff.removed.sort();
+        diff.modified.sort();
+        diff
     }
 
     /// Recursively merge another folder into this folder.
     /// Returns a list of paths that were replaced by other.
     pub fn merge(&mut self, other: Self) -> Vec<String> {
-        self._merge(other, "")
+        self.merge_with_prefix(other, "")
     }
 
-    fn _merge(&mut self, other: Self, prefix: &str) -> Vec<String> {
+    fn merge_with_prefix(&mut self, other: Self, prefix: &str) -> Vec<String> {
         let mut replaced = Vec::new();
         for (name, folder) in other.folders {
             if let Some(existing_folder) = self.folders.get_mut(&name) {
-                let replaced_folder = existing_folder._merge(folder, &format!("{prefix}{name}/"));
+                let replaced_folder =
+                    existing_folder.merge_with_prefix(folder, &format!("{prefix}{name}/"));
                 replaced.extend(replaced_folder);
             } else {
                 self.folders.insert(name, folder);
@@ -281,81 +716,391 @@ impl VFolder {
     }
 }
 
+/// Check whether the path read from a manifest is relative, normalized and stays inside the placed folder.
 #[cfg(feature = "fs_access")]
-impl TryFrom<&std::path::Path> for VFolder {
-    type Error = std::io::Error;
+fn is_manifest_path(path: &str) -> bool {
+    normalize_path(path).is_ok_and(|normalized| normalized == path)
+        && std::path::Path::new(path)
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)))
+}
 
-    fn try_from(value: &std::path::Path) -> Result<Self, Self::Error> {
-        use std::{fs, io};
+/// Normalize the path by removing empty and `.` segments.
+///
+/// # Errors
+/// - If the path contains a `..` segment
+/// - If the path contains no segments
+fn normalize_path(path: &str) -> std::io::Result<Cow<'_, str>> {
+    let invalid = |message: &str| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{message}: {path}"),
+        )
+    };
+    if path.split('/').any(|segment| segment == "..") {
+        return Err(invalid("path must not contain `..`"));
+    }
+    let normalized = normalize_path_lossy(path);
+    if normalized.is_empty() {
+        Err(invalid("path must not be empty"))
+    } else {
+        Ok(normalized)
+    }
+}
 
-        let mut root_vfolder = Self::new();
-        let fs_root_folder = fs::read_dir(value)?;
-        for dir_entry in fs_root_folder {
-            let dir_entry = dir_entry?;
-            let path = dir_entry.path();
-            let name = dir_entry.file_name().into_string().ok();
-            if let Some(name) = name {
-                if path.is_dir() {
-                    root_vfolder.add_existing_folder(&name, Self::try_from(path.as_path())?);
-                } else {
-                    let file = VFile::try_from(path.as_path())?;
-                    root_vfolder.add_file(&name, file);
-                }
-            } else {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "Invalid file name",
-                ));
+/// Normalize the path by removing empty and `.` segments and resolving `..` segments,
+/// ignoring those that would leave the root.
+fn normalize_path_lossy(path: &str) -> Cow<'_, str> {
+    if path
+        .split('/')
+        .all(|segment| !matches!(segment, "" | "." | ".."))
+    {
+        return Cow::Borrowed(path);
+    }
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
             }
+            segment => segments.push(segment),
         }
-
-        Ok(root_vfolder)
     }
+    Cow::Owned(segments.join("/"))
 }
 
-/// File representation in virtual file system
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum VFile {
-    /// Text file
-    Text(String),
-    /// Binary file
-    Binary(Vec<u8>),
+/// Get the entry with the name, falling back to the lexicographically first name matching case-insensitively.
+fn find_ci<'a, T>(entries: &'a HashMap<String, T>, name: &str) -> Option<&'a T> {
+    entries.get(name).or_else(|| {
+        let name = name.to_lowercase();
+        entries
+            .iter()
+            .filter(|(key, _)| key.to_lowercase() == name)
+            .min_by_key(|(key, _)| *key)
+            .map(|(_, entry)| entry)
+    })
 }
 
-impl std::io::Read for VFile {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        self.as_bytes().read(buf)
+/// Check whether the path matches the glob pattern.
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", rest)) => (0..=path.len()).any(|i| match_segments(rest, &path[i..])),
+            Some((segment, rest)) => path.split_first().is_some_and(|(head, tail)| {
+                match_segment(segment.as_bytes(), head.as_bytes()) && match_segments(rest, tail)
+            }),
+        }
     }
-
-    fn read_to_string(&mut self, buf: &mut String) -> std::io::Result<usize> {
-        match self {
-            Self::Text(text) => {
-                buf.push_str(text);
-                Ok(text.len())
-            }
-            Self::Binary(data) => {
-                let text =
-                    std::str::from_utf8(data).map_err(|_| std::io::ErrorKind::InvalidData)?;
-                buf.push_str(text);
-                Ok(text.len())
-            }
+    fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some((b'*', rest)) => (0..=name.len()).any(|i| match_segment(rest, &name[i..])),
+            Some((b'?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+            Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
         }
     }
+
+    let pattern = pattern.split('/').collect::<Vec<_>>();
+    let path = path.split('/').collect::<Vec<_>>();
+    match_segments(&pattern, &path)
 }
 
-impl std::io::Write for VFile {
-    fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
+/// Join the path of a folder and the name of an entry.
+fn join_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}/{name}")
     }
+}
 
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        match self {
-            Self::Text(text) => {
-                text.push_str(&String::from_utf8_lossy(buf));
-                Ok(buf.len())
-            }
-            Self::Binary(data) => {
+/// Iterator over the files of a [`VFolder`] and its subfolders, created by [`VFolder::iter`].
+#[derive(Debug)]
+pub struct Iter<'a> {
+    stack: Vec<IterFrame<'a>>,
+}
+
+/// Remaining entries of a folder visited by [`Iter`].
+#[derive(Debug)]
+struct IterFrame<'a> {
+    path: String,
+    files: std::vec::IntoIter<(&'a String, &'a VFile)>,
+    folders: std::vec::IntoIter<(&'a String, &'a VFolder)>,
+}
+
+impl<'a> IterFrame<'a> {
+    fn new(path: String, folder: &'a VFolder) -> Self {
+        let mut files = folder.files.iter().collect::<Vec<_>>();
+        files.sort_by_key(|(name, _)| *name);
+        let mut folders = folder.folders.iter().collect::<Vec<_>>();
+        folders.sort_by_key(|(name, _)| *name);
+        Self {
+            path,
+            files: files.into_iter(),
+            folders: folders.into_iter(),
+        }
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (String, &'a VFile);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            if let Some((name, file)) = frame.files.next() {
+                return Some((join_path(&frame.path, name), file));
+            }
+            if let Some((name, folder)) = frame.folders.next() {
+                let path = join_path(&frame.path, name);
+                self.stack.push(IterFrame::new(path, folder));
+            } else {
+                self.stack.pop();
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a VFolder {
+    type Item = (String, &'a VFile);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Mutable iterator over the files of a [`VFolder`] and its subfolders, created by [`VFolder::iter_mut`].
+#[derive(Debug)]
+pub struct IterMut<'a> {
+    stack: Vec<IterMutFrame<'a>>,
+}
+
+/// Remaining entries of a folder visited by [`IterMut`].
+#[derive(Debug)]
+struct IterMutFrame<'a> {
+    path: String,
+    files: std::vec::IntoIter<(&'a String, &'a mut VFile)>,
+    folders: std::vec::IntoIter<(&'a String, &'a mut VFolder)>,
+}
+
+impl<'a> IterMutFrame<'a> {
+    fn new(path: String, folder: &'a mut VFolder) -> Self {
+        let mut files = folder.files.iter_mut().collect::<Vec<_>>();
+        files.sort_by_key(|(name, _)| *name);
+        let mut folders = folder.folders.iter_mut().collect::<Vec<_>>();
+        folders.sort_by_key(|(name, _)| *name);
+        Self {
+            path,
+            files: files.into_iter(),
+            folders: folders.into_iter(),
+        }
+    }
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (String, &'a mut VFile);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            if let Some((name, file)) = frame.files.next() {
+                return Some((join_path(&frame.path, name), file));
+            }
+            if let Some((name, folder)) = frame.folders.next() {
+                let path = join_path(&frame.path, name);
+                self.stack.push(IterMutFrame::new(path, folder));
+            } else {
+                self.stack.pop();
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a mut VFolder {
+    type Item = (String, &'a mut VFile);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(feature = "fs_access")]
+impl TryFrom<&std::path::Path> for VFolder {
+    type Error = std::io::Error;
+
+    /// Load the folder from the file system, skipping hidden files and other junk files.
+    /// See [`VFolder::default_path_filter`].
+    fn try_from(value: &std::path::Path) -> Result<Self, Self::Error> {
+        Self::from_path_filtered(value, Self::default_path_filter)
+    }
+}
+
+#[cfg(feature = "fs_access")]
+impl VFolder {
+    /// Load the folder from the file system, only including files and folders for which the filter returns `true`.
+    ///
+    /// The filter is called with the path of each entry. Excluded folders are not traversed.
+    ///
+    /// # Errors
+    /// - If the folder or a file cannot be read
+    /// - If a file name is not valid UTF-8
+    pub fn from_path_filtered<P, F>(path: P, filter: F) -> std::io::Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+        F: Fn(&std::path::Path) -> bool,
+    {
+        Self::from_path_filtered_ref(path.as_ref(), &filter)
+    }
+
+    fn from_path_filtered_ref<F>(path: &std::path::Path, filter: &F) -> std::io::Result<Self>
+    where
+        F: Fn(&std::path::Path) -> bool,
+    {
+        use std::{fs, io};
+
+        let mut root_vfolder = Self::new();
+        let fs_root_folder = fs::read_dir(path)?;
+        for dir_entry in fs_root_folder {
+            let dir_entry = dir_entry?;
+            let path = dir_entry.path();
+            if !filter(&path) {
+                continue;
+            }
+            let name = dir_entry.file_name().into_string().ok();
+            if let Some(name) = name {
+                if path.is_dir() {
+                    root_vfolder
+                        .add_existing_folder(&name, Self::from_path_filtered_ref(&path, filter)?);
+                } else {
+                    let file = VFile::try_from(path.as_path())?;
+                    root_vfolder.add_file(&name, file);
+                }
+            } else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Invalid file name",
+                ));
+            }
+        }
+
+        Ok(root_vfolder)
+    }
+
+    /// Filter used when loading a folder with [`TryFrom`].
+    ///
+    /// Excludes hidden entries starting with a dot (like `.git` or `.DS_Store`) as well as
+    /// `Thumbs.db` and `desktop.ini`.
+    #[must_use]
+    pub fn default_path_filter(path: &std::path::Path) -> bool {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_none_or(|name| {
+                !name.starts_with('.') && !matches!(name, "Thumbs.db" | "desktop.ini")
+            })
+    }
+}
+
+/// Report of an incremental placement of a [`VFolder`].
+#[cfg(feature = "fs_access")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, CopyGetters)]
+#[get_copy = "pub"]
+pub struct PlaceReport {
+    /// Number of files that were written.
+    written: usize,
+    /// Number of files that were deleted.
+    deleted: usize,
+    /// Number of files that were unchanged and therefore not written.
+    unchanged: usize,
+}
+
+/// Differences between two folders, created by [`VFolder::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+#[get = "pub"]
+pub struct VFolderDiff {
+    /// Paths of the files that were added.
+    added: Vec<String>,
+    /// Paths of the files that were removed.
+    removed: Vec<String>,
+    /// Paths of the files whose contents changed.
+    modified: Vec<String>,
+}
+
+impl VFolderDiff {
+    /// Check whether the folders contain the same files with the same contents.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Flat representation of a [`VFolder`], listing all files with their full paths.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    files: Vec<(String, VFile)>,
+}
+
+impl Manifest {
+    /// Get all files with their full paths.
+    #[must_use]
+    pub fn files(&self) -> &[(String, VFile)] {
+        &self.files
+    }
+
+    /// Convert the manifest into the list of files with their full paths.
+    #[must_use]
+    pub fn into_files(self) -> Vec<(String, VFile)> {
+        self.files
+    }
+}
+
+/// File representation in virtual file system
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VFile {
+    /// Text file
+    Text(String),
+    /// Binary file
+    Binary(Vec<u8>),
+}
+
+impl std::io::Read for VFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.as_bytes().read(buf)
+    }
+
+    fn read_to_string(&mut self, buf: &mut String) -> std::io::Result<usize> {
+        match self {
+            Self::Text(text) => {
+                buf.push_str(text);
+                Ok(text.len())
+            }
+            Self::Binary(data) => {
+                let text =
+                    std::str::from_utf8(data).map_err(|_| std::io::ErrorKind::InvalidData)?;
+                buf.push_str(text);
+                Ok(text.len())
+            }
+        }
+    }
+}
+
+impl std::io::Write for VFile {
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Text(text) => {
+                text.push_str(&String::from_utf8_lossy(buf));
+                Ok(buf.len())
+            }
+            Self::Binary(data) => {
                 data.extend_from_slice(buf);
                 Ok(buf.len())
             }
@@ -416,14 +1161,91 @@ impl VFile {
             Self::Text(text) => text.as_bytes(),
         }
     }
+
+    /// Read all bytes from the reader into a binary file.
+    ///
+    /// # Errors
+    /// - If the reader fails
+    pub fn from_reader<R>(mut reader: R) -> std::io::Result<Self>
+    where
+        R: std::io::Read,
+    {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(Self::Binary(data))
+    }
+
+    /// Read all bytes from the reader into a text file.
+    ///
+    /// # Errors
+    /// - If the reader fails
+    /// - If the content is not valid UTF-8
+    pub fn from_text_reader<R>(mut reader: R) -> std::io::Result<Self>
+    where
+        R: std::io::Read,
+    {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(Self::Text(text))
+    }
+
+    /// Compress the content of the file with gzip into a binary file.
+    ///
+    /// # Errors
+    /// - If the content cannot be compressed
+    #[cfg(feature = "gzip")]
+    pub fn gzip(&self) -> std::io::Result<Self> {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(self.as_bytes())?;
+        encoder.finish().map(Self::Binary)
+    }
+
+    /// Decompress the gzip compressed content of the file into a binary file.
+    ///
+    /// # Errors
+    /// - If the content is not valid gzip data
+    #[cfg(feature = "gzip")]
+    pub fn gunzip(&self) -> std::io::Result<Self> {
+        use std::io::Read;
+
+        let mut data = Vec::new();
+        flate2::read::GzDecoder::new(self.as_bytes()).read_to_end(&mut data)?;
+        Ok(Self::Binary(data))
+    }
+
+    /// Encode the content of the file as base64.
+    #[cfg(feature = "base64")]
+    #[must_use]
+    pub fn to_base64(&self) -> String {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD.encode(self.as_bytes())
+    }
+
+    /// Create a binary file from base64 encoded content.
+    ///
+    /// # Errors
+    /// - If the content is not valid base64
+    #[cfg(feature = "base64")]
+    pub fn from_base64(content: &str) -> Result<Self, base64::DecodeError> {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD
+            .decode(content)
+            .map(Self::Binary)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "fs_access")]
     use std::fs;
 
     use super::*;
 
+    #[cfg(feature = "fs_access")]
     #[test]
     fn test_vfolder() {
         let mut v_folder = VFolder::new();
@@ -464,6 +1286,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_file_normalized() {
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("/foo.txt", VFile::from("foo"));
+        v_folder.add_file("a//b.txt", VFile::from("b"));
+        v_folder.add_file("a/../c.txt", VFile::from("c"));
+        v_folder.add_file("../../d.txt", VFile::from("d"));
+
+        assert_eq!(
+            v_folder.get_file("foo.txt").and_then(VFile::as_text),
+            Some("foo")
+        );
+        assert_eq!(
+            v_folder.get_file("a/b.txt").and_then(VFile::as_text),
+            Some("b")
+        );
+        assert_eq!(
+            v_folder.get_file("c.txt").and_then(VFile::as_text),
+            Some("c")
+        );
+        assert_eq!(
+            v_folder.get_file("d.txt").and_then(VFile::as_text),
+            Some("d")
+        );
+        assert!(v_folder.get_folder("").is_none());
+        assert!(v_folder.get_folder("..").is_none());
+
+        let mut v_folder = VFolder::new();
+        assert!(v_folder
+            .try_add_file("/foo.txt", VFile::from("foo"))
+            .is_ok());
+        assert!(v_folder.try_add_file("a//b.txt", VFile::from("b")).is_ok());
+        assert_eq!(
+            v_folder
+                .try_add_file("a/../b.txt", VFile::from("b"))
+                .map_err(|err| err.kind()),
+            Err(std::io::ErrorKind::InvalidInput)
+        );
+        assert!(v_folder.try_add_file("/", VFile::from("")).is_err());
+        assert_eq!(v_folder.file_count(), 2);
+        assert!(v_folder.get_file("a/b.txt").is_some());
+    }
+
     #[test]
     fn test_flatten() {
         let mut v_folder = VFolder::new();
@@ -478,6 +1343,168 @@ mod tests {
         assert!(flattened.iter().any(|(path, _)| path == "a/b/c.txt"));
     }
 
+    #[test]
+    fn test_add_file_with() {
+        let mut v_folder = VFolder::new();
+        let append = |existing: Option<VFile>| {
+            let mut text = existing
+                .and_then(|file| file.as_text().map(str::to_string))
+                .unwrap_or_default();
+            text.push_str("say hi\n");
+            VFile::Text(text)
+        };
+
+        v_folder.add_file_with("a/b.mcfunction", append);
+        v_folder.add_file_with("a/b.mcfunction", append);
+
+        assert_eq!(
+            v_folder.get_file("a/b.mcfunction"),
+            Some(&VFile::from("say hi\nsay hi\n"))
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("a/b/c/d.txt", VFile::from("d"));
+        v_folder.add_file("a/b/e.txt", VFile::from("e"));
+        v_folder.add_file("a/f/g.txt", VFile::from("g"));
+        v_folder.add_file("h.txt", VFile::from("h"));
+
+        assert_eq!(v_folder.remove_file("a/b/c/d.txt"), Some(VFile::from("d")));
+        assert!(v_folder.get_folder("a/b/c").is_none());
+        assert!(v_folder.get_file("a/b/e.txt").is_some());
+        assert_eq!(v_folder.remove_file("a/b/c/d.txt"), None);
+        assert_eq!(v_folder.remove_file("x/y.txt"), None);
+
+        let removed = v_folder.remove_folder("a/b").expect("folder not removed");
+        assert!(removed.get_file("e.txt").is_some());
+        assert!(v_folder.get_folder("a/b").is_none());
+        assert!(v_folder.get_file("a/f/g.txt").is_some());
+
+        assert!(v_folder.remove_folder("a/f").is_some());
+        assert!(v_folder.get_folder("a").is_none());
+        assert!(!v_folder.is_empty());
+        assert_eq!(v_folder.remove_file("h.txt"), Some(VFile::from("h")));
+        assert!(v_folder.is_empty());
+    }
+
+    #[test]
+    fn test_flatten_order() {
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("data/foo/function/bar.mcfunction", VFile::from("say bar"));
+        v_folder.add_file("pack.png", VFile::Binary(vec![1]));
+        v_folder.add_file("data/a.txt", VFile::from("a"));
+        v_folder.add_file("pack.mcmeta", VFile::from("{}"));
+
+        let paths = v_folder
+            .flatten()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "pack.mcmeta",
+                "pack.png",
+                "data/a.txt",
+                "data/foo/function/bar.mcfunction"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("data/foo/function/bar.mcfunction", VFile::from("say bar"));
+        v_folder.add_file("data/foo/big.bin", VFile::Binary(vec![0; 64]));
+        v_folder.add_file("pack.mcmeta", VFile::from("{}"));
+
+        assert_eq!(v_folder.iter().collect::<Vec<_>>(), v_folder.flatten());
+        assert!(VFolder::new().iter().next().is_none());
+
+        let big = v_folder.iter().find(|(_, file)| match file {
+            VFile::Binary(data) => data.len() > 32,
+            VFile::Text(text) => text.len() > 32,
+        });
+        assert_eq!(
+            big.map(|(path, _)| path).as_deref(),
+            Some("data/foo/big.bin")
+        );
+
+        for (path, file) in &mut v_folder {
+            if path.ends_with(".mcfunction") {
+                *file = VFile::from("say baz");
+            }
+        }
+        assert_eq!(
+            v_folder
+                .get_file("data/foo/function/bar.mcfunction")
+                .and_then(VFile::as_text),
+            Some("say baz")
+        );
+        assert_eq!((&v_folder).into_iter().count(), 3);
+    }
+
+    #[test]
+    #[cfg(all(feature = "fs_access", feature = "zip"))]
+    fn test_zip_round_trip() {
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("pack.mcmeta", VFile::from("{}"));
+        v_folder.add_file("pack.png", VFile::Binary(vec![0x89, 0x50, 0xff]));
+        v_folder.add_file("data/foo/function/bar.mcfunction", VFile::from("say bar"));
+        v_folder.add_file("data/foo/function/nested/baz.mcfunction", VFile::from(""));
+
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+        let path = temp.path().join("pack.zip");
+        v_folder.zip(&path).expect("failed to zip folder");
+
+        let file = std::fs::File::open(&path).expect("failed to open zip");
+        let read = VFolder::from_zip(file).expect("failed to read zip");
+        assert_eq!(read, v_folder);
+    }
+
+    #[test]
+    #[cfg(feature = "zip")]
+    fn test_from_zip_directories() {
+        use std::io::{Cursor, Write};
+
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        writer.add_directory("data/", options).unwrap();
+        writer.add_directory("data/empty/", options).unwrap();
+        writer.start_file("data/foo/a.txt", options).unwrap();
+        writer.write_all(b"a").unwrap();
+        let buffer = writer.finish().unwrap();
+
+        let read = VFolder::from_zip(buffer).expect("failed to read zip");
+        assert!(read.get_folder("data/empty").is_some_and(VFolder::is_empty));
+        assert_eq!(
+            read.get_file("data/foo/a.txt").and_then(VFile::as_text),
+            Some("a")
+        );
+
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file("../evil.txt", options).unwrap();
+        let buffer = writer.finish().unwrap();
+        assert!(VFolder::from_zip(buffer).is_err());
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut v_folder = VFolder::new();
+        assert_eq!((v_folder.file_count(), v_folder.total_size()), (0, 0));
+
+        v_folder.add_file("a.txt", VFile::from("abc"));
+        v_folder.add_file("b/c.txt", VFile::from("äö"));
+        v_folder.add_file("b/d/e.bin", VFile::Binary(vec![0; 10]));
+        v_folder.add_folder("f");
+
+        assert_eq!(v_folder.file_count(), 3);
+        assert_eq!(v_folder.total_size(), 3 + 4 + 10);
+        assert_eq!(v_folder.get_folder("b").unwrap().total_size(), 14);
+    }
+
     #[test]
     fn test_merge() {
         let mut first = VFolder::new();
@@ -500,6 +1527,291 @@ mod tests {
         assert!(first.get_file("a/e.txt").is_some());
     }
 
+    #[cfg(feature = "fs_access")]
+    #[test]
+    fn test_place_incremental() {
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+        let target = temp.path().join("pack");
+        let manifest = temp.path().join("manifest.json");
+
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("a.txt", VFile::from("a"));
+        v_folder.add_file("b/c.txt", VFile::from("c"));
+
+        let report = v_folder
+            .place_incremental(&target, &manifest)
+            .expect("failed to place folder");
+        assert_eq!(
+            (report.written(), report.deleted(), report.unchanged()),
+            (2, 0, 0)
+        );
+
+        v_folder.add_file("a.txt", VFile::from("a2"));
+        v_folder.add_file("d.txt", VFile::from("d"));
+        v_folder.get_folder_mut("b").unwrap().files.remove("c.txt");
+
+        let report = v_folder
+            .place_incremental(&target, &manifest)
+            .expect("failed to place folder");
+        assert_eq!(
+            (report.written(), report.deleted(), report.unchanged()),
+            (2, 1, 0)
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("a.txt")).expect("failed to read file"),
+            "a2"
+        );
+        assert!(target.join("d.txt").exists());
+        assert!(!target.join("b/c.txt").exists());
+        assert!(!target.join("b").exists());
+
+        let report = v_folder
+            .place_incremental(&target, &manifest)
+            .expect("failed to place folder");
+        assert_eq!(
+            (report.written(), report.deleted(), report.unchanged()),
+            (0, 0, 2)
+        );
+
+        // paths outside of the target directory are never deleted
+        let outside = temp.path().join("outside.txt");
+        for invalid in ["../outside.txt", "/outside.txt", "./a.txt", "b//c.txt"] {
+            fs::write(&outside, "outside").expect("failed to write file");
+            fs::write(&manifest, format!(r#"{{"{invalid}":"0"}}"#))
+                .expect("failed to write manifest");
+            let err = v_folder
+                .place_incremental(&target, &manifest)
+                .expect_err("manifest should be rejected");
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            assert!(outside.exists());
+        }
+    }
+
+    #[test]
+    fn test_manifest() {
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("b.txt", VFile::from("b"));
+        v_folder.add_file("a/c.txt", VFile::from("c"));
+        v_folder.add_file("a/b/d.bin", VFile::Binary(vec![1, 2, 3]));
+
+        let manifest = v_folder.to_manifest();
+        let paths = manifest
+            .files()
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["a/b/d.bin", "a/c.txt", "b.txt"]);
+
+        assert_eq!(VFolder::from_manifest(manifest), v_folder);
+    }
+
+    #[test]
+    fn test_eq_ignoring() {
+        let mut first = VFolder::new();
+        first.add_file("pack.mcmeta", VFile::from("first"));
+        first.add_file("data/foo/function/a.mcfunction", VFile::from("say a"));
+        first.add_file(
+            "data/foo/function/sb/a/1234.mcfunction",
+            VFile::from("say 1"),
+        );
+
+        let mut second = VFolder::new();
+        second.add_file("pack.mcmeta", VFile::from("second"));
+        second.add_file("data/foo/function/a.mcfunction", VFile::from("say a"));
+        second.add_file(
+            "data/foo/function/sb/a/5678.mcfunction",
+            VFile::from("say 1"),
+        );
+
+        assert_ne!(first, second);
+        assert!(!first.eq_ignoring(&second, &["pack.mcmeta"]));
+        assert!(first.eq_ignoring(&second, &["pack.mcmeta", "data/*/function/sb/**"]));
+        assert!(first.eq_ignoring(&second, &["*.mcmeta", "**/????.mcfunction"]));
+        assert!(!first.eq_ignoring(&second, &["*.mcmeta", "data/*/sb/**"]));
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut old = VFolder::new();
+        old.add_file("pack.mcmeta", VFile::from("meta"));
+        old.add_file("data/foo/function/a.mcfunction", VFile::from("say a"));
+        old.add_file("data/foo/function/b.mcfunction", VFile::from("say b"));
+
+        let mut new = old.clone();
+        assert!(new.diff(&old).is_empty());
+
+        new.remove_file("data/foo/function/a.mcfunction");
+        new.add_file("data/foo/function/b.mcfunction", VFile::from("say c"));
+        new.add_file("data/bar/function/c.mcfunction", VFile::from("say c"));
+        new.add_file("pack.mcmeta", VFile::Binary(b"meta".to_vec()));
+
+        let diff = new.diff(&old);
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.added(),
+            &vec!["data/bar/function/c.mcfunction".to_string()]
+        );
+        assert_eq!(
+            diff.removed(),
+            &vec!["data/foo/function/a.mcfunction".to_string()]
+        );
+        assert_eq!(
+            diff.modified(),
+            &vec!["data/foo/function/b.mcfunction".to_string()]
+        );
+
+        let reverse = old.diff(&new);
+        assert_eq!(reverse.added(), diff.removed());
+        assert_eq!(reverse.removed(), diff.added());
+        assert_eq!(reverse.modified(), diff.modified());
+    }
+
+    #[test]
+    fn test_from_reader() {
+        use std::io::Cursor;
+
+        assert_eq!(
+            VFile::from_reader(Cursor::new(vec![0, 159, 146, 150])).unwrap(),
+            VFile::Binary(vec![0, 159, 146, 150])
+        );
+        assert_eq!(
+            VFile::from_text_reader(Cursor::new("foo")).unwrap(),
+            VFile::Text("foo".to_string())
+        );
+        assert!(VFile::from_text_reader(Cursor::new(vec![0, 159, 146, 150])).is_err());
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64() {
+        assert_eq!(VFile::from("foo").to_base64(), "Zm9v");
+        assert_eq!(
+            VFile::from_base64("Zm9v"),
+            Ok(VFile::Binary(b"foo".to_vec()))
+        );
+        assert!(VFile::from_base64("Zm9v!").is_err());
+    }
+
+    #[cfg(all(feature = "fs_access", feature = "tokio"))]
+    #[tokio::test]
+    async fn test_place_async() {
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("a.txt", VFile::from("a"));
+        v_folder.add_file("b/c/d.txt", VFile::from("d"));
+        v_folder.add_folder("e");
+        for i in 0..100 {
+            v_folder.add_file(&format!("f/{i}.txt"), VFile::from(i.to_string()));
+        }
+        v_folder
+            .place_async(temp.path())
+            .await
+            .expect("failed to place folder");
+
+        assert_eq!(
+            fs::read_to_string(temp.path().join("b/c/d.txt")).expect("failed to read file"),
+            "d"
+        );
+        assert!(temp.path().join("a.txt").is_file());
+        assert!(temp.path().join("e").is_dir());
+        assert_eq!(fs::read_dir(temp.path().join("f")).unwrap().count(), 100);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip() {
+        let file = VFile::from("structure data");
+        let compressed = file.gzip().expect("failed to compress");
+        assert!(compressed.as_bytes().starts_with(&[0x1f, 0x8b]));
+        assert_eq!(
+            compressed.gunzip().expect("failed to decompress"),
+            VFile::from(b"structure data".as_slice())
+        );
+        assert!(file.gunzip().is_err());
+    }
+
+    #[cfg(all(feature = "fs_access", feature = "gzip"))]
+    #[test]
+    fn test_place_gzipped() {
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("a/house.nbt", VFile::from("house"));
+        v_folder.add_file("a/readme.txt", VFile::from("readme"));
+        v_folder
+            .place_gzipped(temp.path(), &["nbt"])
+            .expect("failed to place folder");
+
+        let house = VFile::try_from(temp.path().join("a/house.nbt").as_path())
+            .expect("failed to read file");
+        assert_eq!(house.gunzip().unwrap().as_bytes(), b"house");
+        assert_eq!(
+            fs::read_to_string(temp.path().join("a/readme.txt")).expect("failed to read file"),
+            "readme"
+        );
+    }
+
+    #[test]
+    fn test_get_file_ci() {
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("Data/Foo/bar.TXT", VFile::from("upper"));
+        v_folder.add_file("data/foo/bar.txt", VFile::from("lower"));
+
+        assert_eq!(
+            v_folder
+                .get_file_ci("DATA/foo/BAR.txt")
+                .and_then(VFile::as_text),
+            Some("upper")
+        );
+        assert_eq!(
+            v_folder
+                .get_file_ci("data/foo/bar.txt")
+                .and_then(VFile::as_text),
+            Some("lower")
+        );
+        assert!(v_folder.get_file("DATA/foo/BAR.txt").is_none());
+        assert!(v_folder.get_file_ci("data/foo/baz.txt").is_none());
+    }
+
+    #[test]
+    fn test_glob() {
+        let mut v_folder = VFolder::new();
+        v_folder.add_file("pack.mcmeta", VFile::from("{}"));
+        v_folder.add_file("data/foo/function/a.mcfunction", VFile::from("a"));
+        v_folder.add_file("data/foo/function/sub/b.mcfunction", VFile::from("b"));
+        v_folder.add_file("data/foo/tags/function/c.json", VFile::from("c"));
+
+        let paths = v_folder
+            .glob("data/**/*.mcfunction")
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "data/foo/function/a.mcfunction".to_string(),
+                "data/foo/function/sub/b.mcfunction".to_string(),
+            ]
+        );
+        assert_eq!(v_folder.glob("*.mcmeta").len(), 1);
+        assert!(v_folder.glob("*.json").is_empty());
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("a.txt", "a.txt"));
+        assert!(!glob_matches("a.txt", "b/a.txt"));
+        assert!(glob_matches("*.txt", "a.txt"));
+        assert!(!glob_matches("*.txt", "b/a.txt"));
+        assert!(glob_matches("**/*.txt", "a.txt"));
+        assert!(glob_matches("**/*.txt", "b/c/a.txt"));
+        assert!(glob_matches("b/**", "b/c/a.txt"));
+        assert!(glob_matches("b/?/a.txt", "b/c/a.txt"));
+        assert!(!glob_matches("b/?/a.txt", "b/cd/a.txt"));
+    }
+
+    #[cfg(feature = "fs_access")]
     #[test]
     fn test_try_from() {
         let temp_dir = tempfile::tempdir().expect("failed to create temp dir");
@@ -521,4 +1833,33 @@ mod tests {
             panic!("File is not binary");
         }
     }
+
+    #[cfg(feature = "fs_access")]
+    #[test]
+    fn test_from_path_filtered() {
+        let temp_dir = tempfile::tempdir().expect("failed to create temp dir");
+        fs::create_dir_all(temp_dir.path().join(".git")).expect("failed to create dir");
+        fs::create_dir_all(temp_dir.path().join("bar")).expect("failed to create dir");
+        fs::write(temp_dir.path().join(".git/HEAD"), "ref").expect("failed to write file");
+        fs::write(temp_dir.path().join(".DS_Store"), "").expect("failed to write file");
+        fs::write(temp_dir.path().join("foo.txt"), "foo").expect("failed to write file");
+        fs::write(temp_dir.path().join("bar/baz.txt"), "baz").expect("failed to write file");
+
+        let v_folder = VFolder::try_from(temp_dir.path()).expect("failed to convert");
+        let mut paths = v_folder
+            .flatten()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(paths, vec!["bar/baz.txt", "foo.txt"]);
+
+        let v_folder = VFolder::from_path_filtered(temp_dir.path(), |path| {
+            path.extension().is_none_or(|ext| ext != "txt")
+        })
+        .expect("failed to convert");
+        assert_eq!(v_folder.flatten().len(), 2);
+        assert!(v_folder.get_file(".git/HEAD").is_some());
+        assert!(v_folder.get_file(".DS_Store").is_some());
+    }
 }